@@ -0,0 +1,35 @@
+//! Compares the latency of clicking with the default `click_button`
+//! implementation (a separate `press_button` and `release_button` call)
+//! against the native `click_button` implementation of the current backend
+//!
+//! This benchmark sends real clicks to the current cursor position, run it with;
+//! ```fish
+//! cargo bench --bench click
+//! ```
+use mouce::common::MouseButton;
+use mouce::Mouse;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 100;
+
+fn measure(mut click: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        click();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let manager = Mouse::new();
+    let button = MouseButton::Left;
+
+    let default = measure(|| {
+        manager.press_button(&button).unwrap();
+        manager.release_button(&button).unwrap();
+    });
+    let native = measure(|| manager.click_button(&button).unwrap());
+
+    println!("default click_button: {:?} per click", default);
+    println!("native click_button:  {:?} per click", native);
+}
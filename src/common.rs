@@ -1,15 +1,47 @@
 use crate::error::Error;
+use crate::macros::{self, MacroLibrary};
+use crate::rng::Rng;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign, Deref, DerefMut};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub type CallbackId = u8;
+pub type MouseEventFn = Box<dyn Fn(&MouseEvent) + Send>;
+pub type MouseEventOnceFn = Box<dyn FnOnce(&MouseEvent) + Send>;
+pub type CallbackPanicFn = Box<dyn Fn(&str) + Send>;
+pub type ReactiveEventFn = Box<dyn Fn(&MouseEvent, &dyn MouseActions) + Send>;
+pub type MouseEventContextFn = Box<dyn Fn(&MouseEvent, &EventContext) + Send>;
+pub type MouseEventFilterFn = Box<dyn Fn(&MouseEvent) -> bool + Send>;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
 }
 
-#[derive(Debug, Copy, Clone)]
+/// A modifier key, see `MouseActions::hold_modifiers`
+///
+/// `Meta` is the Command key on MacOS, the Windows key on Windows and
+/// the Super key on X11
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    Meta,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ScrollDirection {
     Up,
     Down,
@@ -17,355 +49,7696 @@ pub enum ScrollDirection {
     Left,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MouseEvent {
     RelativeMove(i32, i32),
     AbsoluteMove(i32, i32),
     Press(MouseButton),
     Release(MouseButton),
     Scroll(ScrollDirection),
-}
-
-pub trait MouseActions {
-    /// Move the mouse to the given `x`, `y` coordinates
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.move_to(0, 0), Ok(()));
-    /// ```
-    fn move_to(&self, x: usize, y: usize) -> Result<(), Error>;
-    /// Move the mouse relative to the current position
-    ///
-    /// # Examples
+    /// A scroll of multiple wheel clicks or on both of the axes at once,
+    /// positive `dx` scrolls right and positive `dy` scrolls up
     ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
+    /// The listeners report a single click on a single axis as `Scroll`. A
+    /// scroll that moves multiple notches in one event keeps its magnitude
+    /// here, e.g. a fast spin of the wheel on Windows, MacOS and uinput. Only
+    /// MacOS reports a diagonal scroll as a single event
+    ScrollDelta(i32, i32),
+    /// A rotation of a dial in detents, positive is clockwise, e.g. of the
+    /// thumb wheels and the rotary controllers that report `REL_DIAL`
     ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.move_relative(100, 100), Ok(()));
-    /// ```
-    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
-        let (x, y) = self.get_position()?;
-        self.move_to((x + x_offset) as usize, (y + y_offset) as usize)
+    /// Only the Linux listener reports this, it can not be sent
+    Dial(i32),
+}
+
+/// A set of the kinds of `MouseEvent`s, the kinds are combined with `|`
+///
+/// `MOVE` covers both the relative and the absolute moves
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EventKindSet(u8);
+
+impl EventKindSet {
+    pub const MOVE: EventKindSet = EventKindSet(1);
+    pub const PRESS: EventKindSet = EventKindSet(1 << 1);
+    pub const RELEASE: EventKindSet = EventKindSet(1 << 2);
+    pub const SCROLL: EventKindSet = EventKindSet(1 << 3);
+    pub const ALL: EventKindSet = EventKindSet(0b1111);
+
+    /// Check whether all of the kinds of `other` are in this set
+    pub const fn contains(self, other: EventKindSet) -> bool {
+        self.0 & other.0 == other.0
     }
-    /// Get the current position of the mouse
-    ///
-    /// # Examples
-    ///
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::error::Error;
-    ///
-    /// let manager = Mouse::new();
-    /// manager.move_to(0, 0);
-    /// // This function may not be implemented on some platforms such as Linux Wayland
-    /// let valid_outs = vec![Ok((0, 0)), Err(Error::NotImplemented)];
-    /// assert!(valid_outs.contains(&manager.get_position()));
-    /// ```
-    fn get_position(&self) -> Result<(i32, i32), Error>;
-    /// Press down the given mouse button
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
-    ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
-    /// ```
-    fn press_button(&self, button: &MouseButton) -> Result<(), Error>;
-    /// Release the given mouse button
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
-    ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.release_button(&MouseButton::Left), Ok(()));
-    /// ```
-    fn release_button(&self, button: &MouseButton) -> Result<(), Error>;
-    /// Click the given mouse button
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
-    ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
-    /// ```
-    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+
+    /// Check whether the kind of the given event is in this set
+    pub const fn matches(self, event: &MouseEvent) -> bool {
+        let kind = match event {
+            MouseEvent::RelativeMove(..) | MouseEvent::AbsoluteMove(..) => EventKindSet::MOVE,
+            MouseEvent::Press(_) => EventKindSet::PRESS,
+            MouseEvent::Release(_) => EventKindSet::RELEASE,
+            MouseEvent::Scroll(_) | MouseEvent::ScrollDelta(..) | MouseEvent::Dial(_) => {
+                EventKindSet::SCROLL
+            }
+        };
+        self.contains(kind)
     }
-    /// Click the given mouse button with the specified delay for "pressing" and "releasing" the button
+}
+
+impl BitOr for EventKindSet {
+    type Output = EventKindSet;
+
+    fn bitor(self, other: EventKindSet) -> EventKindSet {
+        EventKindSet(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for EventKindSet {
+    fn bitor_assign(&mut self, other: EventKindSet) {
+        self.0 |= other.0;
+    }
+}
+
+/// A monitor and its area in the global coordinate space
+///
+/// All of the coordinates used by mouce are the global coordinates of the
+/// platform. On Windows and MacOS the origin (0, 0) is the top-left corner
+/// of the primary display, so other monitors can have negative coordinates
+/// when they are placed to the left of or above it. On X11 the origin is the
+/// top-left corner of the X screen, which spans all of the monitors, so the
+/// primary monitor does not have to start at (0, 0)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    /// The global x coordinate of the top-left corner of the monitor
+    pub x: i32,
+    /// The global y coordinate of the top-left corner of the monitor
+    pub y: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Monitor {
+    /// Check whether the given global coordinates are on this monitor
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width as i32).contains(&x)
+            && (self.y..self.y + self.height as i32).contains(&y)
+    }
+
+    /// Get the point on this monitor that is the closest to the given global
+    /// coordinates, the coordinates on the monitor are returned as they are
+    pub fn clamp(&self, x: i32, y: i32) -> (i32, i32) {
+        let right = self.x + self.width.max(1) as i32 - 1;
+        let bottom = self.y + self.height.max(1) as i32 - 1;
+        (x.clamp(self.x, right), y.clamp(self.y, bottom))
+    }
+}
+
+/// The different ways of moving the mouse, mirrors the move variants of `MouseEvent`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MoveKind {
+    /// Move to the given `x`, `y` coordinates
+    Absolute(i32, i32),
+    /// Move relative to the current position
+    Relative(i32, i32),
+    /// Move to the given percentages (0.0 to 100.0) of the display size
+    Percent(f32, f32),
+}
+
+/// The shape of the mouse cursor, see `MouseActions::get_cursor_shape`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CursorShape {
+    /// The normal pointer
+    Arrow,
+    /// The busy cursor, also reported for the pointer with a busy indicator
+    Wait,
+    /// The text selection cursor
+    IBeam,
+    /// The pointing hand that is shown over links
+    Hand,
+    /// Any other cursor, e.g. a resize arrow or a custom cursor of an application
+    Unknown,
+}
+
+/// Where (0, 0) is for `move_to` and `get_position`, see
+/// `MouseActions::set_coordinate_origin`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CoordinateOrigin {
+    /// The top-left corner of the primary display, `y` grows downwards. This
+    /// is what all of the platforms use
+    #[default]
+    TopLeft,
+    /// The bottom-left corner of the primary display, `y` grows upwards, like
+    /// the axes of a plot
+    BottomLeft,
+}
+
+/// The scheduling priority of the event listener threads, see
+/// `with_listener_priority` of `MouseActions`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// The priority the threads are created with
+    #[default]
+    Normal,
+    /// A higher priority within the normal scheduling class
+    High,
+    /// A real-time priority, the threads preempt all of the normal ones
+    RealTime,
+}
+
+/// What was going on when a mouse event occurred, see
+/// `MouseActions::hook_with_context`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventContext {
+    /// The identifier of the frontmost application, e.g. `com.apple.Safari`
     ///
-    /// # Examples
+    /// It is only known on MacOS, where it is the bundle identifier, `None`
+    /// elsewhere and for the applications without one
+    pub app_id: Option<String>,
+    /// The kind of the device a `Scroll` or a `ScrollDelta` came from, e.g. to
+    /// smooth the scrolls of a touchpad differently
     ///
-    /// ```rust,no_run
-    /// use std::time::Duration;
-    /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
+    /// It is only known on Linux, `ScrollSource::Unknown` elsewhere and for
+    /// the rest of the events
+    pub scroll_source: ScrollSource,
+}
+
+/// The kind of the device a scroll came from, see `EventContext::scroll_source`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ScrollSource {
+    /// A mouse with a scroll wheel, including the high resolution and the
+    /// tilting wheels
+    Wheel,
+    /// A touchpad, the two-finger scrolls of a touchpad are turned into scroll
+    /// events by the drivers of the display server though, so on Linux only
+    /// the touchpads that emulate a wheel themselves report their scrolls
+    Touchpad,
+    /// The device is not known
+    #[default]
+    Unknown,
+}
+
+/// What was set up to listen for the events of a callback, see
+/// `MouseActions::hook_detailed`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookInfo {
+    /// The id of the hooked callback, to use with `unhook`
+    pub id: CallbackId,
+    /// The name of the backend, see `MouseActions::backend_name`
+    pub backend: &'static str,
+    /// What the listener receives the events from
+    /// - The event files of the mice on Linux, e.g. `/dev/input/event3`
+    /// - The low level mouse hook (`WH_MOUSE_LL`) on Windows
+    /// - The HID event tap (`kCGHIDEventTap`) on MacOS
     ///
-    /// let manager = Mouse::new();
-    /// let press_delay = Duration::from_millis(200); //wait 200ms before pressing the button
-    /// let release_delay = Duration::from_millis(200); // hold the button for 200ms and then release it
-    /// assert_eq!(manager.click_button_delayed(&MouseButton::Left,Some(press_delay),Some(release_delay)), Ok(()));
-    /// ```
-    fn click_button_delayed(
-        &self,
-        button: &MouseButton,
-        press_delay: Option<std::time::Duration>,
-        release_delay: Option<std::time::Duration>,
-    ) -> Result<(), Error> {
-        if let Some(delay) = press_delay {
-            std::thread::sleep(delay);
+    /// It is empty where the backend does not report it
+    pub devices: Vec<String>,
+}
+
+/// The role of a pointer device of XInput2, see `PointerDevice`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerRole {
+    /// A virtual pointer with its own cursor on the screen, it moves with all
+    /// of the slave pointers that are attached to it
+    Master,
+    /// A physical device, e.g. a mouse or a touchpad, that drives the cursor
+    /// of the master pointer it is attached to
+    Slave,
+    /// A physical device that is not attached to any master pointer, it does
+    /// not drive any cursor
+    Floating,
+}
+
+/// A pointer device, see `MouseActions::list_pointers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerDevice {
+    /// The id of the device in the X server
+    pub id: i32,
+    pub name: String,
+    pub role: PointerRole,
+    /// The id of the master pointer a slave pointer is attached to, `None` for
+    /// the master and the floating pointers
+    pub master: Option<i32>,
+}
+
+impl PointerDevice {
+    /// Get the id of the master pointer whose cursor this device drives, `None`
+    /// for a floating pointer
+    pub fn master_pointer(&self) -> Option<i32> {
+        match self.role {
+            PointerRole::Master => Some(self.id),
+            PointerRole::Slave => self.master,
+            PointerRole::Floating => None,
         }
-        self.press_button(button)?;
-        if let Some(delay) = release_delay {
-            std::thread::sleep(delay);
+    }
+}
+
+/// Get the id of the master pointer `MouseActions::set_active_pointer` sends
+/// to for the given device, checking that it is one of the master pointers
+pub(crate) fn active_master(
+    pointers: &[PointerDevice],
+    device: &PointerDevice,
+) -> Result<i32, Error> {
+    let master = device
+        .master_pointer()
+        .ok_or(Error::CustomError("a floating pointer has no cursor"))?;
+    let is_master = pointers
+        .iter()
+        .any(|pointer| pointer.id == master && pointer.role == PointerRole::Master);
+    if !is_master {
+        return Err(Error::CustomError("the master pointer does not exist"));
+    }
+    Ok(master)
+}
+
+/// How far a single `MouseActions::scroll_wheel` call scrolls, see
+/// `MouseActions::scroll_granularity`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScrollGranularity {
+    /// The given number of lines, it can be fractional
+    Lines(f32),
+    /// A whole page
+    Page,
+    /// A single notch of the wheel, the application decides how far it scrolls,
+    /// which is usually 3 lines
+    Notch,
+}
+
+/// How the time between the wheel clicks of a scroll changes, see
+/// `MouseActions::scroll_with_acceleration`
+///
+/// The platforms and many applications scroll further per click when the
+/// clicks come in quickly, so the timing decides how far a scroll goes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccelCurve {
+    /// The same interval between all of the clicks
+    Constant(Duration),
+    /// The interval changes linearly from `first` to `last`
+    Linear { first: Duration, last: Duration },
+    /// The interval eases from `slowest` to `fastest` in the middle of the
+    /// scroll and back, like a flick of the wheel
+    Flick {
+        slowest: Duration,
+        fastest: Duration,
+    },
+}
+
+impl AccelCurve {
+    /// Deliberate scrolling, one click every 150ms is too slow to accelerate
+    pub const STEADY: AccelCurve = AccelCurve::Constant(Duration::from_millis(150));
+    /// Spinning the wheel faster and faster, from 80ms down to 10ms between the clicks
+    pub const SPIN_UP: AccelCurve = AccelCurve::Linear {
+        first: Duration::from_millis(80),
+        last: Duration::from_millis(10),
+    };
+    /// A quick flick of the wheel, from 60ms down to 8ms between the clicks and back
+    pub const FLICK: AccelCurve = AccelCurve::Flick {
+        slowest: Duration::from_millis(60),
+        fastest: Duration::from_millis(8),
+    };
+
+    /// Get the intervals between `distance` clicks, i.e. one less than `distance`
+    pub fn intervals(&self, distance: u32) -> Vec<Duration> {
+        let count = distance.saturating_sub(1);
+        // Interpolate in whole nanoseconds, so the ends of the curve are exact
+        let lerp = |from: Duration, to: Duration, t: f64| {
+            let from = from.as_nanos() as f64;
+            Duration::from_nanos((from + (to.as_nanos() as f64 - from) * t).round() as u64)
+        };
+        (0..count)
+            .map(|interval| {
+                let t = match count {
+                    1 => 0.0,
+                    _ => interval as f64 / (count - 1) as f64,
+                };
+                match *self {
+                    AccelCurve::Constant(interval) => interval,
+                    AccelCurve::Linear { first, last } => lerp(first, last, t),
+                    AccelCurve::Flick { slowest, fastest } => {
+                        let peak = 1.0 - (2.0 * t - 1.0).abs();
+                        lerp(slowest, fastest, peak * peak * (3.0 - 2.0 * peak))
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// A single step of a macro, see `MouseActions::execute`
+///
+/// With the `serde` feature the actions are (de)serialized like the lines of
+/// the macro files, see `macros`, e.g. `{"move_to": [400, 300]}`,
+/// `{"click": "right"}` and `{"sleep": 200}` in JSON
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Action {
+    /// Move to the given `x`, `y` coordinates
+    MoveTo(usize, usize),
+    /// Move relative to the current position
+    MoveRelative(i32, i32),
+    Click(MouseButton),
+    Press(MouseButton),
+    Release(MouseButton),
+    Scroll(ScrollDirection),
+    /// Wait for the given duration before the next action
+    Sleep(#[cfg_attr(feature = "serde", serde(with = "crate::macros::millis"))] Duration),
+    /// Press the button, move to the given `x`, `y` coordinates and release the button
+    Drag(MouseButton, usize, usize),
+}
+
+/// Keeps the cursor confined to a rectangle, see `MouseActions::confine_cursor`
+///
+/// The cursor is released again when the guard is dropped
+pub struct CursorClip {
+    release: Option<Box<dyn FnOnce()>>,
+}
+
+impl CursorClip {
+    pub(crate) fn new(release: impl FnOnce() + 'static) -> Self {
+        CursorClip {
+            release: Some(Box::new(release)),
         }
-        self.release_button(button)
     }
-    /// Scroll the mouse wheel towards to the given direction
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::common::ScrollDirection;
-    /// use std::{thread, time::Duration};
-    ///
-    /// let manager = Mouse::new();
-    /// let sleep_duration = Duration::from_millis(250);
-    ///
-    /// for _ in 0..5 {
-    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
-    ///     thread::sleep(sleep_duration);
-    /// }
-    ///
-    /// for _ in 0..5 {
-    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
-    ///     thread::sleep(sleep_duration);
-    /// }
-    /// ```
-    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error>;
-    /// Attach a callback function to mouse events
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    /// use mouce::error::Error;
-    ///
-    /// let mut manager = Mouse::new();
-    /// let hook_result = manager.hook(Box::new(|e| println!("New event: {:?}", e)));
-    /// match hook_result {
-    ///     Ok(id) => {
-    ///         assert_eq!(manager.unhook(id), Ok(()));
-    ///     }
-    ///     // Hooking may require user privileges on some systems
-    ///     // e.g. requires super user for Linux
-    ///     Err(err) => assert_eq!(Error::PermissionDenied, err),
-    /// }
-    /// ```
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error>;
-    /// Remove the callback function with the given `CallbackId`
-    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error>;
-    /// Remove all callback functions
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// use mouce::Mouse;
-    ///
-    /// let mut manager = Mouse::new();
-    /// assert_eq!(manager.unhook_all(), Ok(()));
-    /// ```
-    fn unhook_all(&mut self) -> Result<(), Error>;
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::error::Error;
-    use crate::{common::MouseButton, common::ScrollDirection, Mouse};
-    use std::{thread, time::Duration};
+impl Drop for CursorClip {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// Restores the held modifiers of `with_modifiers`, even if its closure panics
+struct ModifierGuard<'a> {
+    manager: &'a dyn MouseActions,
+    previous: Option<Vec<Modifier>>,
+}
+
+impl ModifierGuard<'_> {
+    fn restore(mut self) -> Result<(), Error> {
+        match self.previous.take() {
+            Some(previous) => self.manager.hold_modifiers(&previous),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ModifierGuard<'_> {
+    fn drop(&mut self) {
+        // Only reached without `restore` when the closure panicked
+        if let Some(previous) = self.previous.take() {
+            let _ = self.manager.hold_modifiers(&previous);
+        }
+    }
+}
+
+/// Keeps a callback hooked, see `MouseActions::hook_scoped`
+///
+/// The callback is unhooked when the guard is dropped. The guard borrows the
+/// mouse manager and dereferences to it, so the manager can still be used
+/// while the callback is hooked
+pub struct HookGuard<'a> {
+    manager: &'a mut dyn MouseActions,
+    callback_id: CallbackId,
+}
+
+impl HookGuard<'_> {
+    /// Get the id of the hooked callback
+    pub fn id(&self) -> CallbackId {
+        self.callback_id
+    }
+}
+
+impl<'a> Deref for HookGuard<'a> {
+    type Target = dyn MouseActions + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.manager
+    }
+}
+
+impl DerefMut for HookGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.manager
+    }
+}
+
+impl Drop for HookGuard<'_> {
+    fn drop(&mut self) {
+        // There is no way to report the errors from drop, the callback may
+        // also have been unhooked through the guard already
+        let _ = self.manager.unhook(self.callback_id);
+    }
+}
+
+/// Keeps polling the cursor position, see `MouseActions::watch_position`
+///
+/// The polling thread is stopped when the handle is dropped
+pub struct WatchHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Start a thread that polls the position with the poller every `interval`
+    /// and invokes the callback whenever it differs from `last`
+    pub(crate) fn spawn(
+        last: (i32, i32),
+        interval: Duration,
+        callback: Box<dyn Fn((i32, i32)) + Send>,
+        mut poll: impl FnMut() -> Result<(i32, i32), Error> + Send + 'static,
+    ) -> Self {
+        // Nothing is sent over the channel, it disconnects when the handle is dropped
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let mut last = last;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                match poll() {
+                    Ok(position) if position != last => {
+                        last = position;
+                        callback(position);
+                    }
+                    // Skip the failed polls, the next one may succeed
+                    _ => {}
+                }
+            }
+        });
+        WatchHandle {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What `MouseActions::hook_channel_bounded` does with a new event when its queue is full
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued event to make room for the new one
+    DropOldest,
+    /// Drop the new event
+    DropNewest,
+    /// Wait until the receiver takes an event, nothing is dropped. The listener
+    /// thread waits in the meantime, so the other callbacks are held up until
+    /// the receiver catches up or is dropped
+    Block,
+}
+
+struct EventQueue {
+    state: Mutex<EventQueueState>,
+    /// Notified whenever the queue or the closed flags change
+    changed: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+struct EventQueueState {
+    events: VecDeque<MouseEvent>,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+/// The sending side of an `EventQueue`, it is owned by the hooked callback
+/// and closes the queue when the callback is unhooked
+struct EventSender {
+    queue: Arc<EventQueue>,
+}
+
+impl EventSender {
+    fn send(&self, event: MouseEvent) {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if state.receiver_closed {
+                return;
+            }
+            if state.events.len() < self.queue.capacity {
+                break;
+            }
+            match self.queue.policy {
+                OverflowPolicy::DropOldest => {
+                    state.events.pop_front();
+                    break;
+                }
+                OverflowPolicy::DropNewest => return,
+                OverflowPolicy::Block => state = self.queue.changed.wait(state).unwrap(),
+            }
+        }
+        state.events.push_back(event);
+        self.queue.changed.notify_all();
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().sender_closed = true;
+        self.queue.changed.notify_all();
+    }
+}
+
+/// Receives the events of a callback that is hooked with
+/// `MouseActions::hook_channel_bounded`
+///
+/// The receive functions return `None` once the callback is unhooked and all
+/// of the queued events are received
+pub struct EventReceiver {
+    queue: Arc<EventQueue>,
+}
+
+impl EventReceiver {
+    /// Wait for the next event
+    pub fn recv(&self) -> Option<MouseEvent> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.take(&mut state) {
+                return Some(event);
+            }
+            if state.sender_closed {
+                return None;
+            }
+            state = self.queue.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Wait for the next event for at most `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<MouseEvent> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.take(&mut state) {
+                return Some(event);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if state.sender_closed || remaining.is_zero() {
+                return None;
+            }
+            state = self.queue.changed.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+
+    /// Take the next event if there is one, without waiting
+    pub fn try_recv(&self) -> Option<MouseEvent> {
+        self.take(&mut self.queue.state.lock().unwrap())
+    }
+
+    /// Get the number of the queued events
+    pub fn len(&self) -> usize {
+        self.queue.state.lock().unwrap().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, state: &mut EventQueueState) -> Option<MouseEvent> {
+        let event = state.events.pop_front()?;
+        // Wake up a sender that is blocked on the full queue
+        self.queue.changed.notify_all();
+        Some(event)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        // Nobody is going to receive the events anymore, so a blocked sender
+        // must not keep waiting for room
+        self.queue.state.lock().unwrap().receiver_closed = true;
+        self.queue.changed.notify_all();
+    }
+}
+
+/// The state that is used by the default implementations of the `MouseActions`
+/// functions, e.g. the configured click hold range
+///
+/// Every mouse manager stores its own `MouseState` and returns it from
+/// `MouseActions::state`, the managers of `MouseActions::output_handle` share
+/// the settings of it
+pub struct MouseState {
+    settings: Arc<Settings>,
+    /// The labels of the callbacks that are hooked with `hook_labeled`
+    labels: Mutex<Vec<(String, CallbackId)>>,
+    /// The queue of the scheduler thread, see `MouseActions::schedule`
+    scheduler: Mutex<Option<Sender<(Instant, MouseEvent)>>>,
+}
+
+/// The part of a `MouseState` that applies to every action of a mouse
+/// manager, it is shared with the managers of `MouseActions::output_handle`
+struct Settings {
+    click_hold_range: Mutex<(Duration, Duration)>,
+    rng: Mutex<Rng>,
+    /// The fractional wheel clicks `scroll_fractional` has not sent yet
+    scroll_remainder: Mutex<(f32, f32)>,
+    /// The sub-pixel movement `move_analog` has not sent yet
+    analog_remainder: Mutex<(f32, f32)>,
+    /// The position of the last button press, see `MouseActions::move_relative_to_last_click`
+    last_click: Mutex<Option<(i32, i32)>>,
+    /// Whether the short sleeps spin for the last part, see `MouseActions::set_precise_timing`
+    precise_timing: Mutex<bool>,
+    listener_priority: Mutex<Priority>,
+    /// Whether `move_to` is sent as a relative move, see `MouseActions::prefer_relative_moves`
+    relative_moves: Mutex<bool>,
+    coordinate_origin: Mutex<CoordinateOrigin>,
+    /// The scroll clicks per second the bulk scrolls stay under, see `MouseActions::set_max_scroll_rate`
+    max_scroll_rate: Mutex<Option<u32>>,
+    /// The modifiers that are held, see `MouseActions::hold_modifiers`
+    modifiers: Mutex<Vec<Modifier>>,
+    /// The buttons that are sent instead of the others, see `MouseActions::set_button_map`
+    button_map: Mutex<HashMap<MouseButton, MouseButton>>,
+    /// Where the time comes from, the tests replace the system clock
+    clock: Arc<dyn Clock>,
+}
+
+/// The time source of a `MouseState`, every sleep and time query of the
+/// default implementations goes through it
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Sleep for the duration, spinning for the last part if `precise` is set
+    fn sleep(&self, duration: Duration, precise: bool);
+
+    /// Wait a moment without sleeping, while a precise sleep spins
+    fn spin(&self) {
+        std::hint::spin_loop();
+    }
+
+    /// Get the sleeps that were recorded instead of slept
+    #[cfg(test)]
+    fn sleeps(&self) -> Vec<Duration> {
+        Vec::new()
+    }
+
+    /// Move the time forward without sleeping
+    #[cfg(test)]
+    fn advance(&self, _duration: Duration) {}
+}
+
+/// The monotonic clock of the system
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration, precise: bool) {
+        if precise {
+            precise_sleep(self, duration);
+        } else {
+            thread::sleep(duration);
+        }
+    }
+}
+
+/// How far the mock clock advances with each spin
+#[cfg(test)]
+const SPIN_STEP: Duration = Duration::from_micros(100);
+
+/// A clock that only advances when it is told to, so the tests do not have to wait
+#[cfg(test)]
+struct MockClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration, _precise: bool) {
+        self.sleeps.lock().unwrap().push(duration);
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    fn spin(&self) {
+        *self.elapsed.lock().unwrap() += SPIN_STEP;
+    }
+
+    fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+
+    fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Default for MouseState {
+    fn default() -> Self {
+        MouseState {
+            settings: Arc::new(Settings::default()),
+            labels: Mutex::new(Vec::new()),
+            scheduler: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            click_hold_range: Mutex::new((Duration::from_millis(60), Duration::from_millis(120))),
+            rng: Mutex::new(Rng::from_time()),
+            scroll_remainder: Mutex::new((0.0, 0.0)),
+            analog_remainder: Mutex::new((0.0, 0.0)),
+            last_click: Mutex::new(None),
+            precise_timing: Mutex::new(false),
+            listener_priority: Mutex::new(Priority::Normal),
+            relative_moves: Mutex::new(false),
+            coordinate_origin: Mutex::new(CoordinateOrigin::TopLeft),
+            max_scroll_rate: Mutex::new(None),
+            modifiers: Mutex::new(Vec::new()),
+            button_map: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl MouseState {
+    /// Create a state that records the sleeps instead of actually sleeping
+    #[cfg(test)]
+    pub(crate) fn with_mock_clock() -> Self {
+        MouseState {
+            settings: Arc::new(Settings {
+                clock: Arc::new(MockClock {
+                    start: Instant::now(),
+                    elapsed: Mutex::new(Duration::ZERO),
+                    sleeps: Mutex::new(Vec::new()),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Create a state that shares the settings of this one, for the managers
+    /// of `MouseActions::output_handle`
+    pub(crate) fn share(&self) -> Self {
+        MouseState {
+            settings: self.settings.clone(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn sleeps(&self) -> Vec<Duration> {
+        self.settings.clock.sleeps()
+    }
+
+    /// Advance the mock clock without recording a sleep, e.g. to simulate slow actions
+    #[cfg(test)]
+    pub(crate) fn advance(&self, duration: Duration) {
+        self.settings.clock.advance(duration);
+    }
+
+    /// Get the current time of the monotonic clock
+    pub(crate) fn now(&self) -> Instant {
+        self.settings.clock.now()
+    }
+
+    /// Get the priority the backends give to the listener threads they start
+    pub(crate) fn listener_priority(&self) -> Priority {
+        *self.settings.listener_priority.lock().unwrap()
+    }
+
+    /// Check whether the backends should send `move_to` as a native relative move
+    pub(crate) fn prefers_relative_moves(&self) -> bool {
+        *self.settings.relative_moves.lock().unwrap()
+    }
+
+    pub(crate) fn coordinate_origin(&self) -> CoordinateOrigin {
+        *self.settings.coordinate_origin.lock().unwrap()
+    }
+
+    /// Check whether a manager of `MouseActions::output_handle` shares the
+    /// settings of this one
+    pub(crate) fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.settings) > 1
+    }
+
+    /// Get the modifiers that are currently held
+    pub(crate) fn held_modifiers(&self) -> Vec<Modifier> {
+        self.settings.modifiers.lock().unwrap().clone()
+    }
+
+    /// Record the modifiers that are held after a backend changed them
+    pub(crate) fn set_held_modifiers(&self, modifiers: &[Modifier]) {
+        *self.settings.modifiers.lock().unwrap() = modifiers.to_vec();
+    }
+
+    /// Remember the position of a button press, the backends call this after
+    /// every press they send
+    pub(crate) fn remember_click(&self, x: i32, y: i32) {
+        *self.settings.last_click.lock().unwrap() = Some((x, y));
+    }
+
+    /// Set the configuration back to the defaults and forget the cached
+    /// positions and remainders, see `MouseActions::reset`
+    pub(crate) fn reset(&self) {
+        let defaults = Settings::default();
+        *self.settings.click_hold_range.lock().unwrap() =
+            *defaults.click_hold_range.lock().unwrap();
+        *self.settings.rng.lock().unwrap() = Rng::from_time();
+        *self.settings.scroll_remainder.lock().unwrap() = (0.0, 0.0);
+        *self.settings.analog_remainder.lock().unwrap() = (0.0, 0.0);
+        *self.settings.last_click.lock().unwrap() = None;
+        *self.settings.precise_timing.lock().unwrap() = false;
+        *self.settings.relative_moves.lock().unwrap() = false;
+        *self.settings.coordinate_origin.lock().unwrap() = CoordinateOrigin::TopLeft;
+        *self.settings.max_scroll_rate.lock().unwrap() = None;
+        self.settings.button_map.lock().unwrap().clear();
+    }
+
+    /// Get the button that is sent for the given button, see
+    /// `MouseActions::set_button_map`, the backends call this before sending
+    /// a button to the system
+    pub(crate) fn map_button(&self, button: &MouseButton) -> MouseButton {
+        *self
+            .settings
+            .button_map
+            .lock()
+            .unwrap()
+            .get(button)
+            .unwrap_or(button)
+    }
+
+    /// Forget the label of the given callback, the backends call this whenever
+    /// a callback is unhooked
+    pub(crate) fn forget_label(&self, callback_id: CallbackId) {
+        self.labels
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(_, id)| *id != callback_id);
+    }
+
+    /// Forget the labels of all of the callbacks
+    pub(crate) fn forget_labels(&self) {
+        self.labels
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub(crate) fn sleep(&self, duration: Duration) {
+        let precise = *self.settings.precise_timing.lock().unwrap();
+        self.sleep_with(duration, precise);
+    }
+
+    /// Sleep precisely regardless of `MouseActions::set_precise_timing`
+    pub(crate) fn sleep_precisely(&self, duration: Duration) {
+        self.sleep_with(duration, true);
+    }
+
+    fn sleep_with(&self, duration: Duration, precise: bool) {
+        self.settings.clock.sleep(duration, precise);
+    }
+}
+
+/// The part of a precise sleep that is spun instead of slept, it covers
+/// the usual timer resolution of the supported platforms
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Sleep for all but the last `SPIN_THRESHOLD` of the duration, then spin until the deadline
+fn precise_sleep<C: Clock + ?Sized>(clock: &C, duration: Duration) {
+    let deadline = clock.now() + duration;
+    if duration > SPIN_THRESHOLD {
+        clock.sleep(duration - SPIN_THRESHOLD, false);
+    }
+    while clock.now() < deadline {
+        clock.spin();
+    }
+}
+
+/// Convert a `y` coordinate between the top-left origin of the platforms and
+/// the origin that is set with `MouseActions::set_coordinate_origin`, the
+/// backends call this in `move_to` and `get_position`
+///
+/// With `CoordinateOrigin::BottomLeft` the y axis is mirrored on the primary
+/// display, `height - 1 - y`, which converts in both of the directions
+pub(crate) fn convert_origin<M: MouseActions + ?Sized>(mouse: &M, y: i32) -> Result<i32, Error> {
+    match mouse.state().coordinate_origin() {
+        CoordinateOrigin::TopLeft => Ok(y),
+        CoordinateOrigin::BottomLeft => Ok(mouse.get_display_size()?.1 as i32 - 1 - y),
+    }
+}
+
+/// Convert a fraction (0.0 to 1.0) of the desktop to the normalized absolute
+/// coordinates of `SendInput`, which range from 0 to 65535
+#[cfg(any(target_os = "windows", test))]
+pub(crate) fn to_normalized_absolute(fraction: f64) -> i32 {
+    (fraction.clamp(0.0, 1.0) * 65535.0).round() as i32
+}
+
+/// Simplify a recorded path, e.g. the positions of the hooked move events
+/// of a drawing, to the points that are needed to keep its shape
+///
+/// This uses the Ramer-Douglas-Peucker algorithm, the simplified path
+/// deviates from the original by at most `epsilon` pixels. The first and
+/// the last points are always kept
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::simplify_path;
+///
+/// let path = [(0, 0), (5, 1), (10, 0), (10, 10)];
+/// assert_eq!(simplify_path(&path, 2.0), vec![(0, 0), (10, 0), (10, 10)]);
+/// ```
+pub fn simplify_path(points: &[(i32, i32)], epsilon: f64) -> Vec<(i32, i32)> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    // Split the path at its farthest point until every part is close enough
+    // to a line, without recursion so long recordings do not overflow the stack
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|index| {
+                (
+                    index,
+                    segment_distance(points[index], points[start], points[end]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, distance)) = farthest {
+            if distance > epsilon {
+                keep[index] = true;
+                ranges.push((start, index));
+                ranges.push((index, end));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Generate a natural looking path from `from` to `to` in `steps` moves,
+/// the returned points start with `from` and end with `to`
+///
+/// The path is a cubic Bezier curve with its control points pushed to the
+/// same side of the straight line by a random amount, which makes a slight
+/// arc like the movement of a hand. The points are eased in and out, so they
+/// are denser at the start and at the end of the path. The same `seed` always
+/// gives the same path. The path stays within 30% of the travel distance of
+/// the straight line. A `steps` of 0 is treated as 1
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::{thread, time::Duration};
+/// use mouce::Mouse;
+/// use mouce::common::human_path;
+///
+/// let manager = Mouse::new();
+/// for (x, y) in human_path((100, 100), (800, 400), 50, 42) {
+///     manager.move_to(x as usize, y as usize).expect("failed to move");
+///     thread::sleep(Duration::from_millis(5));
+/// }
+/// ```
+pub fn human_path(from: (i32, i32), to: (i32, i32), steps: usize, seed: u64) -> Vec<(i32, i32)> {
+    let steps = steps.max(1);
+    let mut rng = Rng::new(seed);
+    let (x0, y0) = (from.0 as f64, from.1 as f64);
+    let (dx, dy) = (to.0 as f64 - x0, to.1 as f64 - y0);
+
+    // The unit vector perpendicular to the travel direction, both of the control
+    // points are pushed to the same side of it so the path bends in a single arc
+    let distance = dx.hypot(dy);
+    let side = if rng.next_u64() >> 63 == 0 { 1.0 } else { -1.0 };
+    let (nx, ny) = if distance == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (-dy / distance * side, dx / distance * side)
+    };
+    let mut control = |along_min: f64, along_max: f64| {
+        let along = rng.f64_between(along_min, along_max);
+        let away = rng.f64_between(0.05, 0.3) * distance;
+        (x0 + dx * along + nx * away, y0 + dy * along + ny * away)
+    };
+    let control1 = control(0.2, 0.4);
+    let control2 = control(0.6, 0.8);
+    let end = (to.0 as f64, to.1 as f64);
+
+    let mut points: Vec<(i32, i32)> = (0..=steps)
+        .map(|step| {
+            // Ease in and out like the acceleration of a hand
+            let t = step as f64 / steps as f64;
+            let t = t * t * (3.0 - 2.0 * t);
+            let u = 1.0 - t;
+            let weights = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+            let x = weights[0] * x0 + weights[1] * control1.0 + weights[2] * control2.0;
+            let y = weights[0] * y0 + weights[1] * control1.1 + weights[2] * control2.1;
+            (
+                (x + weights[3] * end.0).round() as i32,
+                (y + weights[3] * end.1).round() as i32,
+            )
+        })
+        .collect();
+    points[0] = from;
+    points[steps] = to;
+    points
+}
+
+/// Get the distance of the point from the segment between `start` and `end`
+fn segment_distance(point: (i32, i32), start: (i32, i32), end: (i32, i32)) -> f64 {
+    let (px, py) = (point.0 as f64, point.1 as f64);
+    let (sx, sy) = (start.0 as f64, start.1 as f64);
+    let (dx, dy) = (end.0 as f64 - sx, end.1 as f64 - sy);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((px - sx) * dx + (py - sy) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (px - (sx + t * dx)).hypot(py - (sy + t * dy))
+}
+
+/// Plan a path from `start` to `end` that stays on the given monitors, see
+/// `MouseActions::move_to_smooth_global`. The returned waypoints end with `end`
+fn route_across_monitors(
+    monitors: &[Monitor],
+    start: (i32, i32),
+    end: (i32, i32),
+) -> Vec<(i32, i32)> {
+    let find = |(x, y): (i32, i32)| monitors.iter().position(|monitor| monitor.contains(x, y));
+    let (Some(from), Some(to)) = (find(start), find(end)) else {
+        return vec![end];
+    };
+
+    // Search the shortest chain of the adjacent monitors
+    let mut previous = vec![None; monitors.len()];
+    let mut visited = vec![false; monitors.len()];
+    visited[from] = true;
+    let mut queue = VecDeque::from([from]);
+    while let Some(index) = queue.pop_front() {
+        if index == to {
+            break;
+        }
+        for next in 0..monitors.len() {
+            if !visited[next]
+                && edge_crossing(&monitors[index], &monitors[next], start, end).is_some()
+            {
+                visited[next] = true;
+                previous[next] = Some(index);
+                queue.push_back(next);
+            }
+        }
+    }
+    if !visited[to] {
+        return vec![end];
+    }
+
+    let mut chain = vec![to];
+    while let Some(index) = previous[chain[chain.len() - 1]] {
+        chain.push(index);
+    }
+    chain.reverse();
+
+    let mut waypoints = Vec::new();
+    let mut current = start;
+    for pair in chain.windows(2) {
+        if let Some([leave, enter]) =
+            edge_crossing(&monitors[pair[0]], &monitors[pair[1]], current, end)
+        {
+            waypoints.extend([leave, enter]);
+            current = enter;
+        }
+    }
+    waypoints.push(end);
+    waypoints
+}
+
+/// Get the last point on `from` and the first point on `to` where the line
+/// from `current` to `target` crosses the edge the monitors share, clamped
+/// to the edge. `None` if the monitors do not share an edge
+fn edge_crossing(
+    from: &Monitor,
+    to: &Monitor,
+    current: (i32, i32),
+    target: (i32, i32),
+) -> Option<[(i32, i32); 2]> {
+    // The position of the line on the given coordinate of the given axis
+    let line_at = |axis: fn((i32, i32)) -> (i32, i32), value: i32| {
+        let (current, target) = (axis(current), axis(target));
+        if current.0 == target.0 {
+            return current.1;
+        }
+        let progress = (value - current.0) as f64 / (target.0 - current.0) as f64;
+        (current.1 as f64 + (target.1 - current.1) as f64 * progress).round() as i32
+    };
+    let bounds = |monitor: &Monitor| {
+        let (x, y) = (monitor.x, monitor.y);
+        (x, y, x + monitor.width as i32, y + monitor.height as i32)
+    };
+    let (from_left, from_top, from_right, from_bottom) = bounds(from);
+    let (to_left, to_top, to_right, to_bottom) = bounds(to);
+
+    // A vertical edge, the monitors are side by side
+    let (top, bottom) = (from_top.max(to_top), from_bottom.min(to_bottom));
+    if top < bottom {
+        let xs = if from_right == to_left {
+            Some((from_right - 1, to_left))
+        } else if to_right == from_left {
+            Some((from_left, to_right - 1))
+        } else {
+            None
+        };
+        if let Some((from_x, to_x)) = xs {
+            let y = line_at(|point| point, to_x).clamp(top, bottom - 1);
+            return Some([(from_x, y), (to_x, y)]);
+        }
+    }
+
+    // A horizontal edge, the monitors are above each other
+    let (left, right) = (from_left.max(to_left), from_right.min(to_right));
+    if left < right {
+        let ys = if from_bottom == to_top {
+            Some((from_bottom - 1, to_top))
+        } else if to_bottom == from_top {
+            Some((from_top, to_bottom - 1))
+        } else {
+            None
+        };
+        if let Some((from_y, to_y)) = ys {
+            let x = line_at(|(x, y)| (y, x), to_y).clamp(left, right - 1);
+            return Some([(x, from_y), (x, to_y)]);
+        }
+    }
+    None
+}
+
+/// Get the event of a scroll of `dx` and `dy` wheel clicks, a single click is
+/// a `Scroll` and anything more a `ScrollDelta`
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn scroll_event(dx: i32, dy: i32) -> Option<MouseEvent> {
+    match (dx, dy) {
+        (0, 0) => None,
+        (0, 1) => Some(MouseEvent::Scroll(ScrollDirection::Up)),
+        (0, -1) => Some(MouseEvent::Scroll(ScrollDirection::Down)),
+        (1, 0) => Some(MouseEvent::Scroll(ScrollDirection::Right)),
+        (-1, 0) => Some(MouseEvent::Scroll(ScrollDirection::Left)),
+        (dx, dy) => Some(MouseEvent::ScrollDelta(dx, dy)),
+    }
+}
+
+/// Convert the wheel delta of a Windows scroll into wheel clicks, a notch is
+/// 120 and a partial notch of a high resolution wheel is rounded up
+#[cfg(any(target_os = "windows", test))]
+pub(crate) fn wheel_clicks(delta: i16) -> i32 {
+    const WHEEL_DELTA: i32 = 120;
+    let delta = delta as i32;
+    delta.signum() * ((delta.abs() + WHEEL_DELTA - 1) / WHEEL_DELTA)
+}
+
+/// Split a scroll of `dx` and `dy` wheel clicks into the single clicks
+/// of each axis, the vertical ones first
+fn scroll_directions(dx: i32, dy: i32) -> impl Iterator<Item = ScrollDirection> {
+    let vertical = if dy > 0 {
+        ScrollDirection::Up
+    } else {
+        ScrollDirection::Down
+    };
+    let horizontal = if dx > 0 {
+        ScrollDirection::Right
+    } else {
+        ScrollDirection::Left
+    };
+    std::iter::repeat_n(vertical, dy.unsigned_abs() as usize)
+        .chain(std::iter::repeat_n(horizontal, dx.unsigned_abs() as usize))
+}
+
+/// Get the `CGEventFlags` of the given modifiers
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) fn modifier_flags(modifiers: &[Modifier]) -> u64 {
+    modifiers.iter().fold(0, |flags, modifier| {
+        flags
+            | match modifier {
+                Modifier::Shift => 0x0002_0000,
+                Modifier::Control => 0x0004_0000,
+                Modifier::Alt => 0x0008_0000,
+                Modifier::Meta => 0x0010_0000,
+            }
+    })
+}
+
+/// Release the modifiers that are held with `MouseActions::hold_modifiers`
+/// when a manager shuts down, unless a manager of `MouseActions::output_handle`
+/// still shares them
+pub(crate) fn release_modifiers<M: MouseActions + ?Sized>(mouse: &M) -> Result<(), Error> {
+    let state = mouse.state();
+    if state.is_shared() || state.held_modifiers().is_empty() {
+        return Ok(());
+    }
+    mouse.hold_modifiers(&[])
+}
+
+/// Initialize the first of the given backends that works, collecting the
+/// errors of the ones that do not
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    test
+))]
+pub(crate) fn first_available<B>(
+    order: &[B],
+    init: impl Fn(&B) -> Result<Box<dyn MouseActions>, Error>,
+) -> Result<Box<dyn MouseActions>, Error> {
+    let mut errors = Vec::with_capacity(order.len());
+    for backend in order {
+        match init(backend) {
+            Ok(manager) => return Ok(manager),
+            Err(err) => errors.push(err),
+        }
+    }
+    Err(Error::NoBackendAvailable(errors))
+}
+
+/// The refresh rate `move_to_smooth` assumes where it is not known
+const DEFAULT_REFRESH_HZ: f32 = 60.0;
+
+/// The drag threshold `drag_and_drop` assumes where it is not known, the
+/// default of Windows
+const DEFAULT_DRAG_THRESHOLD: (u32, u32) = (4, 4);
+
+/// The number of the steps in a row without getting closer to the target
+/// after which `walk_to` gives up
+const WALK_STALL_LIMIT: u32 = 4;
+
+/// How often `move_to_verified` polls the position of the cursor
+const VERIFY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Get the number of the moves that fit into `duration`, one per frame of
+/// a display that refreshes at `refresh_hz`, at least one
+fn smooth_steps(duration: Duration, refresh_hz: f32) -> u32 {
+    let refresh_hz = if refresh_hz.is_finite() && refresh_hz > 0.0 {
+        refresh_hz
+    } else {
+        DEFAULT_REFRESH_HZ
+    };
+    (duration.as_secs_f64() * refresh_hz as f64)
+        .round()
+        .max(1.0) as u32
+}
+
+/// Send the given event with the matching action of the mouse
+fn send_event<M: MouseActions + ?Sized>(mouse: &M, event: &MouseEvent) -> Result<(), Error> {
+    match *event {
+        MouseEvent::RelativeMove(x_offset, y_offset) => mouse.move_relative(x_offset, y_offset),
+        MouseEvent::AbsoluteMove(x, y) => mouse.r#move(MoveKind::Absolute(x, y)),
+        MouseEvent::Press(button) => mouse.press_button(&button),
+        MouseEvent::Release(button) => mouse.release_button(&button),
+        MouseEvent::Scroll(direction) => mouse.scroll_wheel(&direction),
+        MouseEvent::ScrollDelta(dx, dy) => mouse.scroll_vector(dx, dy),
+        MouseEvent::Dial(_) => Err(Error::NotImplemented),
+    }
+}
+
+/// An event that is queued with `MouseActions::schedule`, the events are
+/// ordered by their time, then by the order they were scheduled in
+struct Scheduled {
+    when: Instant,
+    sequence: u64,
+    event: MouseEvent,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.when, self.sequence).cmp(&(other.when, other.sequence))
+    }
+}
+
+/// Send the scheduled events in time order as they come due, until the
+/// sender is dropped and all of the queued events are sent
+///
+/// The errors are ignored, there is no one to report them to
+fn run_schedule<M: MouseActions + ?Sized>(receiver: Receiver<(Instant, MouseEvent)>, mouse: &M) {
+    let mut queue: BinaryHeap<Reverse<Scheduled>> = BinaryHeap::new();
+    let mut sequence = 0;
+    loop {
+        let received = match queue.peek() {
+            Some(Reverse(next)) => {
+                let remaining = next.when.saturating_duration_since(mouse.state().now());
+                if remaining.is_zero() {
+                    let Reverse(next) = queue.pop().unwrap();
+                    let _ = send_event(mouse, &next.event);
+                    continue;
+                }
+                // Wake up early if an earlier event is scheduled in the meantime
+                receiver.recv_timeout(remaining)
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((when, event)) => {
+                queue.push(Reverse(Scheduled {
+                    when,
+                    sequence,
+                    event,
+                }));
+                sequence += 1;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                while let Some(Reverse(next)) = queue.pop() {
+                    let _ = mouse.dispatch_at(next.event, next.when);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Send the given scroll clicks, spreading them out so they stay under
+/// the `max_scroll_rate` of the mouse
+fn scroll_paced<M: MouseActions + ?Sized>(
+    mouse: &M,
+    directions: impl Iterator<Item = ScrollDirection>,
+) -> Result<(), Error> {
+    let interval = mouse
+        .max_scroll_rate()
+        .map(|rate| Duration::from_secs(1) / rate.max(1));
+    for (click, direction) in directions.enumerate() {
+        if let Some(interval) = interval.filter(|_| click > 0) {
+            mouse.state().sleep(interval);
+        }
+        mouse.scroll_wheel(&direction)?;
+    }
+    Ok(())
+}
+
+/// Convert the line deltas of a MacOS scroll event back into the scroll
+/// event of mouce, where `axis1` is the vertical delta (positive is up) and
+/// `axis2` is the horizontal one (positive is left)
+///
+/// A partial click is rounded up so that small scrolls are not lost
+#[cfg(any(target_vendor = "apple", test))]
+pub(crate) fn scroll_event_from_lines(
+    axis1: i64,
+    axis2: i64,
+    lines_per_click: i64,
+) -> Option<MouseEvent> {
+    let clicks = |lines: i64| {
+        let whole = (lines.abs() + lines_per_click - 1) / lines_per_click;
+        (lines.signum() * whole) as i32
+    };
+    scroll_event(clicks(-axis2), clicks(axis1))
+}
+
+/// Take the whole wheel clicks or pixels out of the given accumulator, leaving the remainder
+fn take_whole(accumulator: &mut f32) -> i32 {
+    // Allow a small rounding error, otherwise e.g. ten 0.1 steps add up to
+    // slightly less than a whole click
+    const EPSILON: f32 = 1e-4;
+    let clicks = (*accumulator + accumulator.signum() * EPSILON).trunc();
+    *accumulator -= clicks;
+    clicks as i32
+}
+
+/// Report the scroll clicks of the same direction that arrive within `window`
+/// of the first one as a single call, until the sender is dropped
+fn coalesce_scrolls(
+    receiver: Receiver<ScrollDirection>,
+    window: Duration,
+    callback: Box<dyn Fn(ScrollDirection, i32) + Send>,
+) {
+    let mut pending: Option<(ScrollDirection, i32, Instant)> = None;
+    loop {
+        let received = match pending {
+            Some((_, _, deadline)) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(direction) => match &mut pending {
+                Some((current, count, _)) if *current == direction => *count += 1,
+                _ => {
+                    // A change of direction ends the current group right away
+                    if let Some((current, count, _)) = pending {
+                        callback(current, count);
+                    }
+                    pending = Some((direction, 1, Instant::now() + window));
+                }
+            },
+            Err(err) => {
+                if let Some((current, count, _)) = pending.take() {
+                    callback(current, count);
+                }
+                if err == RecvTimeoutError::Disconnected {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for dyn MouseActions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MouseActions")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl dyn MouseActions + '_ {
+    /// Press the given mouse button, run `f` and release the button again,
+    /// the button is released even if `f` fails
+    ///
+    /// `f` is given the mouse manager, so it can perform other actions while
+    /// the button is held down, e.g. scroll while holding the right button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, ScrollDirection};
+    ///
+    /// let manager = Mouse::new();
+    /// let result = manager.while_holding(&MouseButton::Right, |manager| {
+    ///     manager.scroll_wheel(&ScrollDirection::Down)?;
+    ///     manager.scroll_wheel(&ScrollDirection::Down)
+    /// });
+    /// assert_eq!(result, Ok(()));
+    /// ```
+    pub fn while_holding(
+        &self,
+        button: &MouseButton,
+        f: impl FnOnce(&Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.press_button(button)?;
+        let result = f(self);
+        let released = self.release_button(button);
+        result.and(released)
+    }
+    /// Click `click` while `hold` is held down, for the chords like "hold
+    /// right, click left" that some applications use
+    ///
+    /// The events are sent in the order press `hold`, press `click`, release
+    /// `click`, release `hold`, and `hold` is released even if the click fails.
+    /// Each button is sent with its own event types. On MacOS a mouse event
+    /// only carries its own button, so the chord is only seen by the
+    /// applications that ask the system for the pressed buttons, e.g. with
+    /// `NSEvent.pressedMouseButtons`, not by the ones that only look at the
+    /// click
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let result = manager.click_while_holding(&MouseButton::Right, &MouseButton::Left);
+    /// assert_eq!(result, Ok(()));
+    /// ```
+    pub fn click_while_holding(
+        &self,
+        hold: &MouseButton,
+        click: &MouseButton,
+    ) -> Result<(), Error> {
+        self.while_holding(hold, |manager| manager.click_button(click))
+    }
+    /// Zoom the focused application in (`amount > 0`) or out (`amount < 0`) by
+    /// scrolling `amount` wheel clicks while Ctrl is held, which is how most
+    /// applications zoom
+    ///
+    /// The scroll goes to whatever application is focused, or the one under
+    /// the cursor on some platforms. Many MacOS applications zoom with Cmd
+    /// instead, use `with_modifiers` with `Modifier::Meta` for them. Ctrl is
+    /// released even if the scroll fails, see `with_modifiers`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Zoom in by 3 steps
+    /// assert_eq!(manager.zoom(3), Ok(()));
+    /// ```
+    pub fn zoom(&self, amount: i32) -> Result<(), Error> {
+        self.with_modifiers(&[Modifier::Control], |manager| {
+            manager.scroll_vector(0, amount)
+        })
+    }
+    /// Hold the given modifiers while running `f`, so all of its actions are
+    /// sent as if e.g. Ctrl was held down, then restore the modifiers that were
+    /// held before
+    ///
+    /// On MacOS the modifier flags are set on every event that is created
+    /// within `f`, on Windows and X11 the modifier keys are pressed for the
+    /// duration of `f`, so they also apply to the input of the user. The
+    /// modifiers are restored even if `f` fails or panics. See `hold_modifiers`
+    /// for the platform support
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{Modifier, MouseButton};
+    ///
+    /// let manager = Mouse::new();
+    /// // Select multiple items with Ctrl held
+    /// let result = manager.with_modifiers(&[Modifier::Control], |manager| {
+    ///     manager.move_to(100, 100)?;
+    ///     manager.click_button(&MouseButton::Left)?;
+    ///     manager.move_to(100, 150)?;
+    ///     manager.click_button(&MouseButton::Left)
+    /// });
+    /// assert_eq!(result, Ok(()));
+    /// ```
+    pub fn with_modifiers(
+        &self,
+        modifiers: &[Modifier],
+        f: impl FnOnce(&Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let previous = self.state().held_modifiers();
+        let mut held = previous.clone();
+        for modifier in modifiers {
+            if !held.contains(modifier) {
+                held.push(*modifier);
+            }
+        }
+        self.hold_modifiers(&held)?;
+
+        let guard = ModifierGuard {
+            manager: self,
+            previous: Some(previous),
+        };
+        let result = f(self);
+        let restored = guard.restore();
+        result.and(restored)
+    }
+    /// Press the given mouse button, hold it for `hold` and release it again,
+    /// reporting the progress of the hold from 0.0 to 1.0 every `tick`
+    ///
+    /// `on_progress` is invoked with 0.0 right after the press and with 1.0
+    /// right before the release, e.g. to draw the timer of a press-and-hold
+    /// confirmation. A `tick` of zero only reports the start and the end
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let result = manager.press_and_report(
+    ///     &MouseButton::Left,
+    ///     Duration::from_secs(2),
+    ///     Duration::from_millis(100),
+    ///     |progress| println!("Held {:.0}%", progress * 100.0),
+    /// );
+    /// assert_eq!(result, Ok(()));
+    /// ```
+    pub fn press_and_report(
+        &self,
+        button: &MouseButton,
+        hold: Duration,
+        tick: Duration,
+        on_progress: impl Fn(f32),
+    ) -> Result<(), Error> {
+        self.while_holding(button, |manager| {
+            let state = manager.state();
+            let start = state.now();
+            on_progress(0.0);
+            loop {
+                let elapsed = state.now() - start;
+                if elapsed >= hold {
+                    break;
+                }
+                let remaining = hold - elapsed;
+                state.sleep(if tick.is_zero() {
+                    remaining
+                } else {
+                    tick.min(remaining)
+                });
+                let progress = (state.now() - start).as_secs_f32() / hold.as_secs_f32();
+                on_progress(progress.min(1.0));
+            }
+            // A zero hold never enters the loop
+            if hold.is_zero() {
+                on_progress(1.0);
+            }
+            Ok(())
+        })
+    }
+    /// Attach a callback function to mouse events until the returned guard is dropped
+    ///
+    /// This is the scoped counterpart of `hook`, the callback can not be leaked
+    /// by forgetting to unhook it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::{thread, time::Duration};
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// {
+    ///     let guard = manager.hook_scoped(Box::new(|e| println!("New event: {:?}", e)));
+    ///     // Requires super user privileges on Linux, like `hook`
+    ///     if let Ok(_guard) = guard {
+    ///         thread::sleep(Duration::from_secs(5));
+    ///     }
+    /// }
+    /// // The callback is unhooked here
+    /// ```
+    pub fn hook_scoped(&mut self, callback: MouseEventFn) -> Result<HookGuard<'_>, Error> {
+        let callback_id = self.hook(callback)?;
+        Ok(HookGuard {
+            manager: self,
+            callback_id,
+        })
+    }
+    /// Attach a callback function to mouse events with a label, so it can be
+    /// found with `hooks_by_label` and removed with `unhook_label` later
+    ///
+    /// The labels do not have to be unique, the returned `CallbackId` can still
+    /// be used with `unhook`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager
+    ///     .hook_labeled("logger", Box::new(|e| println!("New event: {:?}", e)))
+    ///     .unwrap();
+    /// assert_eq!(manager.unhook_label("logger"), Ok(()));
+    /// ```
+    pub fn hook_labeled(
+        &mut self,
+        label: impl Into<String>,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        let callback_id = self.hook(callback)?;
+        self.state()
+            .labels
+            .lock()
+            .unwrap()
+            .push((label.into(), callback_id));
+        Ok(callback_id)
+    }
+}
+
+pub trait MouseActions {
+    /// Get the state that is used by the default implementations of the functions
+    ///
+    /// Implementors only need to store a `MouseState::default()` and return it
+    /// here. The default returns a state that is shared by every implementor
+    /// that does not override it, so their settings affect each other
+    fn state(&self) -> &MouseState {
+        static SHARED: OnceLock<MouseState> = OnceLock::new();
+        SHARED.get_or_init(MouseState::default)
+    }
+    /// Get the name of the backend that is used, e.g. `"x11"`, `"uinput"`,
+    /// `"windows-sendinput"` or `"macos-cgevent"`
+    ///
+    /// This is meant for logging and bug reports, e.g. to find out whether
+    /// uinput was selected instead of X11 on an XWayland session. The default
+    /// returns `"unknown"`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// println!("mouce is using the {} backend", manager.backend_name());
+    /// ```
+    fn backend_name(&self) -> &'static str {
+        "unknown"
+    }
+    /// Create a mouse manager that sends its actions the same way as this
+    /// one, e.g. to act from another thread
+    ///
+    /// It uses the same backend with the same target, e.g. the screen of X11
+    /// or the virtual device of uinput, and shares the settings of this
+    /// manager, e.g. the button map and the coordinate origin, so a change to
+    /// either of them applies to both. The hooks are not shared, where they
+    /// are global, e.g. on Windows and macOS, dropping the returned manager
+    /// leaves them to this one.
+    /// `hook_reactive`, `schedule` and `watch_position` act through one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::thread;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let output = manager.output_handle().unwrap();
+    /// thread::spawn(move || {
+    ///     let _ = output.click_button(&MouseButton::Left);
+    /// });
+    /// ```
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the buttons the backend can press and release, e.g. to gray out
+    /// the options that can not be simulated
+    ///
+    /// This is about what the backend can send, not about the buttons the
+    /// connected mice have. A button that is not physically present can still
+    /// be simulated, and the list does not change when a device is plugged in
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// if manager.supported_buttons().contains(&MouseButton::Middle) {
+    ///     manager.click_button(&MouseButton::Middle).unwrap();
+    /// }
+    /// ```
+    fn supported_buttons(&self) -> &'static [MouseButton] {
+        &[MouseButton::Left, MouseButton::Middle, MouseButton::Right]
+    }
+    /// Move the mouse to the given `x`, `y` coordinates
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to(0, 0), Ok(()));
+    /// ```
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error>;
+    /// Move the mouse to the given `x`, `y` coordinates in exactly `steps` moves
+    /// along a straight line, waiting `step_delay` between the moves
+    ///
+    /// This gives full control over the number of the generated move events,
+    /// e.g. to not overwhelm a slow compositor. The last move ends at the given
+    /// coordinates, so a `steps` of 0 or 1 is a single `move_to`. The line starts
+    /// at the current position, so this fails where `get_position` is not
+    /// available
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to_steps(500, 500, 20, Duration::from_millis(5)), Ok(()));
+    /// ```
+    fn move_to_steps(
+        &self,
+        x: usize,
+        y: usize,
+        steps: u32,
+        step_delay: Duration,
+    ) -> Result<(), Error> {
+        if steps <= 1 {
+            return self.move_to(x, y);
+        }
+
+        let (start_x, start_y) = self.get_position()?;
+        let x_distance = x as f64 - start_x as f64;
+        let y_distance = y as f64 - start_y as f64;
+        for step in 1..=steps {
+            if step > 1 {
+                self.state().sleep(step_delay);
+            }
+            let progress = step as f64 / steps as f64;
+            let step_x = (start_x as f64 + x_distance * progress).round().max(0.0);
+            let step_y = (start_y as f64 + y_distance * progress).round().max(0.0);
+            self.move_to(step_x as usize, step_y as usize)?;
+        }
+        Ok(())
+    }
+    /// Move the mouse to the given `x`, `y` coordinates along a straight line
+    /// within the given `duration`, with a move for every frame of the display
+    ///
+    /// The number of the moves is derived from `duration` and the refresh rate of
+    /// `display_refresh_hz`, so every intermediate position is shown in its own
+    /// frame and no move is wasted within a frame. Where the refresh rate is not
+    /// known, 60 Hz is assumed. See `move_to_steps` for the rest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to_smooth(500, 500, Duration::from_millis(250)), Ok(()));
+    /// ```
+    fn move_to_smooth(&self, x: usize, y: usize, duration: Duration) -> Result<(), Error> {
+        let refresh_hz = self.display_refresh_hz().unwrap_or(DEFAULT_REFRESH_HZ);
+        let steps = smooth_steps(duration, refresh_hz);
+        self.move_to_steps(x, y, steps, duration / steps)
+    }
+    /// Walk the mouse to the given `x`, `y` coordinates with native relative
+    /// moves of at most `step_px` pixels, waiting `step_delay` between them
+    ///
+    /// Unlike `move_to_smooth`, which moves through absolute positions, the
+    /// cursor never jumps, so the games that read the deltas of the mouse
+    /// follow it. The position is read with `get_position` before every step
+    /// and the step heads for the target from there, which corrects the drift
+    /// of the pointer acceleration. This requires `get_position` to work, it
+    /// fails where it does not, e.g. on uinput
+    ///
+    /// A step that does not bring the cursor closer to the target, e.g. when
+    /// the pointer acceleration makes it overshoot, is followed by steps of
+    /// half the length. `Error::MoveNotConfirmed` is returned with the last
+    /// position if the cursor does not get closer over several steps in a row,
+    /// e.g. when the target is off the screen. A `step_px` of 0 is treated as 1
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Turn the camera of a game towards the center of the screen
+    /// assert_eq!(manager.walk_to(960, 540, 8, Duration::from_millis(4)), Ok(()));
+    /// ```
+    fn walk_to(&self, x: usize, y: usize, step_px: u32, step_delay: Duration) -> Result<(), Error> {
+        let mut step_px = step_px.max(1) as f64;
+        // The relative moves are in the coordinates of the platform
+        let target = (x as i64, convert_origin(self, y as i32)? as i64);
+        let mut closest_distance = None;
+        let mut stalled_steps = 0;
+        let mut moved = false;
+        loop {
+            let (from_x, from_y) = self.get_position()?;
+            let (x_distance, y_distance) = (
+                target.0 - from_x as i64,
+                target.1 - convert_origin(self, from_y)? as i64,
+            );
+            if (x_distance, y_distance) == (0, 0) {
+                return Ok(());
+            }
+            let distance = (x_distance as f64).hypot(y_distance as f64);
+            if closest_distance.is_some_and(|closest| distance >= closest) {
+                stalled_steps += 1;
+                if stalled_steps >= WALK_STALL_LIMIT {
+                    return Err(Error::MoveNotConfirmed(from_x, from_y));
+                }
+                step_px = (step_px / 2.0).max(1.0);
+            } else {
+                stalled_steps = 0;
+                closest_distance = Some(distance);
+            }
+
+            // Truncate the scaled step, so it is never longer than `step_px`,
+            // but move at least a pixel along the longer axis
+            let scale = (step_px / distance).min(1.0);
+            let mut x_offset = (x_distance as f64 * scale) as i32;
+            let mut y_offset = (y_distance as f64 * scale) as i32;
+            if (x_offset, y_offset) == (0, 0) {
+                if x_distance.abs() >= y_distance.abs() {
+                    x_offset = x_distance.signum() as i32;
+                } else {
+                    y_offset = y_distance.signum() as i32;
+                }
+            }
+            if moved {
+                self.state().sleep(step_delay);
+            }
+            moved = true;
+            self.move_relative(x_offset, y_offset)?;
+        }
+    }
+    /// Move the mouse to the given `x`, `y` coordinates in about `steps` moves,
+    /// like `move_to_steps`, without passing through the dead space between
+    /// the monitors
+    ///
+    /// A straight line between two monitors that are not aligned can leave
+    /// the monitors, where the moves are clamped to their edges by the system
+    /// and the animation gets stuck. The path is planned with `get_monitors`
+    /// - A move within a monitor is a straight line
+    /// - Otherwise the chain of the monitors that share an edge is searched
+    ///   from the monitor of the current position to the one of the target,
+    ///   with as few monitors as possible. The path crosses each shared edge
+    ///   where the straight line to the target does, or at the closest point of
+    ///   the edge if the line misses it, and is straight in between
+    /// - If either end is not on a monitor, or the monitors of the ends are not
+    ///   connected, the path is a straight line as well
+    ///
+    /// The steps are split between the straight parts by their lengths, every
+    /// part gets at least one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Move to a monitor that is placed to the right of the primary one
+    /// assert_eq!(manager.move_to_smooth_global(2500, 300, 50, Duration::from_millis(5)), Ok(()));
+    /// ```
+    fn move_to_smooth_global(
+        &self,
+        x: usize,
+        y: usize,
+        steps: u32,
+        step_delay: Duration,
+    ) -> Result<(), Error> {
+        if steps <= 1 {
+            return self.move_to(x, y);
+        }
+
+        let start = self.get_position()?;
+        let monitors = self.get_monitors().unwrap_or_default();
+        let waypoints = route_across_monitors(&monitors, start, (x as i32, y as i32));
+
+        let length = |from: (i32, i32), to: (i32, i32)| {
+            ((to.0 - from.0) as f64).hypot((to.1 - from.1) as f64)
+        };
+        let mut total_length = 0.0;
+        let mut previous = start;
+        for waypoint in &waypoints {
+            total_length += length(previous, *waypoint);
+            previous = *waypoint;
+        }
+
+        let mut previous = start;
+        let mut first = true;
+        for waypoint in waypoints {
+            let part_steps = if total_length == 0.0 {
+                1
+            } else {
+                (steps as f64 * length(previous, waypoint) / total_length)
+                    .round()
+                    .max(1.0) as u32
+            };
+            for step in 1..=part_steps {
+                if !first {
+                    self.state().sleep(step_delay);
+                }
+                first = false;
+                let progress = step as f64 / part_steps as f64;
+                let step_x = previous.0 as f64 + (waypoint.0 - previous.0) as f64 * progress;
+                let step_y = previous.1 as f64 + (waypoint.1 - previous.1) as f64 * progress;
+                self.r#move(MoveKind::Absolute(
+                    step_x.round() as i32,
+                    step_y.round() as i32,
+                ))?;
+            }
+            previous = waypoint;
+        }
+        Ok(())
+    }
+    /// Move the mouse to the given `x`, `y` coordinates by warping the cursor,
+    /// without sending a mouse input event
+    ///
+    /// Unlike an input event, a warp does not go through the input stack, so
+    /// the hooks of this and other programs do not receive a move event and
+    /// nothing sees any intermediate motion
+    /// - On Windows `SetCursorPos` is used
+    /// - On MacOS `CGWarpMouseCursorPosition` is used, which does not post an event
+    /// - On X11 `XWarpPointer` is used, the X server still reports the new
+    ///   position to the windows as pointer motion but no device events are made
+    /// - It is not available on uinput, which can only send device events
+    ///
+    /// `move_to` uses the same functions on those platforms unless
+    /// `prefer_relative_moves` is enabled, `move_to_silent` always warps
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to_silent(200, 200), Ok(()));
+    /// ```
+    fn move_to_silent(&self, _x: usize, _y: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Move the mouse to the given normalized coordinates of Windows, which are
+    /// sent to `SendInput` as they are, without converting them from pixels
+    ///
+    /// The coordinates are sent with `MOUSEEVENTF_VIRTUALDESK`, so 0 is the left
+    /// (or top) edge of the virtual desktop that spans all of the monitors and
+    /// 65535 is its right (or bottom) edge, e.g. (32768, 32768) is the center
+    /// of the virtual desktop rather than of the primary monitor. This is meant
+    /// for the special setups, e.g. a custom virtual display, where rounding
+    /// to whole pixels loses precision
+    ///
+    /// Only Windows uses normalized coordinates, `Error::NotImplemented` is
+    /// returned elsewhere
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to_raw_normalized(32768, 32768), Ok(()));
+    /// ```
+    fn move_to_raw_normalized(&self, _nx: u16, _ny: u16) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates like a human would,
+    /// overshooting the target slightly and settling on it
+    ///
+    /// The cursor first lands 1 to 2 pixels off the target, may correct back
+    /// to a pixel short of it and then ends exactly on the target, with a short
+    /// pause between the moves. The offsets and the pauses come from the random
+    /// number generator, see `seed_rng` to make them reproducible
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to_natural(500, 500), Ok(()));
+    /// ```
+    fn move_to_natural(&self, x: usize, y: usize) -> Result<(), Error> {
+        let (x, y) = (x as i32, y as i32);
+        let (overshoot, correction, pauses) = {
+            let mut rng = self.state().settings.rng.lock().unwrap();
+            let overshoot = (rng.offset_between(1, 2), rng.offset_between(1, 2));
+            // Back towards the target, by at most a pixel too far
+            let correction = (
+                -overshoot.0.signum() * rng.offset_between(0, 1).abs(),
+                -overshoot.1.signum() * rng.offset_between(0, 1).abs(),
+            );
+            let (min, max) = (Duration::from_millis(10), Duration::from_millis(30));
+            let pauses = [
+                rng.duration_between(min, max),
+                rng.duration_between(min, max),
+            ];
+            (overshoot, correction, pauses)
+        };
+
+        self.r#move(MoveKind::Absolute(x + overshoot.0, y + overshoot.1))?;
+        if correction != (0, 0) {
+            self.state().sleep(pauses[0]);
+            self.r#move(MoveKind::Absolute(x + correction.0, y + correction.1))?;
+        }
+        self.state().sleep(pauses[1]);
+        self.move_to(x as usize, y as usize)
+    }
+    /// Move the mouse to the given point and keep it still there for `dwell`
+    ///
+    /// Applications only see the mouse entering and leaving their windows and
+    /// elements, there is no event to send for it. Moving across the boundary
+    /// produces the enter, but most of the hover activated UI, e.g. tooltips
+    /// and menus that open on hover, also waits for the mouse to rest for a
+    /// while, usually a few hundred milliseconds. So the dwell should be at
+    /// least that long, any movement during it restarts the timer of the
+    /// application
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Wait for the tooltip of the button under (100, 100) to show up
+    /// assert_eq!(manager.hover(100, 100, Duration::from_millis(800)), Ok(()));
+    /// ```
+    fn hover(&self, x: usize, y: usize, dwell: Duration) -> Result<(), Error> {
+        self.move_to(x, y)?;
+        self.state().sleep(dwell);
+        Ok(())
+    }
+    /// Move the mouse relative to the current position
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_relative(100, 100), Ok(()));
+    /// ```
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let (x, y) = self.get_position()?;
+        self.move_to((x + x_offset) as usize, (y + y_offset) as usize)
+    }
+    /// Move the mouse relative to the position of the last button press that
+    /// was sent by this mouse manager, e.g. to move below a clicked button
+    ///
+    /// Both the presses of `press_button` and the clicks count. The position
+    /// is not tracked on uinput, as it is not able to get the position
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// manager.move_to(100, 100).unwrap();
+    /// manager.click_button(&MouseButton::Left).unwrap();
+    /// manager.move_to(500, 500).unwrap();
+    /// // Moves to (150, 150)
+    /// assert_eq!(manager.move_relative_to_last_click(50, 50), Ok(()));
+    /// ```
+    fn move_relative_to_last_click(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let last_click = *self.state().settings.last_click.lock().unwrap();
+        let (x, y) = last_click.ok_or(Error::CustomError("no button has been pressed yet"))?;
+        self.r#move(MoveKind::Absolute(x + x_offset, y + y_offset))
+    }
+    /// Move the mouse relative to the current position without exceeding the
+    /// given velocity, in pixels per millisecond
+    ///
+    /// A large move is split into multiple smaller native relative moves that
+    /// are spread over time, unlike the absolute moves of `move_to_steps` they
+    /// are seen as deltas by the applications that read the raw motion, e.g.
+    /// games. The moves add up to exactly `x_offset`, `y_offset`. The limit has
+    /// to be positive
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Takes about 100ms
+    /// assert_eq!(manager.move_relative_limited(500, 0, 5.0), Ok(()));
+    /// ```
+    fn move_relative_limited(
+        &self,
+        x_offset: i32,
+        y_offset: i32,
+        max_px_per_ms: f32,
+    ) -> Result<(), Error> {
+        if max_px_per_ms <= 0.0 || max_px_per_ms.is_nan() {
+            return Err(Error::CustomError("the velocity limit must be positive"));
+        }
+
+        let distance = (x_offset as f32).hypot(y_offset as f32);
+        if distance == 0.0 {
+            return Ok(());
+        }
+
+        // Every step moves at least a pixel, slower limits are kept by waiting
+        // longer, and an unlimited velocity moves in a single step
+        let steps = ((distance / max_px_per_ms.max(1.0)).ceil() as i32).max(1);
+        let interval = Duration::from_secs_f32(distance / steps as f32 / max_px_per_ms / 1000.0);
+        let (mut moved_x, mut moved_y) = (0, 0);
+        for step in 1..=steps {
+            if step > 1 {
+                self.state().sleep(interval);
+            }
+            // Round the total progress instead of every step, so the steps add up
+            let x = (x_offset as f32 * step as f32 / steps as f32).round() as i32;
+            let y = (y_offset as f32 * step as f32 / steps as f32).round() as i32;
+            if (x, y) != (moved_x, moved_y) {
+                self.move_relative(x - moved_x, y - moved_y)?;
+            }
+            (moved_x, moved_y) = (x, y);
+        }
+        Ok(())
+    }
+    /// Shake the cursor left and right around the current position, e.g. to
+    /// trigger the "shake to locate" of MacOS
+    ///
+    /// Every cycle moves `amplitude` pixels to the right, twice as far to the
+    /// left and back to the start with native relative moves, which are spread
+    /// evenly over the `duration`. The cursor ends up exactly where it started,
+    /// if the position can not be queried the moves still add up to zero
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.shake(40, 4, Duration::from_millis(300)), Ok(()));
+    /// ```
+    fn shake(&self, amplitude: u32, cycles: u32, duration: Duration) -> Result<(), Error> {
+        let amplitude = i32::try_from(amplitude)
+            .ok()
+            .filter(|amplitude| *amplitude <= i32::MAX / 2)
+            .ok_or(Error::CustomError("the amplitude is too large"))?;
+        if amplitude == 0 || cycles == 0 {
+            return Ok(());
+        }
+
+        let start = self.get_position().ok();
+        let interval = duration / (cycles * 3);
+        for step in 0..cycles * 3 {
+            if step > 0 {
+                self.state().sleep(interval);
+            }
+            let x_offset = match step % 3 {
+                1 => -2 * amplitude,
+                _ => amplitude,
+            };
+            self.move_relative(x_offset, 0)?;
+        }
+
+        // The moves can be accelerated or stopped at the edge of the screen
+        if let Some((x, y)) = start {
+            if self.get_position().ok() != start {
+                self.r#move(MoveKind::Absolute(x, y))?;
+            }
+        }
+        Ok(())
+    }
+    /// Move the mouse like an analog stick that is deflected by `x`, `y` for
+    /// the frame time `dt`
+    ///
+    /// The deflection is clamped to -1.0 to 1.0 on each axis, a full deflection
+    /// moves with `max_speed_px_per_sec`. Positive `y` moves down, like
+    /// `move_relative`. Deflections below a small deadzone are ignored, so a
+    /// stick that rests slightly off center does not drift. The sub-pixel
+    /// movement is accumulated across the calls, so a small deflection still
+    /// moves the cursor eventually
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::{thread, time::Duration};
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let frame = Duration::from_millis(16);
+    /// for _ in 0..60 {
+    ///     assert_eq!(manager.move_analog(0.5, -0.25, 800.0, frame), Ok(()));
+    ///     thread::sleep(frame);
+    /// }
+    /// ```
+    fn move_analog(
+        &mut self,
+        x: f32,
+        y: f32,
+        max_speed_px_per_sec: f32,
+        dt: Duration,
+    ) -> Result<(), Error> {
+        const DEADZONE: f32 = 0.1;
+
+        if max_speed_px_per_sec.is_nan() || max_speed_px_per_sec < 0.0 {
+            return Err(Error::CustomError("the maximum speed must not be negative"));
+        }
+        let (x, y) = (x.clamp(-1.0, 1.0), y.clamp(-1.0, 1.0));
+        if x.is_nan() || y.is_nan() || x.hypot(y) < DEADZONE {
+            return Ok(());
+        }
+
+        let distance = max_speed_px_per_sec * dt.as_secs_f32();
+        let (x_offset, y_offset) = {
+            let mut remainder = self.state().settings.analog_remainder.lock().unwrap();
+            remainder.0 += x * distance;
+            remainder.1 += y * distance;
+            (take_whole(&mut remainder.0), take_whole(&mut remainder.1))
+        };
+        if (x_offset, y_offset) == (0, 0) {
+            return Ok(());
+        }
+        self.move_relative(x_offset, y_offset)
+    }
+    /// Get the current position of the mouse
+    ///
+    /// # Examples
+    ///
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let manager = Mouse::new();
+    /// manager.move_to(0, 0);
+    /// // This function may not be implemented on some platforms such as Linux Wayland
+    /// let valid_outs = vec![Ok((0, 0)), Err(Error::NotImplemented)];
+    /// assert!(valid_outs.contains(&manager.get_position()));
+    /// ```
+    fn get_position(&self) -> Result<(i32, i32), Error>;
+    /// Get the width and height of the main display in pixels
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let manager = Mouse::new();
+    /// // This function may not be implemented on some platforms such as Linux Wayland
+    /// match manager.get_display_size() {
+    ///     Ok((width, height)) => println!("The display is {width}x{height}"),
+    ///     Err(err) => assert_eq!(Error::NotImplemented, err),
+    /// }
+    /// ```
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the primary monitor of the system
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(monitor) = manager.get_primary_monitor() {
+    ///     println!("The primary monitor starts at ({}, {})", monitor.x, monitor.y);
+    /// }
+    /// ```
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the work area of the primary monitor, the part of it that is not
+    /// covered by the taskbar, the Dock or the panels
+    ///
+    /// - On Windows it is `SPI_GETWORKAREA`
+    /// - On MacOS it is the visible frame of the primary screen, which excludes
+    ///   the menu bar and the Dock
+    /// - On X11 it is the `_NET_WORKAREA` of the first desktop, which is set by
+    ///   most window managers, the ones that do not set it fail
+    /// - It is not implemented on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(area) = manager.get_work_area() {
+    ///     println!("The windows can use {}x{} at ({}, {})", area.width, area.height, area.x, area.y);
+    /// }
+    /// ```
+    fn get_work_area(&self) -> Result<Monitor, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates, clamped into the work
+    /// area, so the automation does not click the taskbar or the Dock by accident
+    ///
+    /// The coordinates are in the configured `CoordinateOrigin`, they are
+    /// converted to clamp them into the work area, which is always top-left.
+    /// This fails without moving where `get_work_area` is not available
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Stops above the taskbar at the bottom of the screen
+    /// assert_eq!(manager.move_to_work_area_clamped(100, 100_000), Ok(()));
+    /// ```
+    fn move_to_work_area_clamped(&self, x: i32, y: i32) -> Result<(), Error> {
+        let (x, y) = self.get_work_area()?.clamp(x, convert_origin(self, y)?);
+        self.r#move(MoveKind::Absolute(x, convert_origin(self, y)?))
+    }
+    /// Get the pointer devices of XInput2, the master pointers and the
+    /// physical pointers, i.e. the slave and the floating ones
+    ///
+    /// The X server has a cursor for each of the master pointers, there is a
+    /// single one unless more are created for multi-pointer (MPX) setups, e.g.
+    /// with `xinput create-master`. The physical devices are slave pointers
+    /// that are attached to a master pointer and move its cursor together. A
+    /// floating pointer is not attached to any master pointer
+    ///
+    /// Only implemented on X11, `Error::NotImplemented` is returned for the
+    /// rest and where the X server does not support XInput 2
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// for pointer in manager.list_pointers().unwrap_or_default() {
+    ///     println!("{} ({:?}): {}", pointer.id, pointer.role, pointer.name);
+    /// }
+    /// ```
+    fn list_pointers(&self) -> Result<Vec<PointerDevice>, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Send the output of this mouse manager to the cursor of the given pointer
+    /// device, see `list_pointers`
+    ///
+    /// The output always goes to a master pointer, for a slave pointer it goes
+    /// to the master pointer it is attached to. A floating pointer has no
+    /// cursor and fails with `Error::CustomError`. On X11 this sets the client
+    /// pointer of the connection, which only redirects the core requests: the
+    /// warps of `move_to` and `move_to_silent` and the position queries use
+    /// the cursor of that master pointer. The XTest events, i.e. the clicks,
+    /// the scrolls and the relative moves, still go to the first master
+    /// pointer. `reset` sets the client pointer back
+    ///
+    /// Only implemented on X11, `Error::NotImplemented` is returned for the
+    /// rest and where the X server does not support XInput 2
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::PointerRole;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let pointers = manager.list_pointers().unwrap();
+    /// // Drive the second cursor of a multi-pointer setup
+    /// if let Some(second) = pointers.iter().filter(|pointer| pointer.role == PointerRole::Master).nth(1) {
+    ///     assert_eq!(manager.set_active_pointer(second), Ok(()));
+    /// }
+    /// ```
+    fn set_active_pointer(&mut self, _device: &PointerDevice) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get all of the active monitors of the system, in no particular order
+    ///
+    /// The platforms that can not list the monitors only return the primary one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// for monitor in manager.get_monitors().unwrap_or_default() {
+    ///     println!("{}x{} at ({}, {})", monitor.width, monitor.height, monitor.x, monitor.y);
+    /// }
+    /// ```
+    fn get_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        Ok(vec![self.get_primary_monitor()?])
+    }
+    /// Get the refresh rate of the primary display in Hz
+    ///
+    /// `Error::NotImplemented` is returned where the refresh rate is not known,
+    /// e.g. some built-in displays of MacOS do not report it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(refresh_hz) = manager.display_refresh_hz() {
+    ///     println!("The display refreshes at {refresh_hz} Hz");
+    /// }
+    /// ```
+    fn display_refresh_hz(&self) -> Result<f32, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Make the process per-monitor DPI aware on Windows, so `get_position`,
+    /// `move_to` and the rest use the physical pixels of every monitor
+    ///
+    /// Without it the coordinates of a process that is not DPI aware are scaled
+    /// by the DPI of the primary monitor, which puts the cursor at the wrong
+    /// position on the monitors with a different scaling. The awareness is set
+    /// for the whole process and only once, so call this early, before any
+    /// window is created. It fails if the awareness was already set, e.g. by the
+    /// manifest of the application, and the windows that exist before the call
+    /// keep their old awareness. `Error::NotImplemented` is returned on the
+    /// other platforms and on the Windows versions before Windows 10 1703
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// if manager.set_dpi_awareness().is_ok() {
+    ///     assert_eq!(manager.move_to(1920, 540), Ok(()));
+    /// }
+    /// ```
+    fn set_dpi_awareness(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the effective DPI of the monitor that contains the given point, or
+    /// the nearest monitor if none does, where 96 is 100% scaling
+    ///
+    /// The DPI is only reported per monitor when the process is DPI aware, see
+    /// `set_dpi_awareness`. `Error::NotImplemented` is returned on the platforms
+    /// other than Windows and on the Windows versions before Windows 8.1
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(dpi) = manager.get_dpi_for_point(0, 0) {
+    ///     println!("The primary monitor is scaled to {}%", dpi * 100 / 96);
+    /// }
+    /// ```
+    fn get_dpi_for_point(&self, _x: i32, _y: i32) -> Result<u32, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Send the moves and clicks to the given X screen instead of the default
+    /// one, e.g. on a multi-seat setup or a rig that drives separate displays
+    ///
+    /// An X display can have multiple screens, each with its own root window,
+    /// they are numbered from 0 and selected with the `.N` part of the display
+    /// name, e.g. `DISPLAY=:0.1` is the screen 1 of the display `:0`. These are
+    /// not the monitors of XRandR or Xinerama, which are all parts of one screen.
+    /// After this `move_to` moves the pointer onto the given screen, so the
+    /// clicks follow it there, and `get_position`, `get_display_size` and
+    /// `get_primary_monitor` report that screen
+    ///
+    /// Only X11 has screens, `Error::NotImplemented` is returned elsewhere
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.set_target_screen(1).unwrap();
+    /// manager.move_to(100, 100).unwrap();
+    /// manager.click_button(&MouseButton::Left).unwrap();
+    /// ```
+    fn set_target_screen(&mut self, _screen: i32) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the global coordinates of the top-left corner of the client area of
+    /// the focused window, which excludes the title bar and the borders
+    ///
+    /// The focused window is resolved differently on each platform
+    /// - On Windows it is the foreground window, `GetForegroundWindow`
+    /// - On X11 it is the `_NET_ACTIVE_WINDOW` property of the root window, which
+    ///   is set by most window managers, the ones that do not set it fail
+    /// - It is not implemented on MacOS and uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok((x, y)) = manager.get_active_window_origin() {
+    ///     println!("The focused window starts at ({x}, {y})");
+    /// }
+    /// ```
+    fn get_active_window_origin(&self) -> Result<(i32, i32), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Click the given mouse button at the given `x`, `y` coordinates of the
+    /// client area of the focused window
+    ///
+    /// The coordinates are relative to the top-left corner of the client area,
+    /// see `get_active_window_origin` for how the focused window is resolved
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_active_window(20, 40, &MouseButton::Left), Ok(()));
+    /// ```
+    fn click_active_window(&self, x: i32, y: i32, button: &MouseButton) -> Result<(), Error> {
+        let (origin_x, origin_y) = self.get_active_window_origin()?;
+        self.r#move(MoveKind::Absolute(origin_x + x, origin_y + y))?;
+        self.click_button(button)
+    }
+    /// Get the current shape of the mouse cursor
+    ///
+    /// Useful to wait until an application is no longer busy. Only supported
+    /// on Windows and X11 (with the XFixes extension), `Error::NotImplemented`
+    /// is returned for the rest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::CursorShape;
+    /// use std::{thread, time::Duration};
+    ///
+    /// let manager = Mouse::new();
+    /// while manager.get_cursor_shape() == Ok(CursorShape::Wait) {
+    ///     thread::sleep(Duration::from_millis(100));
+    /// }
+    /// ```
+    fn get_cursor_shape(&self) -> Result<CursorShape, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the pointer button mapping of the X server, which is changed with
+    /// e.g. `xmodmap` to swap the buttons for left-handed users
+    ///
+    /// The element at index `i` is the logical button that the physical button
+    /// `i + 1` is mapped to, 0 means the physical button is disabled. The
+    /// default mapping is `[1, 2, 3, ...]`, with swapped primary and secondary
+    /// buttons it starts with `[3, 2, 1]`. The X11 backend applies the mapping
+    /// to the buttons it sends, so `MouseButton::Left` is always the primary
+    /// button of the user. Only implemented on X11, `Error::NotImplemented` is
+    /// returned for the rest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(mapping) = manager.get_pointer_mapping() {
+    ///     let swapped = mapping.starts_with(&[3, 2, 1]);
+    ///     println!("The primary and secondary buttons are swapped: {swapped}");
+    /// }
+    /// ```
+    fn get_pointer_mapping(&self) -> Result<Vec<u8>, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the longest time between two clicks for them to count as a double click
+    ///
+    /// - On Windows it is the configured double click time
+    /// - On MacOS it is `NSEvent.doubleClickInterval`
+    /// - On X11 it is the `multiClickTime` resource, which is 200ms when it is not set
+    /// - It is not implemented on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(time) = manager.double_click_time() {
+    ///     println!("Double clicks are at most {}ms apart", time.as_millis());
+    /// }
+    /// ```
+    fn double_click_time(&self) -> Result<Duration, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the width in pixels of the area around the first click that the second
+    /// click of a double click has to be in
+    ///
+    /// Only implemented on Windows, `Error::NotImplemented` is returned for the rest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(distance) = manager.double_click_distance() {
+    ///     println!("Double clicks are within a {distance}px wide area");
+    /// }
+    /// ```
+    fn double_click_distance(&self) -> Result<u32, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get how far in pixels the mouse has to move horizontally and vertically
+    /// with a button held before the move is seen as a drag
+    ///
+    /// Only implemented on Windows, `Error::NotImplemented` is returned for the rest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok((width, height)) = manager.drag_threshold() {
+    ///     println!("Drags start after moving {width}px or {height}px");
+    /// }
+    /// ```
+    fn drag_threshold(&self) -> Result<(u32, u32), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Drag with the given button from `from` to `to` in a way that starts the
+    /// drag and drop of the system, e.g. to move a file between windows
+    ///
+    /// A press, move and release may be too abrupt for the system to enter its
+    /// drag loop. Instead, the button is pressed at `from`, the mouse is nudged
+    /// towards `to` by a pixel and then past the `drag_threshold`, which starts
+    /// the drag, before it moves to `to` and the button is released there. The
+    /// threshold of Windows is assumed where it is not known. The button is
+    /// released even if one of the moves fails
+    ///
+    /// This only drives the pointer like a user would, the data of the drag is
+    /// up to the applications, e.g. through OLE on Windows
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // Drop a file from the desktop into a window
+    /// assert_eq!(
+    ///     manager.drag_and_drop(&MouseButton::Left, (80, 120), (900, 400)),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn drag_and_drop(
+        &self,
+        button: &MouseButton,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Result<(), Error> {
+        let (threshold_x, threshold_y) = self.drag_threshold().unwrap_or(DEFAULT_DRAG_THRESHOLD);
+        let direction_x = (to.0 as i64 - from.0 as i64).signum() as i32;
+        let direction_y = (to.1 as i64 - from.1 as i64).signum() as i32;
+        // Dragging in place still has to leave the threshold to start the drag
+        let direction_x = if (direction_x, direction_y) == (0, 0) {
+            1
+        } else {
+            direction_x
+        };
+
+        self.move_to(from.0, from.1)?;
+        self.press_button(button)?;
+        let moved = self
+            .move_relative(direction_x, direction_y)
+            .and_then(|_| {
+                self.move_relative(
+                    direction_x * threshold_x as i32,
+                    direction_y * threshold_y as i32,
+                )
+            })
+            .and_then(|_| self.move_to(to.0, to.1));
+        let released = self.release_button(button);
+        moved.and(released)
+    }
+    /// Select the rectangle between the corners `from` and `to` by dragging
+    /// across it with the given button, i.e. a rubber-band selection of the
+    /// file managers and canvases
+    ///
+    /// The button is pressed at `from`, the mouse moves to `to` with
+    /// `move_to_smooth` within `duration` and the button is released there.
+    /// The mouse rests for a frame of the display after the press and before
+    /// the release, so the applications see the press before the first move
+    /// and the last move before the release. A rectangle without an area, i.e.
+    /// `from` equal to `to`, is still pressed and released. The button is
+    /// released even if one of the moves fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // Select the icons in the top-left corner of the desktop
+    /// assert_eq!(
+    ///     manager.select_rectangle((10, 10), (300, 200), &MouseButton::Left, Duration::from_millis(300)),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn select_rectangle(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        button: &MouseButton,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let refresh_hz = self.display_refresh_hz().unwrap_or(DEFAULT_REFRESH_HZ);
+        let frame = Duration::from_secs_f32(1.0 / refresh_hz);
+
+        self.move_to(from.0, from.1)?;
+        self.press_button(button)?;
+        self.state().sleep(frame);
+        let moved = self.move_to_smooth(to.0, to.1, duration);
+        self.state().sleep(frame);
+        let released = self.release_button(button);
+        moved.and(released)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates of the primary monitor
+    ///
+    /// The coordinates are relative to the top-left corner of the primary
+    /// monitor, so they target the same point on it regardless of how the
+    /// monitors are arranged. They are converted to global coordinates by adding
+    /// the origin of the monitor, e.g. (10, 20) on a primary monitor that starts
+    /// at (1920, 0) becomes (1930, 20)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Move the mouse to the top-left corner of the primary monitor
+    /// assert_eq!(manager.move_to_on_primary(0, 0), Ok(()));
+    /// ```
+    fn move_to_on_primary(&self, x: usize, y: usize) -> Result<(), Error> {
+        let monitor = self.get_primary_monitor()?;
+        let global_x = (monitor.x + x as i32).max(0);
+        let global_y = (monitor.y + y as i32).max(0);
+        self.move_to(global_x as usize, global_y as usize)
+    }
+    /// Move the mouse to the given percentages (0.0 to 100.0) of the display size
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Move the mouse to the center of the display
+    /// assert_eq!(manager.move_to_percent(50.0, 50.0), Ok(()));
+    /// ```
+    fn move_to_percent(&self, x_percent: f32, y_percent: f32) -> Result<(), Error> {
+        let (width, height) = self.get_display_size()?;
+        let x = width as f32 * x_percent.clamp(0.0, 100.0) / 100.0;
+        let y = height as f32 * y_percent.clamp(0.0, 100.0) / 100.0;
+        self.move_to(x.round() as usize, y.round() as usize)
+    }
+    /// Move the mouse to the given fractions (0.0 to 1.0) of the virtual desktop
+    ///
+    /// On Windows the virtual desktop spans all of the monitors and the fractions
+    /// are sent as the native normalized coordinates of `SendInput`, without
+    /// converting them to pixels first. This is the most precise way to position
+    /// the mouse on high DPI setups with multiple monitors. The other platforms
+    /// convert them to pixels of `get_display_size`, 1.0 is the last pixel
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Move the mouse to the center of the desktop
+    /// assert_eq!(manager.move_to_normalized(0.5, 0.5), Ok(()));
+    /// ```
+    fn move_to_normalized(&self, x: f64, y: f64) -> Result<(), Error> {
+        let (width, height) = self.get_display_size()?;
+        let x = width.saturating_sub(1) as f64 * x.clamp(0.0, 1.0);
+        let y = height.saturating_sub(1) as f64 * y.clamp(0.0, 1.0);
+        self.move_to(x.round() as usize, y.round() as usize)
+    }
+    /// Move the mouse as described by the given `MoveKind`
+    ///
+    /// This is a single entry point for all of the move functions, which makes
+    /// it easy to replay recorded move events. Negative absolute coordinates
+    /// are clamped to 0
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MoveKind;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.r#move(MoveKind::Absolute(0, 0)), Ok(()));
+    /// assert_eq!(manager.r#move(MoveKind::Relative(100, 100)), Ok(()));
+    /// ```
+    fn r#move(&self, kind: MoveKind) -> Result<(), Error> {
+        match kind {
+            MoveKind::Absolute(x, y) => self.move_to(x.max(0) as usize, y.max(0) as usize),
+            MoveKind::Relative(x_offset, y_offset) => self.move_relative(x_offset, y_offset),
+            MoveKind::Percent(x_percent, y_percent) => self.move_to_percent(x_percent, y_percent),
+        }
+    }
+    /// Confine the cursor to the rectangle with the given top-left corner,
+    /// `width` and `height` until the returned guard is dropped
+    ///
+    /// The platforms differ in how the cursor is confined
+    /// - On Windows `ClipCursor` is used, the other applications keep receiving
+    ///   the mouse events as usual. Windows may reset the clip by itself, e.g.
+    ///   when the focused window changes
+    /// - On X11 the pointer is grabbed with a confining window, while the pointer
+    ///   is grabbed the other applications do not receive any pointer events
+    /// - It is not implemented on MacOS and uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(clip) = manager.confine_cursor(100, 100, 640, 480) {
+    ///     // The cursor can not leave the rectangle until `clip` is dropped
+    ///     drop(clip);
+    /// }
+    /// ```
+    fn confine_cursor(
+        &self,
+        _x: i32,
+        _y: i32,
+        _width: usize,
+        _height: usize,
+    ) -> Result<CursorClip, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Check whether the pointer is grabbed or confined, e.g. by a fullscreen
+    /// game, in which case `move_to` may not move the cursor where it is told
+    ///
+    /// - On X11 a grab of the pointer is attempted and released right away,
+    ///   the pointer is grabbed if another client holds it
+    /// - On Windows the pointer is confined if the clip rectangle of the cursor
+    ///   does not cover the whole virtual desktop
+    /// - MacOS has no way to query whether the cursor is dissociated from the
+    ///   mouse, so it is not implemented there and on uinput
+    ///
+    /// A cursor confined by `confine_cursor` of this library counts as well
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if manager.is_pointer_grabbed() == Ok(true) {
+    ///     println!("Another application holds the pointer, the moves may fail");
+    /// }
+    /// ```
+    fn is_pointer_grabbed(&self) -> Result<bool, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Press down the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error>;
+    /// Release the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.release_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error>;
+    /// Release all of the buttons of `supported_buttons`, whether they are
+    /// pressed or not, e.g. to not leave a button stuck down when the automation
+    /// fails in the middle of a drag
+    ///
+    /// Releasing a button that is not pressed does nothing. All of the buttons
+    /// are released even if one of them fails, the first error is returned
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// std::panic::set_hook(Box::new(|_| {
+    ///     let _ = Mouse::new().release_all_buttons();
+    /// }));
+    /// ```
+    fn release_all_buttons(&self) -> Result<(), Error> {
+        self.supported_buttons()
+            .iter()
+            .map(|button| self.release_button(button))
+            .fold(Ok(()), Result::and)
+    }
+    /// Click the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.press_button(button)?;
+        self.release_button(button)
+    }
+    /// Hold exactly the given modifiers for the following actions, releasing
+    /// the ones that were held before but are not given. An empty slice releases
+    /// all of them. See `with_modifiers` for a scoped version
+    ///
+    /// On MacOS the modifier flags are set on the events that mouce creates,
+    /// the keyboard is not touched. On Windows and X11 the modifier keys are
+    /// pressed and released, uinput and the other platforms return
+    /// `Error::NotImplemented`. The keys that are still held are released by
+    /// `shutdown` and on drop, unless a manager of `output_handle` still
+    /// shares them
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{Modifier, MouseButton};
+    ///
+    /// let manager = Mouse::new();
+    /// // Shift-click
+    /// assert_eq!(manager.hold_modifiers(&[Modifier::Shift]), Ok(()));
+    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    /// assert_eq!(manager.hold_modifiers(&[]), Ok(()));
+    /// ```
+    fn hold_modifiers(&self, _modifiers: &[Modifier]) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Click the given mouse button at the current position of the cursor,
+    /// optionally nudging the cursor first
+    ///
+    /// Some applications, e.g. certain web widgets, ignore a click that is not
+    /// preceded by any motion, as they only update the hovered element on a
+    /// move. With `nudge` the cursor is moved by 1 pixel to the right and back
+    /// before the click, so it ends up at the same position. Without it this
+    /// is the same as `click_button`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_here(&MouseButton::Left, true), Ok(()));
+    /// ```
+    fn click_here(&self, button: &MouseButton, nudge: bool) -> Result<(), Error> {
+        if nudge {
+            self.move_relative(1, 0)?;
+            self.move_relative(-1, 0)?;
+        }
+        self.click_button(button)
+    }
+    /// Move to the given point and open the context menu there
+    ///
+    /// The right button opens the context menu on all of the platforms, so
+    /// this moves the cursor and clicks it. The keyboard triggers, e.g. Shift+F10
+    /// or the menu key on Windows, are not used since they open the menu of the
+    /// focused element instead of the one under the cursor. Where an application
+    /// only opens it on a Control-click, e.g. some older MacOS applications,
+    /// click the left button within `with_modifiers` and `Modifier::Control`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.open_context_menu(400, 300), Ok(()));
+    /// ```
+    fn open_context_menu(&self, x: usize, y: usize) -> Result<(), Error> {
+        self.move_to(x, y)?;
+        self.click_button(&MouseButton::Right)
+    }
+    /// Move to each of the given points and click the given mouse button
+    /// there, waiting `between` after each click but the last one
+    ///
+    /// This is meant for the batch operations on a grid, e.g. ticking several
+    /// checkboxes. An empty slice does nothing. The clicks stop at the first
+    /// point that fails, `Error::BatchFailed` carries its index and the error
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let checkboxes = [(40, 100), (40, 130), (40, 160)];
+    /// assert_eq!(
+    ///     manager.click_points(&checkboxes, &MouseButton::Left, Duration::from_millis(50)),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn click_points(
+        &self,
+        points: &[(usize, usize)],
+        button: &MouseButton,
+        between: Duration,
+    ) -> Result<(), Error> {
+        for (index, &(x, y)) in points.iter().enumerate() {
+            if index > 0 {
+                self.state().sleep(between);
+            }
+            self.move_to(x, y)
+                .and_then(|_| self.click_button(button))
+                .map_err(|err| Error::BatchFailed(index, Box::new(err)))?;
+        }
+        Ok(())
+    }
+    /// Click the given mouse button while moving the mouse relatively along the
+    /// given `path` between pressing and releasing the button
+    ///
+    /// This simulates imprecise clicks where the pointer moves slightly before
+    /// the button is released. An empty `path` behaves like `click_button`. The
+    /// button is released even if one of the moves fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let path = [(1, 0), (0, 1), (-1, 0)];
+    /// assert_eq!(manager.click_with_movement(&MouseButton::Left, &path), Ok(()));
+    /// ```
+    fn click_with_movement(&self, button: &MouseButton, path: &[(i32, i32)]) -> Result<(), Error> {
+        if path.is_empty() {
+            return self.click_button(button);
+        }
+
+        self.press_button(button)?;
+        let moved = path
+            .iter()
+            .try_for_each(|(x_offset, y_offset)| self.move_relative(*x_offset, *y_offset));
+        let released = self.release_button(button);
+        moved.and(released)
+    }
+    /// Press the given mouse button at the position of the cursor, hold it for
+    /// `hold` and release it, e.g. for the touch enabled applications that open
+    /// the options of an item on a long press
+    ///
+    /// Unlike `click_button_delayed`, the hold is always timed precisely, see
+    /// `set_precise_timing`, and nothing is sent between the press and the
+    /// release, as some applications cancel a long press on the slightest move.
+    /// With `keep_still` the cursor is also pinned to its position during the
+    /// hold, so a bump of the physical mouse does not move it either
+    /// - On Windows the cursor is confined to its pixel with `ClipCursor`
+    /// - On MacOS the cursor is detached from the mouse with
+    ///   `CGAssociateMouseAndMouseCursorPosition`, the applications still
+    ///   receive the movement of the mouse
+    /// - The other platforms can not pin the cursor without taking its events
+    ///   away from the applications, so it has no effect there
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // Open the options of the item under the cursor
+    /// assert_eq!(manager.long_press(&MouseButton::Left, Duration::from_millis(800), true), Ok(()));
+    /// ```
+    fn long_press(
+        &self,
+        button: &MouseButton,
+        hold: Duration,
+        _keep_still: bool,
+    ) -> Result<(), Error> {
+        self.press_button(button)?;
+        self.state().sleep_precisely(hold);
+        self.release_button(button)
+    }
+    /// Click the given mouse button with the specified delay for "pressing" and "releasing" the button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// let press_delay = Duration::from_millis(200); //wait 200ms before pressing the button
+    /// let release_delay = Duration::from_millis(200); // hold the button for 200ms and then release it
+    /// assert_eq!(manager.click_button_delayed(&MouseButton::Left,Some(press_delay),Some(release_delay)), Ok(()));
+    /// ```
+    fn click_button_delayed(
+        &self,
+        button: &MouseButton,
+        press_delay: Option<Duration>,
+        release_delay: Option<Duration>,
+    ) -> Result<(), Error> {
+        if let Some(delay) = press_delay {
+            self.state().sleep(delay);
+        }
+        self.press_button(button)?;
+        if let Some(delay) = release_delay {
+            self.state().sleep(delay);
+        }
+        self.release_button(button)
+    }
+    /// Click the given mouse button `count` times, waiting `inter_click` between
+    /// the release of a click and the press of the next one
+    ///
+    /// The caller controls the timing instead of the double click time of the
+    /// platform, e.g. to test how an application handles clicks that are just
+    /// outside of its double click threshold. A `count` of 0 does nothing
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // A triple click
+    /// let result = manager.click_sequence(&MouseButton::Left, 3, Duration::from_millis(80));
+    /// assert_eq!(result, Ok(()));
+    /// ```
+    fn click_sequence(
+        &self,
+        button: &MouseButton,
+        count: u32,
+        inter_click: Duration,
+    ) -> Result<(), Error> {
+        for click in 0..count {
+            if click > 0 {
+                self.state().sleep(inter_click);
+            }
+            self.click_button(button)?;
+        }
+        Ok(())
+    }
+    /// Click the given mouse button like a human would, holding the button for a
+    /// random duration within the click hold range (60ms to 120ms by default)
+    ///
+    /// Some applications flag clicks that are released impossibly fast, see
+    /// `set_click_hold_range` to configure the range and `seed_rng` to make the
+    /// hold durations reproducible
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_realistic(&MouseButton::Left), Ok(()));
+    /// ```
+    fn click_realistic(&self, button: &MouseButton) -> Result<(), Error> {
+        let (min, max) = *self.state().settings.click_hold_range.lock().unwrap();
+        let hold = self
+            .state()
+            .settings
+            .rng
+            .lock()
+            .unwrap()
+            .duration_between(min, max);
+        self.click_button_delayed(button, None, Some(hold))
+    }
+    /// Set the range of the durations `click_realistic` holds the button for
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.set_click_hold_range(Duration::from_millis(80), Duration::from_millis(100));
+    /// ```
+    fn set_click_hold_range(&mut self, min: Duration, max: Duration) {
+        *self.state().settings.click_hold_range.lock().unwrap() = (min.min(max), min.max(max));
+    }
+    /// Make the delays of the mouse actions, e.g. the hold of `click_button_delayed`,
+    /// more accurate by spinning for the last 2ms of every sleep instead of relying
+    /// on the timer resolution of the OS, which can be as coarse as 15ms on windows
+    ///
+    /// Spinning keeps a CPU core busy for up to 2ms per delay, so only enable this
+    /// when the timing matters more than the CPU usage
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::{Mouse, common::MouseButton};
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.set_precise_timing(true);
+    /// manager.click_button_delayed(&MouseButton::Left, None, Some(Duration::from_millis(2)));
+    /// ```
+    fn set_precise_timing(&mut self, enabled: bool) {
+        *self.state().settings.precise_timing.lock().unwrap() = enabled;
+    }
+    /// Send the absolute moves of `move_to` as native relative moves from the
+    /// current position, for the remote desktops and virtual machines where the
+    /// absolute positioning is unreliable but the relative motion works
+    ///
+    /// This requires `get_position` to work, `move_to` fails where it does not.
+    /// The relative moves go through the pointer acceleration of the system on
+    /// some setups, e.g. on Windows with "Enhance pointer precision" enabled, so
+    /// the cursor may not end up exactly at the given position. uinput always
+    /// moves relatively, so this has no effect there
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.prefer_relative_moves(true);
+    /// assert_eq!(manager.move_to(200, 200), Ok(()));
+    /// ```
+    fn prefer_relative_moves(&mut self, enabled: bool) {
+        *self.state().settings.relative_moves.lock().unwrap() = enabled;
+    }
+    /// Set where (0, 0) is for `move_to` and `get_position`, the default is
+    /// `CoordinateOrigin::TopLeft` like on all of the platforms
+    ///
+    /// With `CoordinateOrigin::BottomLeft` the `y` of these two is mirrored on
+    /// the primary display, `y = height - 1 - y` with the height of
+    /// `get_display_size`, so they fail where the display size is not known,
+    /// e.g. on uinput. The bottom row of the display is `y = 0` then. The rest
+    /// of the functions that take or return coordinates directly, e.g.
+    /// `move_relative`, the monitors and the hooked events, keep the top-left
+    /// origin of the platform
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::CoordinateOrigin;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.set_coordinate_origin(CoordinateOrigin::BottomLeft);
+    /// // Move to 100 pixels above the bottom edge of the primary display
+    /// assert_eq!(manager.move_to(0, 100), Ok(()));
+    /// ```
+    fn set_coordinate_origin(&mut self, origin: CoordinateOrigin) {
+        *self.state().settings.coordinate_origin.lock().unwrap() = origin;
+    }
+    /// Send other buttons instead of the given ones, e.g. `Left` to `Right`
+    /// makes `press_button(&MouseButton::Left)` press the right button. The
+    /// buttons that are not in the `map` are sent as they are and an empty
+    /// `map` removes the remapping
+    ///
+    /// The map applies to every function that sends a button, e.g. the clicks,
+    /// the drags and `atomic`, but not to the hooked events, which are the
+    /// buttons that arrived. It is applied before the button reaches the
+    /// system, so the button mapping of the system, e.g. the swapped buttons of
+    /// a left-handed user, still applies on top of it as it does for a physical
+    /// mouse. Except on X11, where the pointer mapping is undone before sending,
+    /// so the applications receive the buttons of this map as they are
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::collections::HashMap;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let mut manager = Mouse::new();
+    /// // Swap the buttons to test the left-handed setup of an application
+    /// manager.set_button_map(HashMap::from([
+    ///     (MouseButton::Left, MouseButton::Right),
+    ///     (MouseButton::Right, MouseButton::Left),
+    /// ]));
+    /// // Clicks the right button
+    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn set_button_map(&mut self, map: HashMap<MouseButton, MouseButton>) {
+        *self.state().settings.button_map.lock().unwrap() = map;
+    }
+    /// Set the mouse manager back to its defaults without recreating it, so the
+    /// connection to the backend is kept, e.g. between the jobs of a daemon
+    ///
+    /// The following is reset
+    /// - The configuration: `set_click_hold_range`, `seed_rng` (the generator is
+    ///   seeded from the time again), `set_precise_timing`, `prefer_relative_moves`,
+    ///   `set_coordinate_origin`, `set_max_scroll_rate`, `set_button_map`,
+    ///   `set_target_screen`, `set_mark_synthetic` and `set_active_pointer`
+    /// - The cached state: the fractional scroll of `scroll_fractional`, the
+    ///   sub-pixel movement of `move_analog` and the position of the last click
+    ///
+    /// The following is kept
+    /// - The hooked callbacks with their labels and the listener threads
+    /// - The priority of `with_listener_priority`, which the listener threads
+    ///   are started with
+    /// - The held modifiers, they are still held down on the system
+    /// - The events queued with `schedule`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.prefer_relative_moves(true);
+    /// manager.set_max_scroll_rate(Some(20));
+    /// manager.reset();
+    /// ```
+    fn reset(&mut self) {
+        self.state().reset();
+    }
+    /// Poll the cursor position `count` times, `interval` apart, and return the
+    /// samples, e.g. to analyze the motion where hooking needs privileges
+    ///
+    /// The first sample is taken right away. The samples are scheduled on a
+    /// monotonic clock, so the time `get_position` takes does not add up. This
+    /// fails right away where `get_position` is not available, e.g. on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Sample the cursor for a second
+    /// let samples = manager.sample_positions(Duration::from_millis(10), 100);
+    /// if let Ok(samples) = samples {
+    ///     println!("The cursor moved through {samples:?}");
+    /// }
+    /// ```
+    fn sample_positions(&self, interval: Duration, count: usize) -> Result<Vec<(i32, i32)>, Error> {
+        let start = self.state().now();
+        let mut samples = Vec::with_capacity(count);
+        for sample in 0..count {
+            let elapsed = self.state().now().duration_since(start);
+            let remaining = (interval * sample as u32).saturating_sub(elapsed);
+            if !remaining.is_zero() {
+                self.state().sleep(remaining);
+            }
+            samples.push(self.get_position()?);
+        }
+        Ok(samples)
+    }
+    /// Poll the cursor position every `interval` on a background thread and
+    /// invoke the callback with the new position whenever it changes
+    ///
+    /// This is an alternative to hooking the moves that needs no privileges,
+    /// at the cost of missing the positions between the polls. The polling
+    /// stops when the returned handle is dropped. The thread polls through the
+    /// manager of `output_handle`, the polls that fail are skipped. This fails
+    /// right away where `get_position` or `output_handle` is not available,
+    /// e.g. on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// let watch = manager.watch_position(
+    ///     Duration::from_millis(50),
+    ///     Box::new(|(x, y)| println!("The cursor is at {x}, {y}")),
+    /// );
+    /// std::thread::sleep(Duration::from_secs(5));
+    /// drop(watch);
+    /// ```
+    fn watch_position(
+        &self,
+        interval: Duration,
+        callback: Box<dyn Fn((i32, i32)) + Send>,
+    ) -> Result<WatchHandle, Error> {
+        let position = self.get_position()?;
+        let output = self.output_handle()?;
+        Ok(WatchHandle::spawn(
+            position,
+            interval,
+            callback,
+            move || output.get_position(),
+        ))
+    }
+    /// Move the mouse to the given `x`, `y` coordinates and wait until the
+    /// cursor is confirmed to be within `tolerance` pixels of them on both axes
+    ///
+    /// On laggy or remote displays `move_to` may return before the cursor
+    /// arrives. The position is polled every 10ms until it is close enough,
+    /// `Error::MoveNotConfirmed` with the last polled position is returned if
+    /// it is not after `timeout`. This fails before moving where `get_position`
+    /// is not available, e.g. on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(
+    ///     manager.move_to_verified(100, 200, 1, Duration::from_millis(500)),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn move_to_verified(
+        &self,
+        x: usize,
+        y: usize,
+        tolerance: u32,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.get_position()?;
+        self.move_to(x, y)?;
+        let deadline = self.state().now() + timeout;
+        loop {
+            let (current_x, current_y) = self.get_position()?;
+            if (current_x as i64 - x as i64).unsigned_abs() <= tolerance as u64
+                && (current_y as i64 - y as i64).unsigned_abs() <= tolerance as u64
+            {
+                return Ok(());
+            }
+            let remaining = deadline.saturating_duration_since(self.state().now());
+            if remaining.is_zero() {
+                return Err(Error::MoveNotConfirmed(current_x, current_y));
+            }
+            self.state().sleep(remaining.min(VERIFY_POLL_INTERVAL));
+        }
+    }
+    /// Seed the random number generator that is used to humanize the mouse
+    /// actions, the same seed produces the same sequence of random actions
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.seed_rng(42);
+    /// ```
+    fn seed_rng(&mut self, seed: u64) {
+        *self.state().settings.rng.lock().unwrap() = Rng::new(seed);
+    }
+    /// Perform the given actions in order, stopping at the first action that fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::{Action, MouseButton};
+    ///
+    /// let manager = Mouse::new();
+    /// let actions = [
+    ///     Action::MoveTo(100, 100),
+    ///     Action::Click(MouseButton::Left),
+    ///     Action::Sleep(Duration::from_millis(500)),
+    ///     Action::Drag(MouseButton::Left, 300, 100),
+    /// ];
+    /// assert_eq!(manager.execute(&actions), Ok(()));
+    /// ```
+    fn execute(&self, actions: &[Action]) -> Result<(), Error> {
+        for action in actions {
+            match *action {
+                Action::MoveTo(x, y) => self.move_to(x, y)?,
+                Action::MoveRelative(x_offset, y_offset) => {
+                    self.move_relative(x_offset, y_offset)?
+                }
+                Action::Click(button) => self.click_button(&button)?,
+                Action::Press(button) => self.press_button(&button)?,
+                Action::Release(button) => self.release_button(&button)?,
+                Action::Scroll(direction) => self.scroll_wheel(&direction)?,
+                Action::Sleep(duration) => self.state().sleep(duration),
+                Action::Drag(button, x, y) => {
+                    // Release the button even if the move fails
+                    self.press_button(&button)?;
+                    let moved = self.move_to(x, y);
+                    let released = self.release_button(&button);
+                    moved.and(released)?
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Run the macro with the given name from the library, see `MacroLibrary`
+    /// for the file format
+    ///
+    /// `Error::UnknownMacro` is returned if the library has no such macro
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::macros::MacroLibrary;
+    ///
+    /// let manager = Mouse::new();
+    /// let library = MacroLibrary::load("macros.txt").unwrap();
+    /// assert_eq!(manager.run_macro(&library, "context_menu"), Ok(()));
+    /// ```
+    fn run_macro(&self, library: &MacroLibrary, name: &str) -> Result<(), Error> {
+        let actions = library
+            .get(name)
+            .ok_or_else(|| Error::UnknownMacro(name.to_string()))?;
+        self.execute(actions)
+    }
+    /// Parse the given script and run its actions, see `macros::parse_script`
+    /// for the format
+    ///
+    /// The whole script is parsed before the first action is run, so a script
+    /// with an invalid line does nothing. `Error::InvalidMacro` carries the line
+    /// number then
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// let script = "
+    ///     move 100 200
+    ///     click left
+    ///     sleep 500
+    ///     scroll down 3
+    ///     drag 0 0 500 500
+    /// ";
+    /// assert_eq!(manager.run_script(script), Ok(()));
+    /// ```
+    fn run_script(&self, script: &str) -> Result<(), Error> {
+        self.execute(&macros::parse_script(script)?)
+    }
+    /// Replay the given events, each one after its delay from the previous event
+    ///
+    /// The delays are divided by `speed`, e.g. 2.0 replays the events twice as
+    /// fast, which has to be positive. The events are scheduled on a monotonic
+    /// clock, so the time that the actions themselves take does not add up over
+    /// a long replay. Events without a delay are sent back to back. The replay
+    /// stops at the first event that fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let manager = Mouse::new();
+    /// let events = [
+    ///     (Duration::ZERO, MouseEvent::AbsoluteMove(100, 100)),
+    ///     (Duration::from_millis(200), MouseEvent::Press(MouseButton::Left)),
+    ///     (Duration::from_millis(80), MouseEvent::Release(MouseButton::Left)),
+    /// ];
+    /// assert_eq!(manager.dispatch_many(&events, 1.0), Ok(()));
+    /// ```
+    fn dispatch_many(&self, events: &[(Duration, MouseEvent)], speed: f32) -> Result<(), Error> {
+        if speed.is_nan() || speed <= 0.0 {
+            return Err(Error::CustomError("the replay speed must be positive"));
+        }
+
+        let start = self.state().now();
+        let mut offset = Duration::ZERO;
+        for (delay, event) in events {
+            // Wait for the scheduled time of the event instead of the delay itself,
+            // which already includes the time the previous events took
+            offset += *delay;
+            let elapsed = self.state().now().duration_since(start);
+            let remaining = offset.div_f64(speed as f64).saturating_sub(elapsed);
+            if !remaining.is_zero() {
+                self.state().sleep(remaining);
+            }
+
+            send_event(self, event)?;
+        }
+        Ok(())
+    }
+    /// Block until `when`, then send the given event with the matching action
+    ///
+    /// The event is sent right away if `when` has already passed. The wait is
+    /// as precise as the sleeps, see `set_precise_timing`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::{Duration, Instant};
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let manager = Mouse::new();
+    /// let when = Instant::now() + Duration::from_millis(250);
+    /// assert_eq!(
+    ///     manager.dispatch_at(MouseEvent::Press(MouseButton::Left), when),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn dispatch_at(&self, event: MouseEvent, when: Instant) -> Result<(), Error> {
+        let remaining = when.saturating_duration_since(self.state().now());
+        if !remaining.is_zero() {
+            self.state().sleep(remaining);
+        }
+        send_event(self, &event)
+    }
+    /// Queue the given event to be sent at `when` without blocking
+    ///
+    /// The events are sent in time order by a scheduler thread that is started
+    /// with the first scheduled event, so they can be scheduled in any order.
+    /// The events of the same time are sent in the order they were scheduled.
+    /// The scheduler sends the events through the manager of `output_handle`,
+    /// with the settings of this one, and the failed events are skipped. The
+    /// events that are still queued when this manager is dropped are sent
+    /// before the scheduler thread exits. This fails where `output_handle` is
+    /// not available
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::{Duration, Instant};
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let manager = Mouse::new();
+    /// // Click on the next tick of an external clock
+    /// let tick = Instant::now() + Duration::from_secs(1);
+    /// manager.schedule(MouseEvent::Press(MouseButton::Left), tick).unwrap();
+    /// manager
+    ///     .schedule(MouseEvent::Release(MouseButton::Left), tick + Duration::from_millis(50))
+    ///     .unwrap();
+    /// ```
+    fn schedule(&self, event: MouseEvent, when: Instant) -> Result<(), Error> {
+        let mut scheduler = self.state().scheduler.lock().unwrap();
+        if scheduler.is_none() {
+            let output = self.output_handle()?;
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || run_schedule(receiver, output.as_ref()));
+            *scheduler = Some(sender);
+        }
+        scheduler
+            .as_ref()
+            .unwrap()
+            .send((when, event))
+            .map_err(|_| Error::CustomError("the scheduler thread has stopped"))
+    }
+    /// Send the given events back to back as a single batch
+    ///
+    /// On Windows all of the events are packed into a single `SendInput` call,
+    /// which inserts them into the input stream without any interruption, so no
+    /// real event can sneak in between e.g. the press, the moves and the release
+    /// of a drag. The other platforms send the events one by one, a real event
+    /// can still arrive between them. The absolute moves are relative to the
+    /// virtual desktop on Windows, like the rest of the coordinates. A batch
+    /// with a `Dial` fails with `Error::NotImplemented`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let manager = Mouse::new();
+    /// let drag = [
+    ///     MouseEvent::AbsoluteMove(100, 100),
+    ///     MouseEvent::Press(MouseButton::Left),
+    ///     MouseEvent::RelativeMove(200, 0),
+    ///     MouseEvent::Release(MouseButton::Left),
+    /// ];
+    /// assert_eq!(manager.atomic(&drag), Ok(()));
+    /// ```
+    fn atomic(&self, events: &[MouseEvent]) -> Result<(), Error> {
+        for event in events {
+            send_event(self, event)?;
+        }
+        Ok(())
+    }
+    /// Scroll the mouse wheel towards to the given direction
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::ScrollDirection;
+    /// use std::{thread, time::Duration};
+    ///
+    /// let manager = Mouse::new();
+    /// let sleep_duration = Duration::from_millis(250);
+    ///
+    /// for _ in 0..5 {
+    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
+    ///     thread::sleep(sleep_duration);
+    /// }
+    ///
+    /// for _ in 0..5 {
+    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
+    ///     thread::sleep(sleep_duration);
+    /// }
+    /// ```
+    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error>;
+    /// Get how far a single `scroll_wheel` call scrolls
+    ///
+    /// - On Windows it is based on the configured number of lines per notch
+    /// - On MacOS it is a fixed number of lines
+    /// - On X11 and uinput it is a single wheel notch
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::ScrollGranularity;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(ScrollGranularity::Lines(lines)) = manager.scroll_granularity() {
+    ///     println!("A click scrolls {lines} lines");
+    /// }
+    /// ```
+    fn scroll_granularity(&self) -> Result<ScrollGranularity, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Check whether high resolution scrolling is in use, i.e. the scrolls can
+    /// be finer than a wheel notch, e.g. to decide whether to present scroll
+    /// amounts in lines or in pixels
+    ///
+    /// - On MacOS the scroll events always carry pixel deltas
+    /// - On Windows the wheel deltas can be a fraction of a notch, whether an
+    ///   application scrolls by less than a notch is up to the application
+    /// - On Linux it depends on the mice, it is available if any of them has a
+    ///   high resolution wheel (`REL_WHEEL_HI_RES`). This needs the permission
+    ///   to read `/dev/input`, without it `false` is returned
+    /// - It is not available elsewhere
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if manager.high_res_scroll_available() {
+    ///     println!("Scrolling is smooth");
+    /// }
+    /// ```
+    fn high_res_scroll_available(&self) -> bool {
+        false
+    }
+    /// Scroll by the given fractional amount of wheel clicks, positive `dx`
+    /// scrolls right and positive `dy` scrolls up
+    ///
+    /// The fractions are accumulated across the calls like a high resolution
+    /// wheel does, a click is only sent once the accumulated amount reaches a
+    /// whole click and the remainder is carried forward to the next call
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // The third call sends a single click down
+    /// for _ in 0..3 {
+    ///     assert_eq!(manager.scroll_fractional(0.0, -0.4), Ok(()));
+    /// }
+    /// ```
+    fn scroll_fractional(&self, dx: f32, dy: f32) -> Result<(), Error> {
+        let (x_clicks, y_clicks) = {
+            let mut remainder = self.state().settings.scroll_remainder.lock().unwrap();
+            remainder.0 += dx;
+            remainder.1 += dy;
+            (take_whole(&mut remainder.0), take_whole(&mut remainder.1))
+        };
+
+        let x_direction = if x_clicks > 0 {
+            ScrollDirection::Right
+        } else {
+            ScrollDirection::Left
+        };
+        let y_direction = if y_clicks > 0 {
+            ScrollDirection::Up
+        } else {
+            ScrollDirection::Down
+        };
+        scroll_paced(
+            self,
+            std::iter::repeat_n(x_direction, x_clicks.unsigned_abs() as usize).chain(
+                std::iter::repeat_n(y_direction, y_clicks.unsigned_abs() as usize),
+            ),
+        )
+    }
+    /// Scroll by `dx` and `dy` wheel clicks at once, positive `dx` scrolls
+    /// right and positive `dy` scrolls up
+    ///
+    /// MacOS sends a diagonal scroll as a single event on both of the axes.
+    /// The other platforms split it into the single clicks of each axis, the
+    /// vertical ones first
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Scroll up and to the left diagonally
+    /// assert_eq!(manager.scroll_vector(-2, 2), Ok(()));
+    /// ```
+    fn scroll_vector(&self, dx: i32, dy: i32) -> Result<(), Error> {
+        scroll_paced(self, scroll_directions(dx, dy))
+    }
+    /// Scroll by the given number of whole wheel detents towards the direction,
+    /// negative `detents` scroll towards the opposite direction
+    ///
+    /// A detent is a notch of a standard wheel, which Windows reports as
+    /// `WHEEL_DELTA`, i.e. 120 units. Windows sends exactly `detents * 120`
+    /// units in a single event, for the applications that expect the deltas to
+    /// be whole multiples of 120. `scroll_wheel` sends 150 units instead. The
+    /// other platforms send a wheel click for each of the detents
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseActions, ScrollDirection};
+    ///
+    /// let manager = Mouse::new();
+    /// // Sends a single event of 360 units on Windows
+    /// assert_eq!(manager.scroll_detents(&ScrollDirection::Down, 3), Ok(()));
+    /// ```
+    fn scroll_detents(&self, direction: &ScrollDirection, detents: i32) -> Result<(), Error> {
+        let direction = match (direction, detents < 0) {
+            (ScrollDirection::Up, true) => ScrollDirection::Down,
+            (ScrollDirection::Down, true) => ScrollDirection::Up,
+            (ScrollDirection::Right, true) => ScrollDirection::Left,
+            (ScrollDirection::Left, true) => ScrollDirection::Right,
+            (direction, false) => *direction,
+        };
+        scroll_paced(
+            self,
+            std::iter::repeat_n(direction, detents.unsigned_abs() as usize),
+        )
+    }
+    /// Get the most scroll clicks per second that are delivered reliably, `None`
+    /// if there is no known limit
+    ///
+    /// Some compositors silently drop the synthetic scroll events that exceed
+    /// their rate limit. The bulk scrolls, i.e. `scroll_fractional`,
+    /// `scroll_vector` and `swipe`, pace their clicks to stay under this rate,
+    /// which trades speed for reliability
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Some(rate) = manager.max_scroll_rate() {
+    ///     println!("Scrolling at most {rate} clicks per second");
+    /// }
+    /// ```
+    fn max_scroll_rate(&self) -> Option<u32> {
+        *self.state().settings.max_scroll_rate.lock().unwrap()
+    }
+    /// Set the most scroll clicks per second the bulk scrolls send, see
+    /// `max_scroll_rate`. `None` removes the limit, a limit of 0 is treated as 1
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// // The compositor drops the clicks beyond 100 per second
+    /// manager.set_max_scroll_rate(Some(100));
+    /// assert_eq!(manager.scroll_vector(0, -1000), Ok(()));
+    /// ```
+    fn set_max_scroll_rate(&mut self, rate: Option<u32>) {
+        *self.state().settings.max_scroll_rate.lock().unwrap() = rate;
+    }
+    /// Scroll towards the given direction with a constant velocity, spreading
+    /// the scroll over the given `duration` like a touchpad swipe
+    ///
+    /// The `distance` is the number of scroll wheel clicks, each of them is a
+    /// single `scroll_wheel` call. How far a click scrolls depends on the platform
+    /// and the application, usually it is a few lines. The first click is sent
+    /// right away and the rest of them follow every `duration / distance`. A
+    /// `distance` of 0 does nothing
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::ScrollDirection;
+    ///
+    /// let manager = Mouse::new();
+    /// // Turn the page by scrolling 10 clicks within half a second
+    /// assert_eq!(manager.swipe(ScrollDirection::Down, 10, Duration::from_millis(500)), Ok(()));
+    /// ```
+    fn swipe(
+        &self,
+        direction: ScrollDirection,
+        distance: u32,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        if distance == 0 {
+            return Ok(());
+        }
+
+        let mut interval = duration / distance;
+        if let Some(rate) = self.max_scroll_rate() {
+            interval = interval.max(Duration::from_secs(1) / rate.max(1));
+        }
+        for click in 0..distance {
+            if click > 0 {
+                self.state().sleep(interval);
+            }
+            self.scroll_wheel(&direction)?;
+        }
+        Ok(())
+    }
+    /// Scroll `distance` clicks towards the given direction, timing the clicks
+    /// after the given acceleration curve
+    ///
+    /// The platforms and the applications accelerate the scroll when the
+    /// clicks come in quickly, so one big scroll ends up elsewhere than many
+    /// small ones. Timing the clicks like a real user does lets their
+    /// acceleration kick in the same way, which makes it possible to faithfully
+    /// reproduce the scrolling of a user. See `AccelCurve` for the presets. The
+    /// intervals are never shorter than the `max_scroll_rate` allows
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{AccelCurve, ScrollDirection};
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(
+    ///     manager.scroll_with_acceleration(ScrollDirection::Down, 12, AccelCurve::FLICK),
+    ///     Ok(())
+    /// );
+    /// ```
+    fn scroll_with_acceleration(
+        &self,
+        direction: ScrollDirection,
+        distance: u32,
+        curve: AccelCurve,
+    ) -> Result<(), Error> {
+        let shortest = self
+            .max_scroll_rate()
+            .map_or(Duration::ZERO, |rate| Duration::from_secs(1) / rate.max(1));
+        let mut intervals = curve.intervals(distance).into_iter();
+        for click in 0..distance {
+            if click > 0 {
+                let interval = intervals.next().unwrap_or_default();
+                self.state().sleep(interval.max(shortest));
+            }
+            self.scroll_wheel(&direction)?;
+        }
+        Ok(())
+    }
+    /// Set whether the simulated events are marked as synthetic (injected)
+    ///
+    /// Some applications ignore the synthetic events, so automation that needs
+    /// to look like real input can try to hide the mark. Only some platforms let
+    /// the mark be changed, `Error::NotImplemented` is returned for the rest
+    /// - On Windows `SendInput` always sets the `LLMHF_INJECTED` flag, the events
+    ///   can only be marked
+    /// - On MacOS the events are marked by default, without the mark they are
+    ///   posted from a `CGEventSource` in the HID system state like hardware events
+    /// - On X11 (XTest) and uinput the events are never marked, they can only
+    ///   be unmarked
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let mut manager = Mouse::new();
+    /// match manager.set_mark_synthetic(false) {
+    ///     Ok(()) => println!("The events are not marked as synthetic"),
+    ///     Err(err) => assert_eq!(Error::NotImplemented, err),
+    /// }
+    /// ```
+    fn set_mark_synthetic(&mut self, _mark: bool) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Attach a callback function to mouse events
+    ///
+    /// The callbacks are invoked from a background listener thread that is
+    /// started with the first hook, so this can be called from any thread. On
+    /// Windows the listener thread installs the `WH_MOUSE_LL` hook and runs the
+    /// message pump it requires, the calling thread does not need one
+    ///
+    /// The callbacks only observe the events, the other applications still
+    /// receive them and the cursor keeps moving. See `hook_exclusive` to keep
+    /// the events from the other applications
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook(Box::new(|e| println!("New event: {:?}", e)));
+    /// match hook_result {
+    ///     Ok(id) => {
+    ///         assert_eq!(manager.unhook(id), Ok(()));
+    ///     }
+    ///     // Hooking may require user privileges on some systems
+    ///     // e.g. requires super user for Linux
+    ///     Err(err) => assert_eq!(Error::PermissionDenied, err),
+    /// }
+    /// ```
+    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error>;
+    /// Attach a callback function to mouse events like `hook`, and describe
+    /// what the listener was set up with, see `HookInfo`
+    ///
+    /// This helps to find out why a hook receives no events, e.g. when the
+    /// mouse is not among the devices that are listened to on Linux
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let info = manager.hook_detailed(Box::new(|e| println!("New event: {:?}", e))).unwrap();
+    /// println!("Listening on {:?} with the {} backend", info.devices, info.backend);
+    /// ```
+    fn hook_detailed(&mut self, callback: MouseEventFn) -> Result<HookInfo, Error> {
+        let id = self.hook(callback)?;
+        Ok(HookInfo {
+            id,
+            backend: self.backend_name(),
+            devices: self.listener_sources(),
+        })
+    }
+    /// Get what the event listener receives the events from, see
+    /// `HookInfo::devices`
+    ///
+    /// It is empty while the listener is not running, and where the backend
+    /// does not report it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.ensure_listener().unwrap();
+    /// println!("Listening on {:?}", manager.listener_sources());
+    /// ```
+    fn listener_sources(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Attach a callback function to mouse events and keep the events from the
+    /// rest of the system, e.g. to intercept the mouse of a kiosk
+    ///
+    /// The input devices are grabbed (`EVIOCGRAB`) while any exclusive callback
+    /// is hooked, so only the hooks of this manager receive their events. The
+    /// cursor does not move and the other applications see no clicks, the other
+    /// callbacks still receive the events. Use `hook` to only observe the events,
+    /// which is what most applications need. The grab is released once the
+    /// last exclusive callback is unhooked, it may take up to 100ms
+    ///
+    /// Only the Linux listener can grab the devices, on X11 as well as uinput,
+    /// `Error::NotImplemented` is returned elsewhere
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let id = manager.hook_exclusive(Box::new(|e| println!("Intercepted {:?}", e))).unwrap();
+    /// // The mouse does not control the cursor until the callback is unhooked
+    /// manager.unhook(id).unwrap();
+    /// ```
+    fn hook_exclusive(&mut self, _callback: MouseEventFn) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Attach a callback function that is invoked with the next mouse event only
+    ///
+    /// The callback is removed right before it is invoked, so it runs exactly
+    /// once even if more events arrive at the same time. The returned
+    /// `CallbackId` can be used with `unhook` until then
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::mpsc;
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseEvent;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let (sender, receiver) = mpsc::channel();
+    /// manager
+    ///     .hook_once(Box::new(move |event| sender.send(*event).unwrap()))
+    ///     .unwrap();
+    /// let event: MouseEvent = receiver.recv().unwrap();
+    /// println!("The next event was {:?}", event);
+    /// ```
+    fn hook_once(&mut self, _callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Wait for the next `n` mouse events and return them, e.g. for a prompt
+    /// to click the corners of a region
+    ///
+    /// A callback is hooked until the events arrived, see `hook`. Without a
+    /// `timeout` this waits as long as it takes, otherwise
+    /// `Error::EventsTimedOut` is returned with the events that arrived in time.
+    /// It is returned as well if the listener stops before all of them arrived
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let events = manager.collect_events(10, Some(Duration::from_secs(5))).unwrap();
+    /// println!("The next 10 events were {:?}", events);
+    /// ```
+    fn collect_events(
+        &mut self,
+        n: usize,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<MouseEvent>, Error> {
+        self.collect_events_matching(n, timeout, Box::new(|_| true))
+    }
+    /// Wait for the next `n` mouse events that match the `filter` and return
+    /// them, the other events are skipped, see `collect_events`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let mut manager = Mouse::new();
+    /// println!("Click the three corners of the region");
+    /// let clicks = manager
+    ///     .collect_events_matching(
+    ///         3,
+    ///         Some(Duration::from_secs(30)),
+    ///         Box::new(|event| *event == MouseEvent::Press(MouseButton::Left)),
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(clicks.len(), 3);
+    /// ```
+    fn collect_events_matching(
+        &mut self,
+        n: usize,
+        timeout: Option<Duration>,
+        filter: MouseEventFilterFn,
+    ) -> Result<Vec<MouseEvent>, Error> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let id = self.hook(Box::new(move |event| {
+            if filter(event) {
+                // The receiver is gone once enough events arrived
+                let _ = sender.send(*event);
+            }
+        }))?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut events = Vec::with_capacity(n);
+        while events.len() < n {
+            let event = match deadline {
+                Some(deadline) => receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => receiver.recv().ok(),
+            };
+            match event {
+                Some(event) => events.push(event),
+                None => break,
+            }
+        }
+        self.unhook(id)?;
+
+        if events.len() < n {
+            return Err(Error::EventsTimedOut(events));
+        }
+        Ok(events)
+    }
+    /// Attach a callback function that is only invoked with the given kinds
+    /// of mouse events
+    ///
+    /// The other events are dropped by the listener before the callback is
+    /// invoked, e.g. a tool that only cares about the clicks does not pay for
+    /// the flood of move events
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::EventKindSet;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager
+    ///     .hook_filtered_kinds(
+    ///         EventKindSet::PRESS | EventKindSet::RELEASE,
+    ///         Box::new(|e| println!("{:?}", e)),
+    ///     )
+    ///     .unwrap();
+    /// ```
+    fn hook_filtered_kinds(
+        &mut self,
+        _kinds: EventKindSet,
+        _callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Attach a callback function that receives the mouse events along with
+    /// an `EventContext`, e.g. which application was frontmost
+    ///
+    /// On MacOS the bundle identifier of the frontmost application is queried
+    /// from `NSWorkspace` for every event, which takes a few microseconds on
+    /// the listener thread. A callback that only needs it once in a while, e.g.
+    /// per click, should cache it rather than look at every move event. The
+    /// frontmost application is only updated while the main thread runs an
+    /// event loop, as any application with a window does
+    ///
+    /// On Linux the kind of the device of every scroll is known, the devices
+    /// are classified when the listener opens them. Elsewhere the context is
+    /// empty, the events are delivered like with `hook`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseEvent;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager
+    ///     .hook_with_context(Box::new(|event, context| {
+    ///         if let MouseEvent::Press(button) = event {
+    ///             println!("{:?} pressed in {:?}", button, context.app_id);
+    ///         }
+    ///     }))
+    ///     .unwrap();
+    /// ```
+    fn hook_with_context(&mut self, callback: MouseEventContextFn) -> Result<CallbackId, Error> {
+        let context = EventContext::default();
+        self.hook(Box::new(move |event| callback(event, &context)))
+    }
+    /// Hook a queue of at most `capacity` events, which can be received from
+    /// another thread with the returned `EventReceiver`
+    ///
+    /// The `policy` decides what happens with the new events when the queue is
+    /// full, no policy lets a slow receiver make the queue grow without a limit.
+    /// With `OverflowPolicy::Block` a slow receiver holds up the other callbacks.
+    /// The `CallbackId` can be used with `unhook` like the ones of `hook`. The
+    /// `capacity` has to be at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::thread;
+    /// use mouce::Mouse;
+    /// use mouce::common::OverflowPolicy;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let (_, receiver) = manager
+    ///     .hook_channel_bounded(1024, OverflowPolicy::DropOldest)
+    ///     .unwrap();
+    /// thread::spawn(move || {
+    ///     while let Some(event) = receiver.recv() {
+    ///         println!("New event: {:?}", event);
+    ///     }
+    /// });
+    /// ```
+    fn hook_channel_bounded(
+        &mut self,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> Result<(CallbackId, EventReceiver), Error> {
+        if capacity == 0 {
+            return Err(Error::CustomError("the queue capacity must be at least 1"));
+        }
+
+        let queue = Arc::new(EventQueue {
+            state: Mutex::new(EventQueueState {
+                events: VecDeque::with_capacity(capacity),
+                sender_closed: false,
+                receiver_closed: false,
+            }),
+            changed: Condvar::new(),
+            capacity,
+            policy,
+        });
+        let sender = EventSender {
+            queue: queue.clone(),
+        };
+        let id = self.hook(Box::new(move |event| sender.send(*event)))?;
+        Ok((id, EventReceiver { queue }))
+    }
+    /// Attach a callback function that can simulate mouse actions in response
+    /// to the events, e.g. to remap a button
+    ///
+    /// The callback runs on the listener thread, where it can not use the
+    /// manager it was hooked to, so it is given the manager of `output_handle`
+    /// for the output. It is created when hooking, it sends with the same
+    /// backend and the settings of this manager. This fails where
+    /// `output_handle` is not available
+    ///
+    /// The simulated actions are mouse events like any other, on most of the
+    /// platforms they are received by the hooks again, including this callback.
+    /// Responding to them again can loop forever, e.g. remapping a click to a
+    /// click. Keep count of the events that are simulated and skip that many of
+    /// the matching events, as the example does. The hooked events arrive
+    /// asynchronously, so a flag that is only set while simulating does not work
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use mouce::Mouse;
+    /// use mouce::common::{MouseButton, MouseEvent};
+    ///
+    /// let mut manager = Mouse::new();
+    /// // Turn the middle clicks into left clicks
+    /// let pending = AtomicUsize::new(0);
+    /// manager
+    ///     .hook_reactive(Box::new(move |event, output| match event {
+    ///         // Skip the left clicks this callback simulated
+    ///         MouseEvent::Press(MouseButton::Left) => {
+    ///             let _ = pending.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+    ///                 n.checked_sub(1)
+    ///             });
+    ///         }
+    ///         MouseEvent::Press(MouseButton::Middle) => {
+    ///             pending.fetch_add(1, Ordering::SeqCst);
+    ///             let _ = output.click_button(&MouseButton::Left);
+    ///         }
+    ///         _ => {}
+    ///     }))
+    ///     .unwrap();
+    /// ```
+    fn hook_reactive(&mut self, callback: ReactiveEventFn) -> Result<CallbackId, Error> {
+        let output = Mutex::new(self.output_handle()?);
+        self.hook(Box::new(move |event| {
+            let output = output.lock().unwrap_or_else(PoisonError::into_inner);
+            callback(event, output.as_ref());
+        }))
+    }
+    /// Attach a callback function to the scroll events, the clicks of the same
+    /// direction are reported together with their count
+    ///
+    /// A group starts with the first click and is reported once `window` has
+    /// passed since then, or right away when a click of another direction
+    /// arrives. The groups are reported from a separate thread, which exits
+    /// when the returned `CallbackId` is unhooked
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook_scroll_coalesced(
+    ///     Duration::from_millis(100),
+    ///     Box::new(|direction, count| println!("Scrolled {:?} {} times", direction, count)),
+    /// );
+    /// ```
+    fn hook_scroll_coalesced(
+        &mut self,
+        window: Duration,
+        callback: Box<dyn Fn(ScrollDirection, i32) + Send>,
+    ) -> Result<CallbackId, Error> {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.hook(Box::new(move |event| match *event {
+            MouseEvent::Scroll(direction) => {
+                let _ = sender.send(direction);
+            }
+            MouseEvent::ScrollDelta(dx, dy) => {
+                for direction in scroll_directions(dx, dy) {
+                    let _ = sender.send(direction);
+                }
+            }
+            _ => {}
+        }))?;
+        thread::spawn(move || coalesce_scrolls(receiver, window, callback));
+        Ok(id)
+    }
+    /// Attach multiple callback functions to mouse events at once
+    ///
+    /// Either all of the callbacks are hooked or none of them, if hooking
+    /// one of the callbacks fails, the already hooked ones are unhooked
+    /// before the error is returned
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook_many(vec![
+    ///     Box::new(|e| println!("First callback: {:?}", e)),
+    ///     Box::new(|e| println!("Second callback: {:?}", e)),
+    /// ]);
+    /// match hook_result {
+    ///     Ok(ids) => assert_eq!(ids.len(), 2),
+    ///     Err(err) => assert_eq!(Error::PermissionDenied, err),
+    /// }
+    /// ```
+    fn hook_many(&mut self, callbacks: Vec<MouseEventFn>) -> Result<Vec<CallbackId>, Error> {
+        let mut ids = Vec::with_capacity(callbacks.len());
+        for callback in callbacks {
+            match self.hook(callback) {
+                Ok(id) => ids.push(id),
+                Err(err) => {
+                    // Roll back the callbacks that are already hooked, the original
+                    // error is more relevant to the caller than a failing unhook
+                    for id in ids {
+                        let _ = self.unhook(id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(ids)
+    }
+    /// Attach a callback function to the raw evdev events of the mouse devices
+    ///
+    /// This is only available on Linux and it is not portable, but it delivers
+    /// every `(type, code, value)` record with its timestamp, including the ones
+    /// `MouseEvent` can not represent such as high resolution scrolling and
+    /// touchpad gestures. The returned `CallbackId` can be used with `unhook`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// // Requires super user privileges, like `hook`
+    /// let hook_result = manager.hook_raw_evdev(Box::new(|e| {
+    ///     println!("type: {}, code: {}, value: {}", e.r#type, e.code, e.value)
+    /// }));
+    /// ```
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn hook_raw_evdev(
+        &mut self,
+        _callback: crate::nix::RawEvdevEventFn,
+    ) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Remove the callback function with the given `CallbackId`
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error>;
+    /// Remove all of the callback functions that are hooked with the given
+    /// label by `hook_labeled`
+    ///
+    /// Returns `Error::UnhookFailed` if no callback has the label
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook_labeled("clicks", Box::new(|_| {})).unwrap();
+    /// assert_eq!(manager.unhook_label("clicks"), Ok(()));
+    /// ```
+    fn unhook_label(&mut self, label: &str) -> Result<(), Error> {
+        let ids: Vec<_> = self
+            .hooks_by_label()
+            .into_iter()
+            .filter(|(hooked_label, _)| hooked_label == label)
+            .map(|(_, id)| id)
+            .collect();
+        if ids.is_empty() {
+            return Err(Error::UnhookFailed);
+        }
+        ids.into_iter().try_for_each(|id| self.unhook(id))
+    }
+    /// Get the labels and the ids of the callbacks that are hooked with
+    /// `hook_labeled`, in the order they were hooked
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook_labeled("logger", Box::new(|_| {})).unwrap();
+    /// for (label, id) in manager.hooks_by_label() {
+    ///     println!("{label}: {id}");
+    /// }
+    /// ```
+    fn hooks_by_label(&self) -> Vec<(String, CallbackId)> {
+        self.state().labels.lock().unwrap().clone()
+    }
+    /// Remove all callback functions
+    ///
+    /// On Windows this also stops the listener thread and removes the
+    /// `WH_MOUSE_LL` hook, the next `hook` starts a new one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// assert_eq!(manager.unhook_all(), Ok(()));
+    /// ```
+    fn unhook_all(&mut self) -> Result<(), Error>;
+    /// Set the starting point of the position that is estimated from the
+    /// hooked move events, see `get_position_from_hook`
+    ///
+    /// Seeding the position starts the event listener if it is not started
+    /// yet, so it may require the same user privileges as `hook`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// // e.g. after moving the mouse to the top-left corner
+    /// assert_eq!(manager.seed_position(0, 0), Ok(()));
+    /// ```
+    fn seed_position(&mut self, _x: i32, _y: i32) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the position of the mouse as it is estimated from the hooked move
+    /// events, for the platforms where `get_position` is not available such as
+    /// Linux Wayland
+    ///
+    /// The relative moves are added up starting from the point given to
+    /// `seed_position`, absolute moves set the position directly. This is only
+    /// an estimate, it drifts if the listener misses events, e.g. the ones of a
+    /// device that is plugged in later, and it is not clamped to the display.
+    /// An error is returned while the event listener is not running or the
+    /// position is not known yet
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.seed_position(0, 0).unwrap();
+    /// manager.move_relative(100, 50).unwrap();
+    /// if let Ok((x, y)) = manager.get_position_from_hook() {
+    ///     println!("The mouse is around ({x}, {y})");
+    /// }
+    /// ```
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Check whether the given callback is hooked and the event listener that
+    /// feeds it is still running
+    ///
+    /// An active hook can still be silent, so combine this with `last_event_at`
+    /// to detect a hook that does not receive any events. The usual causes are
+    /// - On Linux (uinput) the user can not read `/dev/input/event*`, e.g. it is
+    ///   not in the `input` group, or the mouse is not an evdev device that was
+    ///   present when the listener started
+    /// - On Windows the low level hook is removed by the system when a callback
+    ///   takes too long, see `LowLevelHooksTimeout`
+    /// - On MacOS the application is not trusted for accessibility (or input
+    ///   monitoring), the event tap is then created but never receives events
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let hook = manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+    /// assert!(manager.hook_is_active(hook));
+    /// ```
+    fn hook_is_active(&self, _callback_id: CallbackId) -> bool {
+        false
+    }
+    /// Start the event listener unless it is already running, without hooking
+    /// a callback
+    ///
+    /// The hooks do this on their own, it is only needed to watch the activity
+    /// with `last_event_at` or `is_mouse_active` before anything is hooked
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.ensure_listener().expect("failed to start the listener");
+    /// ```
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the time the event listener received the last event, `None` if it
+    /// has not received any events yet
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::{thread, time::Duration};
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+    /// thread::sleep(Duration::from_secs(5));
+    /// if manager.last_event_at().is_none() {
+    ///     eprintln!("No mouse events were received, check the permissions");
+    /// }
+    /// ```
+    fn last_event_at(&self) -> Option<Instant> {
+        None
+    }
+    /// Check whether the mouse was used within the last `within`, e.g. to tell
+    /// whether the user is still there without hooking a callback
+    ///
+    /// On Windows this asks the system for the time of the last input, which
+    /// includes the keyboard, unless the event listener is running. On the
+    /// other platforms it relies on `last_event_at` and starts the event
+    /// listener on the first call, so the mouse is only seen from then on
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.is_mouse_active(Duration::ZERO).expect("failed to listen");
+    /// // ...
+    /// if manager.is_mouse_active(Duration::from_secs(300)) == Ok(false) {
+    ///     println!("The user is away");
+    /// }
+    /// ```
+    fn is_mouse_active(&mut self, within: Duration) -> Result<bool, Error> {
+        self.ensure_listener()?;
+        let now = self.state().now();
+        Ok(self
+            .last_event_at()
+            .is_some_and(|at| now.saturating_duration_since(at) <= within))
+    }
+    /// Get how long ago the last input of the whole system was, the mouse and
+    /// the keyboard alike, e.g. for a screensaver
+    ///
+    /// Unlike `is_mouse_active`, this does not need a hooked callback, it asks
+    /// the system
+    /// - On Windows it is `GetLastInputInfo`
+    /// - On MacOS it is `CGEventSourceSecondsSinceLastEventType` of the combined
+    ///   session state for any input event
+    /// - On X11 it is the idle time of the X Screen Saver extension, where the
+    ///   X server does not support it `Error::NotImplemented` is returned
+    /// - It is not implemented on uinput
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if manager.system_idle_time().is_ok_and(|idle| idle > Duration::from_secs(600)) {
+    ///     println!("Starting the screensaver");
+    /// }
+    /// ```
+    fn system_idle_time(&self) -> Result<Duration, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Stop all of the background threads, e.g. the event listener and the
+    /// display watcher, to exit the application cleanly
+    ///
+    /// All of the callbacks are unhooked, then the threads are signalled to exit
+    /// and joined with a timeout. This ends the hook message pump on Windows and
+    /// closes the `/dev/input` files on Linux. If a thread does not exit in time,
+    /// it is left running in the background and an error is returned
+    ///
+    /// The manager is not meant to be used anymore after the shutdown. Dropping
+    /// the manager shuts it down as well, ignoring the errors
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|e| println!("New event: {:?}", e)));
+    /// assert_eq!(manager.shutdown(), Ok(()));
+    /// ```
+    fn shutdown(&mut self) -> Result<(), Error> {
+        self.unhook_all()
+    }
+    /// Start keeping the last `capacity` mouse events that are received by
+    /// the listener, a capacity of 0 disables the history again
+    ///
+    /// The history is separate from the hooked callbacks and is meant to help
+    /// with debugging what just happened. Once the history is full, the oldest
+    /// event is evicted for every new event. Lowering the capacity evicts the
+    /// oldest events right away
+    ///
+    /// Enabling the history starts the event listener if it is not started yet,
+    /// so it may require the same user privileges as `hook`
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// assert_eq!(manager.enable_event_history(100), Ok(()));
+    /// ```
+    fn enable_event_history(&mut self, _capacity: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get a snapshot of the event history, ordered from the oldest to the newest event
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.enable_event_history(100).unwrap();
+    /// for event in manager.event_history() {
+    ///     println!("{:?}", event);
+    /// }
+    /// ```
+    fn event_history(&self) -> Vec<MouseEvent> {
+        Vec::new()
+    }
+    /// Set the function that is invoked with the panic message when a hooked
+    /// callback panics
+    ///
+    /// A panicking callback does not stop the listener, the panic is caught and
+    /// the rest of the callbacks still receive the event and the following ones.
+    /// Without a handler the panic is only printed by the panic hook as usual.
+    /// The callbacks should still avoid panicking, they have to be unwind safe,
+    /// e.g. a callback that panics while it modifies some shared state may leave
+    /// that state half updated
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager
+    ///     .on_callback_panic(Box::new(|message| eprintln!("A callback panicked: {message}")))
+    ///     .unwrap();
+    /// ```
+    fn on_callback_panic(&mut self, _handler: CallbackPanicFn) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Attach a callback function that is invoked whenever the display
+    /// configuration changes, e.g. the resolution is changed or a monitor
+    /// is plugged in
+    ///
+    /// Watching the display configuration is opt-in, the background watcher
+    /// (`WM_DISPLAYCHANGE` on Windows, XRandR screen change events on X11 and
+    /// a display reconfiguration callback on MacOS) is only started with the
+    /// first call to this function.
+    ///
+    /// mouce does not cache any display metrics, they are queried from the
+    /// platform whenever they are needed. If your application caches things
+    /// like the screen size, this callback is the place to invalidate them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let result = manager.on_display_change(Box::new(|| println!("Display changed")));
+    /// // The display configuration can not be watched on some platforms such as Linux Wayland
+    /// assert!(result == Ok(()) || result == Err(Error::NotImplemented));
+    /// ```
+    fn on_display_change(&mut self, _callback: Box<dyn Fn() + Send>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+}
+
+impl dyn MouseActions {
+    /// Give the event listener threads the given scheduling priority, so they
+    /// keep up with the events on a loaded system. The default is
+    /// `Priority::Normal`
+    ///
+    /// The threads are given the priority when they are started, it is set
+    /// up while creating the manager so the listener never runs with another
+    /// one, and `reset` keeps it. Raising the priority is best effort, without
+    /// the needed privileges the threads keep the normal one
+    /// - On Windows `High` and `RealTime` are the highest and the time critical
+    ///   thread priorities, they do not need any privileges
+    /// - On Linux `High` lowers the nice value of the threads, `RealTime` uses
+    ///   the `SCHED_RR` policy. Both need root or `CAP_SYS_NICE` (or a matching
+    ///   `RLIMIT_NICE`/`RLIMIT_RTPRIO`). The other unix-like systems keep the
+    ///   normal priority, their nice value is shared by the whole process
+    /// - On MacOS the threads get a higher priority with `pthread_setschedparam`,
+    ///   `RealTime` uses the `SCHED_RR` policy
+    ///
+    /// Use this sparingly, the callbacks run on these threads as well and a
+    /// busy callback with a real-time priority can starve the rest of the system
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::Priority;
+    ///
+    /// let mut manager = Mouse::new().with_listener_priority(Priority::High);
+    /// manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+    /// ```
+    pub fn with_listener_priority(self: Box<Self>, priority: Priority) -> Box<Self> {
+        *self.state().settings.listener_priority.lock().unwrap() = priority;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::headless::HeadlessMouse;
+    use crate::hooks;
+    use crate::macros::MacroLibrary;
+    use crate::mock::MockMouseManager;
+    use crate::{
+        common, common::AccelCurve, common::Action, common::Clock, common::CoordinateOrigin,
+        common::EventContext, common::EventKindSet, common::EventReceiver, common::HookInfo,
+        common::MockClock, common::Modifier, common::Monitor, common::MouseActions,
+        common::MouseButton, common::MouseEvent, common::MouseState, common::MoveKind,
+        common::OverflowPolicy, common::PointerDevice, common::PointerRole, common::Priority,
+        common::ScrollDirection, Mouse,
+    };
+    use std::{
+        cell::RefCell,
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::{atomic::Ordering, mpsc, Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    #[ignore]
+    fn move_to_right_bottom() {
+        let manager = Mouse::new();
+        assert_eq!(manager.move_to(1920, 1080), Ok(()));
+    }
+
+    #[test]
+    #[ignore]
+    fn move_to_top_left() {
+        let manager = Mouse::new();
+        assert_eq!(manager.move_to(0, 0), Ok(()));
+    }
+
+    #[test]
+    #[ignore]
+    fn move_to_left_to_right() {
+        let manager = Mouse::new();
+        let sleep_duration = Duration::from_millis(1);
+        let mut x = 0;
+        while x < 1920 {
+            assert_eq!(manager.move_to(x, 540), Ok(()));
+            x += 1;
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn move_relative_left_to_right() {
+        let manager = Mouse::new();
+        let sleep_duration = Duration::from_millis(1);
+        let mut x = 0;
+        assert_eq!(manager.move_to(0, 540), Ok(()));
+        while x < 1920 {
+            assert_eq!(manager.move_relative(1, 0), Ok(()));
+            x += 1;
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn move_to_top_to_bottom() {
+        let manager = Mouse::new();
+        let sleep_duration = Duration::from_millis(1);
+        let mut y = 0;
+        while y < 1080 {
+            assert_eq!(manager.move_to(960, y), Ok(()));
+            y += 1;
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn move_relative_top_to_bottom() {
+        let manager = Mouse::new();
+        let sleep_duration = Duration::from_millis(1);
+        let mut y = 0;
+        assert_eq!(manager.move_to(960, 0), Ok(()));
+        while y < 1080 {
+            assert_eq!(manager.move_relative(0, 1), Ok(()));
+            y += 1;
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn get_position() {
+        let manager = Mouse::new();
+        match manager.get_position() {
+            Ok(_) => {
+                let positions = [
+                    (0, 0),
+                    (100, 100),
+                    (250, 250),
+                    (325, 325),
+                    (400, 100),
+                    (100, 400),
+                ];
+
+                let mut x;
+                let mut y;
+                for position in positions.iter() {
+                    assert_eq!(manager.move_to(position.0, position.1), Ok(()));
+                    (x, y) = manager.get_position().unwrap();
+                    assert_eq!(x, position.0 as i32);
+                    assert_eq!(y, position.1 as i32);
+                }
+            }
+            Err(error) => assert_eq!(error, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn left_click() {
+        let manager = Mouse::new();
+        assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    }
+
+    #[test]
+    #[ignore]
+    fn scroll_down() {
+        let manager = Mouse::new();
+        for _ in 0..10 {
+            assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
+            let sleep_duration = Duration::from_millis(250);
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn scroll_up() {
+        let manager = Mouse::new();
+        for _ in 0..10 {
+            assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
+            let sleep_duration = Duration::from_millis(250);
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn scroll_right() {
+        let manager = Mouse::new();
+        for _ in 0..10 {
+            assert_eq!(manager.scroll_wheel(&ScrollDirection::Right), Ok(()));
+            let sleep_duration = Duration::from_millis(250);
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn scroll_left() {
+        let manager = Mouse::new();
+        for _ in 0..10 {
+            assert_eq!(manager.scroll_wheel(&ScrollDirection::Left), Ok(()));
+            let sleep_duration = Duration::from_millis(250);
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn hook_and_unhook() {
+        let mut manager = Mouse::new();
+        assert_eq!(manager.unhook(5), Err(Error::UnhookFailed));
+        let hook_result = manager.hook(Box::new(|e| println!("{:?}", e)));
+        match hook_result {
+            Ok(id) => {
+                assert_eq!(manager.unhook(id), Ok(()));
+
+                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+                let id = manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+
+                manager.unhook_all().unwrap();
+                assert_eq!(manager.unhook(id), Err(Error::UnhookFailed));
+            }
+            Err(err) => assert_eq!(Error::PermissionDenied, err),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn hook_receives_events_without_message_pump() {
+        // Hook from a plain thread that never pumps messages, like an async runtime worker
+        let (tx, rx) = mpsc::channel();
+        let received = thread::spawn(move || {
+            let mut manager = Mouse::new();
+            manager
+                .hook(Box::new(move |e| {
+                    let _ = tx.send(*e);
+                }))
+                .unwrap();
+            manager.click_button(&MouseButton::Left).unwrap();
+            rx.recv_timeout(Duration::from_secs(1))
+        })
+        .join()
+        .unwrap();
+        assert!(received.is_ok());
+    }
+
+    #[test]
+    #[ignore]
+    fn confine_cursor() {
+        let manager = Mouse::new();
+        match manager.confine_cursor(100, 100, 200, 200) {
+            Ok(clip) => {
+                // Try to move the cursor with the real mouse, it should stay in the rectangle
+                thread::sleep(Duration::from_secs(3));
+                drop(clip);
+            }
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn get_pointer_mapping() {
+        let manager = Mouse::new();
+        match manager.get_pointer_mapping() {
+            Ok(mapping) => assert!(mapping.len() >= 3),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn system_idle_time() {
+        let manager = Mouse::new();
+        // Leave the mouse and the keyboard alone during the test
+        let before = manager.system_idle_time().unwrap();
+        thread::sleep(Duration::from_millis(500));
+        let after = manager.system_idle_time().unwrap();
+        assert!(after >= before + Duration::from_millis(400));
+    }
+
+    #[test]
+    #[ignore]
+    fn double_click_thresholds() {
+        let manager = Mouse::new();
+        match manager.double_click_time() {
+            Ok(time) => assert!(!time.is_zero()),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+        match manager.double_click_distance() {
+            Ok(distance) => assert!(distance > 0),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+        match manager.drag_threshold() {
+            Ok((width, height)) => assert!(width > 0 && height > 0),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn get_cursor_shape() {
+        let manager = Mouse::new();
+        match manager.get_cursor_shape() {
+            Ok(shape) => println!("The cursor shape is {:?}", shape),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn scroll_granularity() {
+        let manager = Mouse::new();
+        match manager.scroll_granularity() {
+            Ok(granularity) => println!("A scroll wheel click is {:?}", granularity),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn set_target_screen() {
+        let mut manager = Mouse::new();
+        match manager.set_target_screen(0) {
+            Ok(()) => assert!(manager.set_target_screen(-1).is_err()),
+            Err(err) => assert_eq!(err, Error::NotImplemented),
+        }
+    }
+
+    #[test]
+    fn backend_name() {
+        let manager: Box<dyn MouseActions> = Box::new(MockMouseManager::new());
+        assert_eq!(manager.backend_name(), "mock");
+        assert_eq!(
+            format!("{:?}", manager),
+            "MouseActions { backend: \"mock\", .. }"
+        );
+    }
+
+    #[test]
+    fn drop_stops_listener() {
+        let mut manager = MockMouseManager::new();
+        let listener_exited = manager.listener_exited.clone();
+        manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+        assert!(!listener_exited.load(Ordering::Relaxed));
+
+        drop(manager);
+        assert!(listener_exited.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn press_and_report() {
+        let mock = MockMouseManager::new();
+        let manager: &dyn MouseActions = &mock;
+        let progress = RefCell::new(vec![]);
+        let hold = Duration::from_millis(250);
+        let result = manager.press_and_report(
+            &MouseButton::Left,
+            hold,
+            Duration::from_millis(100),
+            |value| progress.borrow_mut().push(value),
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(progress.take(), vec![0.0, 0.4, 0.8, 1.0]);
+        assert_eq!(mock.state().sleeps().iter().sum::<Duration>(), hold);
+        assert_eq!(
+            mock.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+
+        assert_eq!(
+            manager.press_and_report(
+                &MouseButton::Left,
+                Duration::ZERO,
+                Duration::ZERO,
+                |value| { progress.borrow_mut().push(value) }
+            ),
+            Ok(())
+        );
+        assert_eq!(progress.take(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn while_holding() {
+        let mock = MockMouseManager::new();
+        let manager: &dyn MouseActions = &mock;
+        let result = manager.while_holding(&MouseButton::Middle, |manager| {
+            manager.scroll_wheel(&ScrollDirection::Down)?;
+            manager.scroll_wheel(&ScrollDirection::Down)
+        });
+        assert_eq!(result, Ok(()));
+
+        // The button is released even if the actions fail
+        let result = manager.while_holding(&MouseButton::Right, |_| Err(Error::NotImplemented));
+        assert_eq!(result, Err(Error::NotImplemented));
+
+        assert_eq!(
+            mock.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Middle),
+                MouseEvent::Scroll(ScrollDirection::Down),
+                MouseEvent::Scroll(ScrollDirection::Down),
+                MouseEvent::Release(MouseButton::Middle),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn click_while_holding() {
+        let mock = MockMouseManager::new();
+        let manager: &dyn MouseActions = &mock;
+        assert_eq!(
+            manager.click_while_holding(&MouseButton::Right, &MouseButton::Left),
+            Ok(())
+        );
+        assert_eq!(
+            mock.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_position_from_hook() {
+        let mut manager = MockMouseManager::new();
+        assert!(manager.get_position_from_hook().is_err());
+
+        manager.seed_position(100, 100).unwrap();
+        manager.inject(MouseEvent::RelativeMove(5, -3));
+        manager.inject(MouseEvent::RelativeMove(0, 10));
+        assert_eq!(manager.get_position_from_hook(), Ok((105, 107)));
+
+        manager.inject(MouseEvent::AbsoluteMove(20, 30));
+        manager.inject(MouseEvent::RelativeMove(-1, -1));
+        assert_eq!(manager.get_position_from_hook(), Ok((19, 29)));
+    }
+
+    #[test]
+    fn move_to_natural() {
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut manager = MockMouseManager::new();
+            manager.seed_rng(3);
+            for _ in 0..10 {
+                assert_eq!(manager.move_to_natural(400, 300), Ok(()));
+            }
+            let events = manager.events();
+            assert!(events.len() > 10);
+            assert_eq!(events.last(), Some(&MouseEvent::AbsoluteMove(400, 300)));
+            for event in &events {
+                let MouseEvent::AbsoluteMove(x, y) = *event else {
+                    panic!("unexpected event {:?}", event);
+                };
+                assert!((x - 400).abs() <= 2 && (y - 300).abs() <= 2);
+            }
+            // Every call starts with an overshoot next to the target
+            assert_ne!(events[0], MouseEvent::AbsoluteMove(400, 300));
+            runs.push(events);
+        }
+        // The same seed produces the same settle moves
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[test]
+    fn move_to_steps() {
+        let manager = MockMouseManager::new();
+        let step_delay = Duration::from_millis(10);
+        assert_eq!(manager.move_to_steps(100, 50, 4, step_delay), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(25, 13),
+                MouseEvent::AbsoluteMove(50, 25),
+                MouseEvent::AbsoluteMove(75, 38),
+                MouseEvent::AbsoluteMove(100, 50),
+            ]
+        );
+        assert_eq!(manager.state().sleeps(), vec![step_delay; 3]);
+
+        // Zero and one step are a single move
+        assert_eq!(manager.move_to_steps(0, 0, 0, step_delay), Ok(()));
+        assert_eq!(manager.move_to_steps(10, 10, 1, step_delay), Ok(()));
+        assert_eq!(
+            manager.events()[4..],
+            [
+                MouseEvent::AbsoluteMove(0, 0),
+                MouseEvent::AbsoluteMove(10, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_steps() {
+        let duration = Duration::from_millis(500);
+        assert_eq!(common::smooth_steps(duration, 60.0), 30);
+        assert_eq!(common::smooth_steps(duration, 144.0), 72);
+        assert_eq!(common::smooth_steps(Duration::from_millis(100), 59.94), 6);
+        assert_eq!(common::smooth_steps(Duration::ZERO, 60.0), 1);
+        assert_eq!(common::smooth_steps(duration, 0.0), 30);
+        assert_eq!(common::smooth_steps(duration, f32::NAN), 30);
+    }
+
+    #[test]
+    fn move_to_smooth() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(
+            manager.move_to_smooth(100, 0, Duration::from_millis(50)),
+            Ok(())
+        );
+        // 60 Hz is assumed without a known refresh rate
+        assert_eq!(manager.events().len(), 3);
+        assert_eq!(manager.events()[2], MouseEvent::AbsoluteMove(100, 0));
+
+        manager.refresh_hz = Some(120.0);
+        assert_eq!(
+            manager.move_to_smooth(0, 0, Duration::from_millis(50)),
+            Ok(())
+        );
+        assert_eq!(manager.events().len(), 3 + 6);
+        assert_eq!(
+            manager.state().sleeps()[2..],
+            [Duration::from_nanos(8_333_333); 5]
+        );
+    }
+
+    #[test]
+    fn walk_to() {
+        let manager = MockMouseManager::new();
+        manager.set_position(10, 500);
+        let step_delay = Duration::from_millis(4);
+        assert_eq!(manager.walk_to(103, 460, 10, step_delay), Ok(()));
+        assert_eq!(manager.get_position(), Ok((103, 460)));
+
+        let events = manager.events();
+        assert_eq!(events.len(), 11);
+        for event in &events {
+            let MouseEvent::RelativeMove(x_offset, y_offset) = *event else {
+                panic!("{event:?} is not a relative move");
+            };
+            assert!((x_offset as f64).hypot(y_offset as f64) <= 10.0);
+        }
+        assert_eq!(manager.state().sleeps(), [step_delay; 10]);
+
+        // The pointer acceleration overshoots, the following steps are shorter
+        let manager = MockMouseManager::new();
+        manager.script_positions(&[(0, 0), (45, 0)]);
+        assert_eq!(manager.walk_to(20, 0, 10, step_delay), Ok(()));
+        assert_eq!(manager.get_position(), Ok((20, 0)));
+        let events = manager.events();
+        assert_eq!(
+            events[..2],
+            [
+                MouseEvent::RelativeMove(10, 0),
+                MouseEvent::RelativeMove(-5, 0)
+            ]
+        );
+        assert!(events[2..]
+            .iter()
+            .all(|event| matches!(event, MouseEvent::RelativeMove(x, 0) if x.abs() <= 5)));
+
+        // The cursor is stuck at the edge of the screen, it gives up after a few steps
+        let manager = MockMouseManager::new();
+        manager.script_positions(&[(0, 0); 5]);
+        assert_eq!(
+            manager.walk_to(50, 0, 10, step_delay),
+            Err(Error::MoveNotConfirmed(0, 0))
+        );
+        assert_eq!(manager.events().len(), 4);
+    }
+
+    #[test]
+    fn move_to_smooth_global() {
+        let mut manager = MockMouseManager::new();
+        // A shorter monitor to the right, top aligned, and one below that
+        // one, which does not share an edge with the primary monitor
+        let right = Monitor {
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 720,
+        };
+        let below = Monitor {
+            x: 2200,
+            y: 720,
+            width: 1280,
+            height: 720,
+        };
+        manager.other_monitors = vec![right];
+        manager.set_position(100, 1050);
+
+        // The straight line would cross x = 1920 at y = 830, below the right monitor
+        let step_delay = Duration::from_millis(5);
+        assert_eq!(
+            manager.move_to_smooth_global(3000, 700, 40, step_delay),
+            Ok(())
+        );
+        let events = manager.events();
+        assert!(events.len() >= 40);
+        assert_eq!(manager.state().sleeps(), vec![step_delay; events.len() - 1]);
+        let monitors = manager.get_monitors().unwrap();
+        for event in &events {
+            let MouseEvent::AbsoluteMove(x, y) = *event else {
+                panic!("unexpected event {:?}", event);
+            };
+            assert!(monitors.iter().any(|monitor| monitor.contains(x, y)));
+        }
+        assert!(events.contains(&MouseEvent::AbsoluteMove(1919, 719)));
+        assert!(events.contains(&MouseEvent::AbsoluteMove(1920, 719)));
+        assert_eq!(events.last(), Some(&MouseEvent::AbsoluteMove(3000, 700)));
+
+        // The monitor below is reached through the right one
+        let mut manager = MockMouseManager::new();
+        manager.other_monitors = vec![right, below];
+        manager.set_position(100, 100);
+        assert_eq!(
+            manager.move_to_smooth_global(2500, 1400, 40, step_delay),
+            Ok(())
+        );
+        let events = manager.events();
+        let on_monitor = |index: usize, event: &MouseEvent| {
+            let MouseEvent::AbsoluteMove(x, y) = *event else {
+                panic!("unexpected event {:?}", event);
+            };
+            [manager.primary_monitor, right, below][index].contains(x, y)
+        };
+        // The path goes through the monitors in order
+        let mut monitor = 0;
+        for event in &events {
+            if !on_monitor(monitor, event) {
+                monitor += 1;
+                assert!(on_monitor(monitor, event));
+            }
+        }
+        assert_eq!(monitor, 2);
+        assert_eq!(events.last(), Some(&MouseEvent::AbsoluteMove(2500, 1400)));
+    }
+
+    #[test]
+    fn execute() {
+        let manager = MockMouseManager::new();
+        let actions = [
+            Action::MoveTo(100, 200),
+            Action::Click(MouseButton::Left),
+            Action::Sleep(Duration::from_millis(50)),
+            Action::MoveRelative(10, -10),
+            Action::Press(MouseButton::Right),
+            Action::Release(MouseButton::Right),
+            Action::Scroll(ScrollDirection::Up),
+            Action::Drag(MouseButton::Left, 300, 400),
+        ];
+        assert_eq!(manager.execute(&actions), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(100, 200),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::RelativeMove(10, -10),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+                MouseEvent::Scroll(ScrollDirection::Up),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::AbsoluteMove(300, 400),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+        assert_eq!(manager.state().sleeps(), vec![Duration::from_millis(50)]);
+    }
+
+    #[test]
+    fn dispatch_many() {
+        let manager = MockMouseManager::new();
+        let events = [(Duration::ZERO, MouseEvent::Press(MouseButton::Left))];
+        assert!(manager.dispatch_many(&events, 0.0).is_err());
+        assert!(manager.dispatch_many(&events, -1.0).is_err());
+        assert_eq!(manager.events(), vec![]);
+
+        let events = [
+            (Duration::from_millis(100), MouseEvent::AbsoluteMove(10, 20)),
+            (
+                Duration::from_millis(100),
+                MouseEvent::Press(MouseButton::Left),
+            ),
+            (Duration::ZERO, MouseEvent::RelativeMove(5, 5)),
+            (
+                Duration::from_millis(200),
+                MouseEvent::Release(MouseButton::Left),
+            ),
+        ];
+        // Every action takes 10ms, which is subtracted from the next wait
+        manager.slow_actions(Duration::from_millis(10));
+        assert_eq!(manager.dispatch_many(&events, 2.0), Ok(()));
+        assert_eq!(
+            manager.events(),
+            events.iter().map(|(_, event)| *event).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            manager.state().sleeps(),
+            vec![
+                Duration::from_millis(50),
+                Duration::from_millis(40),
+                Duration::from_millis(80),
+            ]
+        );
+        // The last event was sent at 200ms, the replay did not drift
+        let total =
+            manager.state().sleeps().iter().sum::<Duration>() + 4 * Duration::from_millis(10);
+        assert_eq!(total, Duration::from_millis(210));
+    }
+
+    #[test]
+    fn move_relative_limited() {
+        let manager = MockMouseManager::new();
+        assert!(manager.move_relative_limited(10, 10, 0.0).is_err());
+        assert_eq!(manager.move_relative_limited(0, 0, 5.0), Ok(()));
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(manager.move_relative_limited(100, -35, 10.0), Ok(()));
+        let events = manager.events();
+        // The move is about 106 pixels long, so it takes 11 steps of up to 10 pixels
+        assert_eq!(events.len(), 11);
+        let (mut x, mut y) = (0, 0);
+        for event in events {
+            let MouseEvent::RelativeMove(x_offset, y_offset) = event else {
+                panic!("unexpected event {:?}", event);
+            };
+            assert!((x_offset as f32).hypot(y_offset as f32) <= 11.0);
+            (x, y) = (x + x_offset, y + y_offset);
+        }
+        assert_eq!((x, y), (100, -35));
+
+        // Without a limit it is a single move
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.move_relative_limited(30, 40, f32::INFINITY), Ok(()));
+        assert_eq!(manager.move_relative_limited(-30, 0, f32::MAX), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::RelativeMove(30, 40),
+                MouseEvent::RelativeMove(-30, 0)
+            ]
+        );
+        assert_eq!(manager.state().sleeps(), vec![]);
+    }
+
+    #[test]
+    fn shake() {
+        let manager = MockMouseManager::new();
+        assert!(manager.shake(u32::MAX, 1, Duration::ZERO).is_err());
+        assert_eq!(manager.shake(0, 3, Duration::ZERO), Ok(()));
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(manager.move_to(5, 300), Ok(()));
+        assert_eq!(manager.shake(20, 2, Duration::from_millis(60)), Ok(()));
+        let events = manager.events();
+        // Only the relative moves were sent, they cancel each other out
+        assert_eq!(events.len(), 7);
+        let mut x = 0;
+        for event in &events[1..] {
+            let MouseEvent::RelativeMove(x_offset, 0) = event else {
+                panic!("unexpected event {:?}", event);
+            };
+            assert!(x_offset.abs() <= 40);
+            x += x_offset;
+        }
+        assert_eq!(x, 0);
+        assert_eq!(manager.get_position(), Ok((5, 300)));
+        assert_eq!(manager.state().sleeps(), vec![Duration::from_millis(10); 5]);
+    }
+
+    #[test]
+    fn hook_is_active() {
+        let mut manager = MockMouseManager::new();
+        assert!(!manager.hook_is_active(0));
+        assert_eq!(manager.last_event_at(), None);
+
+        let id = manager.hook(Box::new(|_| {})).unwrap();
+        assert!(manager.hook_is_active(id));
+        assert!(!manager.hook_is_active(id + 1));
+
+        let before = Instant::now();
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        assert!(manager.last_event_at().is_some_and(|at| at >= before));
+
+        manager.unhook(id).unwrap();
+        assert!(!manager.hook_is_active(id));
+        // Shutting down stops the listener along with the hooks
+        let id = manager.hook(Box::new(|_| {})).unwrap();
+        manager.shutdown().unwrap();
+        assert!(!manager.hook_is_active(id));
+    }
+
+    #[test]
+    fn hook_filtered_kinds() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        manager
+            .hook_filtered_kinds(
+                EventKindSet::ALL,
+                Box::new(move |event| sender.send(*event).unwrap()),
+            )
+            .unwrap();
+        let (sender, clicks) = mpsc::channel();
+        let kinds = EventKindSet::PRESS | EventKindSet::RELEASE;
+        assert!(!kinds.contains(EventKindSet::MOVE));
+        manager
+            .hook_filtered_kinds(kinds, Box::new(move |event| sender.send(*event).unwrap()))
+            .unwrap();
+
+        let events = [
+            MouseEvent::RelativeMove(1, 1),
+            MouseEvent::Press(MouseButton::Left),
+            MouseEvent::AbsoluteMove(5, 5),
+            MouseEvent::Scroll(ScrollDirection::Up),
+            MouseEvent::Release(MouseButton::Left),
+        ];
+        for event in events {
+            manager.inject(event);
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), events);
+        assert_eq!(
+            clicks.try_iter().collect::<Vec<_>>(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn hover() {
+        let manager = MockMouseManager::new();
+        manager.slow_actions(Duration::from_millis(5));
+        let start = manager.state().now();
+        assert_eq!(manager.hover(30, 40, Duration::from_millis(500)), Ok(()));
+        assert_eq!(manager.events(), vec![MouseEvent::AbsoluteMove(30, 40)]);
+        assert_eq!(manager.state().sleeps(), vec![Duration::from_millis(500)]);
+        assert_eq!(manager.state().now() - start, Duration::from_millis(505));
+    }
+
+    #[test]
+    fn move_to_normalized() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.move_to_normalized(0.5, 1.0), Ok(()));
+        assert_eq!(manager.move_to_normalized(-1.0, 0.25), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(960, 1079),
+                MouseEvent::AbsoluteMove(0, 270),
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_normalized_absolute() {
+        assert_eq!(common::to_normalized_absolute(0.0), 0);
+        assert_eq!(common::to_normalized_absolute(1.0), 65535);
+        assert_eq!(common::to_normalized_absolute(0.5), 32768);
+        assert_eq!(common::to_normalized_absolute(0.25), 16384);
+        assert_eq!(common::to_normalized_absolute(-0.5), 0);
+        assert_eq!(common::to_normalized_absolute(2.0), 65535);
+    }
+
+    #[test]
+    fn with_listener_priority() {
+        let manager: Box<dyn MouseActions> = Box::new(MockMouseManager::new());
+        assert_eq!(manager.state().listener_priority(), Priority::Normal);
+        let mut manager = manager.with_listener_priority(Priority::RealTime);
+        assert_eq!(manager.state().listener_priority(), Priority::RealTime);
+        // It is part of the setup of the manager, not of its configuration
+        manager.reset();
+        assert_eq!(manager.state().listener_priority(), Priority::RealTime);
+    }
+
+    #[test]
+    fn supported_buttons() {
+        let manager = MockMouseManager::new();
+        let buttons = manager.supported_buttons();
+        assert_eq!(buttons.len(), 3);
+        for button in buttons {
+            assert_eq!(manager.click_button(button), Ok(()));
+        }
+    }
+
+    #[test]
+    fn human_path() {
+        let (from, to) = ((100, 200), (700, -100));
+        let margin = 0.3 * 600f64.hypot(300.0) + 1.0;
+        for seed in 0..20 {
+            let path = common::human_path(from, to, 40, seed);
+            assert_eq!(path.len(), 41);
+            assert_eq!(path.first(), Some(&from));
+            assert_eq!(path.last(), Some(&to));
+            assert_eq!(path, common::human_path(from, to, 40, seed));
+            for &(x, y) in &path {
+                assert!((100.0 - margin..=700.0 + margin).contains(&(x as f64)));
+                assert!((-100.0 - margin..=200.0 + margin).contains(&(y as f64)));
+            }
+            // The path bends away from the straight line
+            assert!(path
+                .iter()
+                .any(|point| common::segment_distance(*point, from, to) > 5.0));
+        }
+        assert_ne!(
+            common::human_path(from, to, 40, 1),
+            common::human_path(from, to, 40, 2)
+        );
+
+        assert_eq!(common::human_path(from, to, 0, 7), vec![from, to]);
+        assert_eq!(common::human_path(from, from, 3, 7), vec![from; 4]);
+    }
+
+    #[test]
+    fn simplify_path() {
+        // A wobbly line to the right, then a sharp turn down and a closed loop
+        let path = [
+            (0, 0),
+            (10, 1),
+            (20, -1),
+            (30, 1),
+            (40, 0),
+            (40, 10),
+            (40, 20),
+            (40, 30),
+            (0, 0),
+        ];
+        assert_eq!(
+            common::simplify_path(&path, 2.0),
+            vec![(0, 0), (40, 0), (40, 30), (0, 0)]
+        );
+        // Nothing is within a zero epsilon except the collinear points
+        assert_eq!(
+            common::simplify_path(&path, 0.0),
+            vec![
+                (0, 0),
+                (10, 1),
+                (20, -1),
+                (30, 1),
+                (40, 0),
+                (40, 30),
+                (0, 0),
+            ]
+        );
+        assert_eq!(common::simplify_path(&path[..2], 100.0), path[..2]);
+        assert_eq!(common::simplify_path(&[], 1.0), vec![]);
+    }
+
+    #[test]
+    fn is_mouse_active() {
+        let mut manager = MockMouseManager::new();
+        let within = Duration::from_secs(60);
+        // The listener is started on the first call, nothing was seen yet
+        assert_eq!(manager.is_mouse_active(within), Ok(false));
+        let hook = manager.hook(Box::new(|_| {})).unwrap();
+        assert!(manager.hook_is_active(hook));
+
+        manager.inject(MouseEvent::RelativeMove(1, 0));
+        assert_eq!(manager.is_mouse_active(within), Ok(true));
+        manager.state().advance(within * 2);
+        assert_eq!(manager.is_mouse_active(within), Ok(false));
+
+        let mut headless = HeadlessMouse::new(100, 100);
+        assert_eq!(headless.is_mouse_active(within), Err(Error::NotImplemented));
+    }
+
+    #[test]
+    fn prefer_relative_moves() {
+        let mut manager = MockMouseManager::new();
+        manager.set_position(50, 50);
+        manager.prefer_relative_moves(true);
+        assert_eq!(manager.move_to(200, 200), Ok(()));
+        assert_eq!(manager.get_position(), Ok((200, 200)));
+        assert_eq!(manager.events(), vec![MouseEvent::RelativeMove(150, 150)]);
+
+        manager.prefer_relative_moves(false);
+        assert_eq!(manager.move_to(10, 20), Ok(()));
+        assert_eq!(manager.events()[1..], [MouseEvent::AbsoluteMove(10, 20)]);
+    }
+
+    #[test]
+    fn dpi_awareness_is_windows_only() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(manager.set_dpi_awareness(), Err(Error::NotImplemented));
+        assert_eq!(manager.get_dpi_for_point(0, 0), Err(Error::NotImplemented));
+    }
+
+    #[test]
+    fn atomic() {
+        let manager = MockMouseManager::new();
+        let drag = [
+            MouseEvent::AbsoluteMove(100, 100),
+            MouseEvent::Press(MouseButton::Left),
+            MouseEvent::RelativeMove(200, 0),
+            MouseEvent::Release(MouseButton::Left),
+        ];
+        assert_eq!(manager.atomic(&drag), Ok(()));
+        assert_eq!(manager.events(), drag);
+        assert_eq!(manager.get_position(), Ok((300, 100)));
+    }
+
+    #[test]
+    fn click_points() {
+        let manager = MockMouseManager::new();
+        let between = Duration::from_millis(50);
+        assert_eq!(
+            manager.click_points(&[], &MouseButton::Left, between),
+            Ok(())
+        );
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(
+            manager.click_points(
+                &[(40, 100), (40, 130), (40, 160)],
+                &MouseButton::Left,
+                between
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(40, 100),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::AbsoluteMove(40, 130),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::AbsoluteMove(40, 160),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+        assert_eq!(manager.state().sleeps(), vec![between; 2]);
+    }
+
+    #[test]
+    fn reset_keeps_the_hooks() {
+        let mut manager = MockMouseManager::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        manager
+            .hook(Box::new(move |event| sink.lock().unwrap().push(*event)))
+            .unwrap();
+        manager.set_click_hold_range(Duration::from_millis(5), Duration::from_millis(10));
+        manager.set_precise_timing(true);
+        manager.prefer_relative_moves(true);
+        manager.set_max_scroll_rate(Some(20));
+        manager.set_coordinate_origin(CoordinateOrigin::BottomLeft);
+        manager.set_button_map(HashMap::from([(MouseButton::Left, MouseButton::Right)]));
+        manager.move_to(10, 20).unwrap();
+        manager.click_button(&MouseButton::Left).unwrap();
+
+        manager.reset();
+        let state = manager.state();
+        assert_eq!(
+            *state.settings.click_hold_range.lock().unwrap(),
+            (Duration::from_millis(60), Duration::from_millis(120))
+        );
+        assert!(!*state.settings.precise_timing.lock().unwrap());
+        assert!(!state.prefers_relative_moves());
+        assert_eq!(*state.settings.max_scroll_rate.lock().unwrap(), None);
+        assert_eq!(*state.settings.last_click.lock().unwrap(), None);
+        assert_eq!(state.coordinate_origin(), CoordinateOrigin::TopLeft);
+        assert_eq!(state.map_button(&MouseButton::Left), MouseButton::Left);
+
+        manager.inject(MouseEvent::Press(MouseButton::Right));
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![MouseEvent::Press(MouseButton::Right)]
+        );
+    }
+
+    #[test]
+    fn drag_and_drop() {
+        let manager = MockMouseManager::new();
+        assert_eq!(
+            manager.drag_and_drop(&MouseButton::Left, (100, 100), (40, 100)),
+            Ok(())
+        );
+        // The first moves leave the threshold of 4 pixels towards the target
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(100, 100),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::RelativeMove(-1, 0),
+                MouseEvent::RelativeMove(-4, 0),
+                MouseEvent::AbsoluteMove(40, 100),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn select_rectangle() {
+        let manager = MockMouseManager::new();
+        let duration = Duration::from_millis(50);
+        assert_eq!(
+            manager.select_rectangle((10, 10), (70, 40), &MouseButton::Left, duration),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(10, 10),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::AbsoluteMove(30, 20),
+                MouseEvent::AbsoluteMove(50, 30),
+                MouseEvent::AbsoluteMove(70, 40),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+        let frame = Duration::from_secs_f32(1.0 / 60.0);
+        let step = duration / 3;
+        assert_eq!(manager.state().sleeps(), [frame, step, step, frame]);
+
+        // A rectangle without an area is still pressed and released
+        assert_eq!(
+            manager.select_rectangle((5, 5), (5, 5), &MouseButton::Right, duration),
+            Ok(())
+        );
+        let events = &manager.events()[6..];
+        assert_eq!(events[1], MouseEvent::Press(MouseButton::Right));
+        assert_eq!(
+            events.last(),
+            Some(&MouseEvent::Release(MouseButton::Right))
+        );
+    }
+
+    #[test]
+    fn active_pointer() {
+        let pointer = |id, role, master| PointerDevice {
+            id,
+            name: format!("pointer {id}"),
+            role,
+            master,
+        };
+        let mut manager = MockMouseManager::new();
+        assert_eq!(manager.list_pointers(), Ok(vec![]));
+        manager.pointers = vec![
+            pointer(2, PointerRole::Master, None),
+            pointer(8, PointerRole::Slave, Some(2)),
+            pointer(12, PointerRole::Master, None),
+            pointer(14, PointerRole::Floating, None),
+        ];
+        assert_eq!(manager.list_pointers().unwrap().len(), 4);
+
+        // A slave pointer drives the cursor of its master pointer
+        assert_eq!(
+            manager.set_active_pointer(&manager.pointers[1].clone()),
+            Ok(())
+        );
+        assert_eq!(manager.active_pointer, Some(2));
+        assert_eq!(
+            manager.set_active_pointer(&manager.pointers[2].clone()),
+            Ok(())
+        );
+        assert_eq!(manager.active_pointer, Some(12));
+        assert!(manager
+            .set_active_pointer(&manager.pointers[3].clone())
+            .is_err());
+        assert_eq!(manager.active_pointer, Some(12));
+        manager.reset();
+        assert_eq!(manager.active_pointer, None);
+
+        // The device must be attached to a master pointer that is listed
+        let pointers = &manager.pointers;
+        assert_eq!(common::active_master(pointers, &pointers[1]), Ok(2));
+        assert_eq!(
+            common::active_master(pointers, &pointer(9, PointerRole::Slave, Some(4))),
+            Err(Error::CustomError("the master pointer does not exist"))
+        );
+        assert_eq!(
+            common::active_master(pointers, &pointer(4, PointerRole::Master, None)),
+            Err(Error::CustomError("the master pointer does not exist"))
+        );
+        assert_eq!(
+            common::active_master(pointers, &pointers[3]),
+            Err(Error::CustomError("a floating pointer has no cursor"))
+        );
+    }
+
+    #[test]
+    fn open_context_menu() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.open_context_menu(400, 300), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(400, 300),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn monitor_clamp() {
+        let area = Monitor {
+            x: -1920,
+            y: 40,
+            width: 1920,
+            height: 1000,
+        };
+        assert_eq!(area.clamp(-100, 500), (-100, 500));
+        assert_eq!(area.clamp(-3000, 0), (-1920, 40));
+        assert_eq!(area.clamp(0, 2000), (-1, 1039));
+        assert_eq!(area.clamp(-1, 1039), (-1, 1039));
+
+        let empty = Monitor {
+            x: 10,
+            y: 10,
+            width: 0,
+            height: 0,
+        };
+        assert_eq!(empty.clamp(50, -50), (10, 10));
+    }
+
+    #[test]
+    fn move_to_work_area_clamped() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(
+            manager.move_to_work_area_clamped(10, 10),
+            Err(Error::NotImplemented)
+        );
+        assert_eq!(manager.events(), vec![]);
+
+        // A taskbar of 40 pixels at the bottom of the display
+        manager.work_area = Some(Monitor {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1040,
+        });
+        assert_eq!(manager.move_to_work_area_clamped(500, 1060), Ok(()));
+        assert_eq!(manager.move_to_work_area_clamped(-5, 300), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(500, 1039),
+                MouseEvent::AbsoluteMove(0, 300),
+            ]
+        );
+
+        // The taskbar is at the bottom with the bottom-left origin as well
+        manager.set_coordinate_origin(CoordinateOrigin::BottomLeft);
+        assert_eq!(manager.move_to_work_area_clamped(500, 10), Ok(()));
+        assert_eq!(manager.get_position(), Ok((500, 40)));
+        assert_eq!(manager.events()[2], MouseEvent::AbsoluteMove(500, 1039));
+    }
+
+    #[test]
+    fn click_here() {
+        let manager = MockMouseManager::new();
+        manager.set_position(30, 40);
+        assert_eq!(manager.click_here(&MouseButton::Left, false), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+
+        assert_eq!(manager.click_here(&MouseButton::Right, true), Ok(()));
+        assert_eq!(
+            manager.events()[2..],
+            [
+                MouseEvent::RelativeMove(1, 0),
+                MouseEvent::RelativeMove(-1, 0),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+        assert_eq!(manager.get_position(), Ok((30, 40)));
+    }
+
+    #[test]
+    fn with_modifiers() {
+        let manager: Box<dyn MouseActions> = Box::new(MockMouseManager::new());
+        manager.hold_modifiers(&[Modifier::Shift]).unwrap();
+        let result = manager.with_modifiers(&[Modifier::Control, Modifier::Shift], |manager| {
+            assert_eq!(
+                manager.state().held_modifiers(),
+                vec![Modifier::Shift, Modifier::Control]
+            );
+            Err(Error::CustomError("failed"))
+        });
+        assert_eq!(result, Err(Error::CustomError("failed")));
+        assert_eq!(manager.state().held_modifiers(), vec![Modifier::Shift]);
+
+        // The modifiers are restored when the closure panics as well
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            manager.with_modifiers(&[Modifier::Alt], |_| panic!("the closure panicked"))
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(manager.state().held_modifiers(), vec![Modifier::Shift]);
+    }
+
+    #[test]
+    fn modifiers_are_released_on_shutdown() {
+        let manager = MockMouseManager::new();
+        manager.hold_modifiers(&[Modifier::Shift]).unwrap();
+        let mut output = manager.output();
+        // The output still shares the modifiers
+        drop(manager);
+        assert_eq!(output.state().held_modifiers(), vec![Modifier::Shift]);
+        assert_eq!(output.modifier_changes(), vec![]);
+
+        assert_eq!(output.shutdown(), Ok(()));
+        assert_eq!(output.state().held_modifiers(), vec![]);
+        assert_eq!(output.modifier_changes(), vec![(0, vec![])]);
+    }
+
+    #[test]
+    fn dropping_an_output_keeps_the_hooks() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        let id = manager
+            .hook(Box::new(move |event| {
+                let _ = sender.send(*event);
+            }))
+            .unwrap();
+        drop(manager.output());
+        drop(manager.output_handle().unwrap());
+
+        assert!(manager.hook_is_active(id));
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(MouseEvent::Press(MouseButton::Left))
+        );
+    }
+
+    #[test]
+    fn unhooked_callbacks_are_dropped_after_the_lock() {
+        // Like the output of `hook_reactive`, the callback owns a manager that
+        // locks the hooks when it is dropped
+        struct CountOnDrop(MockMouseManager, mpsc::Sender<usize>);
+        impl Drop for CountOnDrop {
+            fn drop(&mut self) {
+                let _ = self.1.send(self.0.hooked_count());
+            }
+        }
+
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..2 {
+            let owned = CountOnDrop(manager.output(), sender.clone());
+            manager
+                .hook(Box::new(move |_| {
+                    let _ = &owned;
+                }))
+                .unwrap();
+        }
+        manager.unhook(0).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        manager.unhook_all().unwrap();
+        assert_eq!(receiver.try_recv(), Ok(0));
+    }
+
+    #[test]
+    fn zoom() {
+        let mock = MockMouseManager::new();
+        let manager: &dyn MouseActions = &mock;
+        assert_eq!(manager.zoom(-3), Ok(()));
+        assert_eq!(
+            mock.events(),
+            vec![MouseEvent::Scroll(ScrollDirection::Down); 3]
+        );
+        // Ctrl is held before the first scroll and released after the last one
+        assert_eq!(
+            mock.modifier_changes(),
+            vec![(0, vec![Modifier::Control]), (3, vec![])]
+        );
+    }
+
+    #[test]
+    fn modifier_flags() {
+        assert_eq!(common::modifier_flags(&[]), 0);
+        assert_eq!(common::modifier_flags(&[Modifier::Shift]), 0x0002_0000);
+        assert_eq!(
+            common::modifier_flags(&[Modifier::Meta, Modifier::Control, Modifier::Alt]),
+            0x001C_0000
+        );
+    }
+
+    #[test]
+    fn watch_position() {
+        let mouse = HeadlessMouse::new(100, 100);
+        let (sender, receiver) = mpsc::channel();
+        let watch = mouse
+            .watch_position(
+                Duration::from_millis(1),
+                Box::new(move |position| sender.send(position).unwrap()),
+            )
+            .unwrap();
+        let timeout = Duration::from_secs(5);
+
+        mouse.move_to(10, 10).unwrap();
+        assert_eq!(receiver.recv_timeout(timeout), Ok((10, 10)));
+        mouse.move_relative(0, 0).unwrap();
+        mouse.move_to(20, 5).unwrap();
+        assert_eq!(receiver.recv_timeout(timeout), Ok((20, 5)));
+
+        // The callback is dropped with the thread
+        drop(watch);
+        mouse.move_to(30, 30).unwrap();
+        assert_eq!(receiver.recv(), Err(mpsc::RecvError));
+    }
+
+    #[test]
+    fn hook_detailed() {
+        let mut manager = MockMouseManager::new();
+        assert!(manager.listener_sources().is_empty());
+        let first = manager.hook_detailed(Box::new(|_| {})).unwrap();
+        let second = manager.hook_detailed(Box::new(|_| {})).unwrap();
+        assert_eq!(
+            first,
+            HookInfo {
+                id: 0,
+                backend: "mock",
+                devices: vec!["mock-device".to_string()],
+            }
+        );
+        assert_eq!(second.id, 1);
+        assert_eq!(second.devices, first.devices);
+        assert!(manager.hook_is_active(second.id));
+    }
+
+    #[test]
+    fn coordinate_origin() {
+        let mut manager = MockMouseManager::new();
+        // The display of the mock is 1920x1080
+        for (origin, sent) in [
+            (CoordinateOrigin::TopLeft, (30, 200)),
+            (CoordinateOrigin::BottomLeft, (30, 879)),
+        ] {
+            manager.set_coordinate_origin(origin);
+            assert_eq!(manager.move_to(30, 200), Ok(()));
+            assert_eq!(manager.get_position(), Ok((30, 200)));
+            assert_eq!(
+                manager.events().last(),
+                Some(&MouseEvent::AbsoluteMove(sent.0, sent.1))
+            );
+        }
+
+        // The relative path moves by the offset on the screen
+        manager.prefer_relative_moves(true);
+        assert_eq!(manager.move_to(30, 210), Ok(()));
+        assert_eq!(manager.get_position(), Ok((30, 210)));
+        assert_eq!(
+            manager.events().last(),
+            Some(&MouseEvent::RelativeMove(0, -10))
+        );
+    }
+
+    #[test]
+    fn sample_positions() {
+        let manager = MockMouseManager::new();
+        let interval = Duration::from_millis(10);
+        assert_eq!(manager.sample_positions(interval, 0), Ok(vec![]));
+
+        manager.script_positions(&[(0, 0), (10, 5), (25, 12)]);
+        assert_eq!(
+            manager.sample_positions(interval, 4),
+            Ok(vec![(0, 0), (10, 5), (25, 12), (25, 12)])
+        );
+        assert_eq!(manager.state().sleeps(), vec![interval; 3]);
+    }
+
+    #[test]
+    fn first_available_backend() {
+        let init = |backend: &&str| -> Result<Box<dyn MouseActions>, Error> {
+            match *backend {
+                "mock" => Ok(Box::new(MockMouseManager::new())),
+                _ => Err(Error::PermissionDenied),
+            }
+        };
+        let manager = common::first_available(&["failing", "mock", "failing"], init);
+        assert_eq!(manager.map(|manager| manager.backend_name()), Ok("mock"));
+
+        let manager = common::first_available(&["failing", "failing"], init);
+        assert_eq!(
+            manager.err(),
+            Some(Error::NoBackendAvailable(vec![
+                Error::PermissionDenied,
+                Error::PermissionDenied
+            ]))
+        );
+        assert_eq!(
+            Error::NoBackendAvailable(vec![Error::PermissionDenied, Error::NotImplemented])
+                .to_string(),
+            "none of the backends could be initialized; \
+             permission denied for this operation, plese try as super user; \
+             this function is not implemented for the current platform"
+        );
+    }
+
+    #[test]
+    fn release_all_buttons() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
+        assert_eq!(manager.release_all_buttons(), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Middle),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn headless_clamps_moves() {
+        let mut manager = HeadlessMouse::new(800, 600);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let hooked_events = events.clone();
+        manager
+            .hook(Box::new(move |event| {
+                hooked_events.lock().unwrap().push(*event)
+            }))
+            .unwrap();
+
+        assert_eq!(manager.get_position(), Ok((0, 0)));
+        assert_eq!(manager.move_to(5000, 300), Ok(()));
+        assert_eq!(manager.get_position(), Ok((799, 300)));
+        assert_eq!(manager.move_relative(-1000, 20), Ok(()));
+        assert_eq!(manager.get_position(), Ok((0, 320)));
+        // A move that is fully clamped does not move at all
+        assert_eq!(manager.move_relative(-10, 0), Ok(()));
+        assert_eq!(manager.r#move(MoveKind::Absolute(-50, -50)), Ok(()));
+        assert_eq!(manager.get_position(), Ok((0, 0)));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                MouseEvent::AbsoluteMove(799, 300),
+                MouseEvent::RelativeMove(-799, 20),
+                MouseEvent::AbsoluteMove(0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn headless_tracks_buttons() {
+        let mut manager = HeadlessMouse::new(800, 600);
+        manager.add_target("ok", 100, 100, 80, 30);
+        manager.add_target("cancel", 200, 100, 80, 30);
+        assert_eq!(manager.target_at(150, 110), Some("ok"));
+        assert_eq!(manager.target_at(190, 110), None);
+
+        assert!(!manager.is_button_pressed(&MouseButton::Left));
+        manager.move_to(120, 110).unwrap();
+        manager.press_button(&MouseButton::Left).unwrap();
+        assert!(manager.is_button_pressed(&MouseButton::Left));
+        assert!(!manager.is_button_pressed(&MouseButton::Right));
+        manager.release_button(&MouseButton::Left).unwrap();
+        assert!(!manager.is_button_pressed(&MouseButton::Left));
+
+        // Dragging off the target is not a click
+        manager.press_button(&MouseButton::Left).unwrap();
+        manager.move_to(250, 110).unwrap();
+        manager.release_button(&MouseButton::Left).unwrap();
+        manager.click_button(&MouseButton::Right).unwrap();
+        assert_eq!(
+            manager.clicked_targets(),
+            vec![
+                (MouseButton::Left, "ok".to_string()),
+                (MouseButton::Right, "cancel".to_string()),
+            ]
+        );
+
+        manager.scroll_vector(-2, 3).unwrap();
+        assert_eq!(manager.scrolled(), (-2, 3));
+    }
+
+    #[test]
+    fn hook_with_context() {
+        let mut mock = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        mock.hook_with_context(Box::new(move |event, context| {
+            sender.send((*event, context.clone())).unwrap()
+        }))
+        .unwrap();
+        mock.inject(MouseEvent::Press(MouseButton::Left));
+        // Only MacOS knows the frontmost application
+        assert_eq!(
+            receiver.recv(),
+            Ok((
+                MouseEvent::Press(MouseButton::Left),
+                EventContext::default()
+            ))
+        );
+    }
+
+    #[test]
+    fn move_to_verified() {
+        let ms = Duration::from_millis;
+        let manager = MockMouseManager::new();
+        // The cursor lags behind and converges after a couple of polls, the
+        // first position is the check that `get_position` works
+        manager.script_positions(&[(0, 0), (0, 0), (40, 45), (99, 101)]);
+        assert_eq!(manager.move_to_verified(100, 100, 1, ms(500)), Ok(()));
+        assert_eq!(manager.events(), vec![MouseEvent::AbsoluteMove(100, 100)]);
+        assert_eq!(manager.state().sleeps(), vec![ms(10), ms(10)]);
+
+        // The cursor never arrives
+        manager.script_positions(&[(99, 101), (99, 101), (50, 50)]);
+        assert_eq!(
+            manager.move_to_verified(200, 100, 2, ms(25)),
+            Err(Error::MoveNotConfirmed(50, 50))
+        );
+        assert_eq!(manager.state().sleeps()[2..], [ms(10), ms(10), ms(5)]);
+    }
+
+    #[test]
+    fn run_macro() {
+        let library = MacroLibrary::parse(
+            "# Two macros
+            [context_menu]
+            move_to 400 300
+            click right
+            sleep 200
+
+            [select]
+            press left
+            move_relative 20 -5
+            release left
+            scroll down",
+        )
+        .unwrap();
+        assert_eq!(
+            library.names().collect::<Vec<_>>(),
+            vec!["context_menu", "select"]
+        );
+
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.run_macro(&library, "select"), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::RelativeMove(20, -5),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Scroll(ScrollDirection::Down),
+            ]
+        );
+        assert_eq!(
+            manager.run_macro(&library, "missing"),
+            Err(Error::UnknownMacro(String::from("missing")))
+        );
+    }
+
+    #[test]
+    fn macro_library_validates() {
+        let invalid = |source| MacroLibrary::parse(source).unwrap_err();
+        assert_eq!(
+            invalid("[a]\nsleep -5"),
+            Error::InvalidMacro(2, "the sleep must be a positive number of milliseconds")
+        );
+        assert_eq!(
+            invalid("[a]\nmove_to -1 10"),
+            Error::InvalidMacro(2, "the coordinates must not be negative")
+        );
+        assert_eq!(
+            invalid("[a]\nmove_to 1 ten"),
+            Error::InvalidMacro(2, "expected a number")
+        );
+        assert_eq!(
+            invalid("click left"),
+            Error::InvalidMacro(1, "the action does not belong to a macro")
+        );
+        assert_eq!(
+            invalid("[a]\n\n[a]"),
+            Error::InvalidMacro(3, "the macro is defined more than once")
+        );
+        assert_eq!(
+            invalid("[a]\nclick left twice"),
+            Error::InvalidMacro(2, "too many arguments")
+        );
+        assert_eq!(
+            invalid("[a]\njump"),
+            Error::InvalidMacro(2, "unknown action")
+        );
+        assert_eq!(
+            invalid("[]"),
+            Error::InvalidMacro(1, "expected a macro name in square brackets")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn macro_library_serde() {
+        let library = MacroLibrary::parse(
+            "[menu]\nmove_to 400 300\nclick right\nsleep 200\ndrag left 10 20\nscroll down",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&library).unwrap();
+        assert_eq!(
+            json,
+            r#"{"menu":[{"move_to":[400,300]},{"click":"right"},{"sleep":200},{"drag":["left",10,20]},{"scroll":"down"}]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<MacroLibrary>(&json).unwrap(),
+            library
+        );
+        assert!(serde_json::from_str::<MacroLibrary>(r#"{"menu":[{"jump":1}]}"#).is_err());
+    }
 
     #[test]
-    #[ignore]
-    fn move_to_right_bottom() {
-        let manager = Mouse::new();
-        assert_eq!(manager.move_to(1920, 1080), Ok(()));
+    fn parse_script() {
+        use crate::macros::parse_script;
+
+        assert_eq!(
+            parse_script(
+                "# Open a file
+                move 10 20
+                click right
+
+                sleep 500
+                scroll up
+                scroll left 2
+                drag 0 5 300 400
+                drag middle 1 2"
+            ),
+            Ok(vec![
+                Action::MoveTo(10, 20),
+                Action::Click(MouseButton::Right),
+                Action::Sleep(Duration::from_millis(500)),
+                Action::Scroll(ScrollDirection::Up),
+                Action::Scroll(ScrollDirection::Left),
+                Action::Scroll(ScrollDirection::Left),
+                Action::MoveTo(0, 5),
+                Action::Drag(MouseButton::Left, 300, 400),
+                Action::Drag(MouseButton::Middle, 1, 2),
+            ])
+        );
+        assert_eq!(parse_script(""), Ok(vec![]));
+
+        let invalid = |source| parse_script(source).unwrap_err();
+        assert_eq!(
+            invalid("move 1 2\nmove -1 2"),
+            Error::InvalidMacro(2, "the coordinates must not be negative")
+        );
+        assert_eq!(
+            invalid("move 1"),
+            Error::InvalidMacro(1, "expected a number")
+        );
+        assert_eq!(
+            invalid("\n\nscroll down 0"),
+            Error::InvalidMacro(3, "the scroll count must not be 0")
+        );
+        assert_eq!(
+            invalid("scroll down 70000"),
+            Error::InvalidMacro(1, "the scroll count must be a number up to 65535")
+        );
+        assert_eq!(
+            invalid("scroll sideways 2"),
+            Error::InvalidMacro(1, "expected up, down, left or right as the direction")
+        );
+        assert_eq!(
+            invalid("drag 0 0 500 500 500"),
+            Error::InvalidMacro(1, "expected left, middle or right as the button")
+        );
+        assert_eq!(
+            invalid("click left\n[name]"),
+            Error::InvalidMacro(2, "unknown action")
+        );
     }
 
     #[test]
-    #[ignore]
-    fn move_to_top_left() {
-        let manager = Mouse::new();
-        assert_eq!(manager.move_to(0, 0), Ok(()));
+    fn run_script() {
+        let manager = MockMouseManager::new();
+        assert_eq!(
+            manager.run_script("move 100 200\nclick left\nscroll down 2\ndrag 0 0 50 60"),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(100, 200),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Scroll(ScrollDirection::Down),
+                MouseEvent::Scroll(ScrollDirection::Down),
+                MouseEvent::AbsoluteMove(0, 0),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::AbsoluteMove(50, 60),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+
+        // Nothing is run if a line is not valid
+        let manager = MockMouseManager::new();
+        assert_eq!(
+            manager.run_script("click left\njump"),
+            Err(Error::InvalidMacro(2, "unknown action"))
+        );
+        assert_eq!(manager.events(), vec![]);
     }
 
     #[test]
-    #[ignore]
-    fn move_to_left_to_right() {
-        let manager = Mouse::new();
-        let sleep_duration = Duration::from_millis(1);
-        let mut x = 0;
-        while x < 1920 {
-            assert_eq!(manager.move_to(x, 540), Ok(()));
-            x += 1;
-            thread::sleep(sleep_duration);
-        }
+    fn dispatch_at() {
+        let manager = MockMouseManager::new();
+        let start = manager.state().now();
+        let event = MouseEvent::Press(MouseButton::Left);
+        assert_eq!(
+            manager.dispatch_at(event, start + Duration::from_millis(40)),
+            Ok(())
+        );
+        // A time that has passed is sent right away
+        assert_eq!(manager.dispatch_at(event, start), Ok(()));
+        assert_eq!(manager.events(), vec![event; 2]);
+        assert_eq!(manager.state().sleeps(), vec![Duration::from_millis(40)]);
     }
 
     #[test]
-    #[ignore]
-    fn move_relative_left_to_right() {
-        let manager = Mouse::new();
-        let sleep_duration = Duration::from_millis(1);
-        let mut x = 0;
-        assert_eq!(manager.move_to(0, 540), Ok(()));
-        while x < 1920 {
-            assert_eq!(manager.move_relative(1, 0), Ok(()));
-            x += 1;
-            thread::sleep(sleep_duration);
+    fn schedule_sends_in_time_order() {
+        let ms = Duration::from_millis;
+        let manager = MockMouseManager::new();
+        let observer = manager.output();
+        let start = manager.state().now();
+        let scheduled = [
+            (ms(30), MouseEvent::Release(MouseButton::Left)),
+            (ms(10), MouseEvent::AbsoluteMove(5, 5)),
+            (ms(20), MouseEvent::Press(MouseButton::Left)),
+            (ms(30), MouseEvent::Scroll(ScrollDirection::Up)),
+            (ms(0), MouseEvent::AbsoluteMove(0, 0)),
+        ];
+        for (offset, event) in scheduled {
+            manager.schedule(event, start + offset).unwrap();
+        }
+        // The mock clock does not advance on its own, the queued events are sent
+        // once the manager is dropped
+        drop(manager);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while observer.events().len() < scheduled.len() && Instant::now() < deadline {
+            thread::sleep(ms(1));
         }
+        assert_eq!(
+            observer.events(),
+            vec![
+                MouseEvent::AbsoluteMove(0, 0),
+                MouseEvent::AbsoluteMove(5, 5),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Scroll(ScrollDirection::Up),
+            ]
+        );
+        assert_eq!(observer.state().sleeps(), vec![ms(10), ms(10), ms(10)]);
     }
 
     #[test]
-    #[ignore]
-    fn move_to_top_to_bottom() {
-        let manager = Mouse::new();
-        let sleep_duration = Duration::from_millis(1);
-        let mut y = 0;
-        while y < 1080 {
-            assert_eq!(manager.move_to(960, y), Ok(()));
-            y += 1;
-            thread::sleep(sleep_duration);
-        }
+    fn hook_many_rolls_back_on_failure() {
+        let mut manager = MockMouseManager::new();
+        manager.fail_hook_at = Some(1);
+        let result = manager.hook_many(vec![
+            Box::new(|e| println!("{:?}", e)),
+            Box::new(|e| println!("{:?}", e)),
+            Box::new(|e| println!("{:?}", e)),
+        ]);
+        assert_eq!(result, Err(Error::PermissionDenied));
+        assert_eq!(manager.hooked_count(), 0);
+        // The first callback got the id 0, it must not be hooked anymore
+        assert_eq!(manager.unhook(0), Err(Error::UnhookFailed));
     }
 
     #[test]
-    #[ignore]
-    fn move_relative_top_to_bottom() {
-        let manager = Mouse::new();
-        let sleep_duration = Duration::from_millis(1);
-        let mut y = 0;
-        assert_eq!(manager.move_to(960, 0), Ok(()));
-        while y < 1080 {
-            assert_eq!(manager.move_relative(0, 1), Ok(()));
-            y += 1;
-            thread::sleep(sleep_duration);
-        }
+    fn hook_many() {
+        let mut manager = MockMouseManager::new();
+        let ids = manager
+            .hook_many(vec![
+                Box::new(|e| println!("{:?}", e)),
+                Box::new(|e| println!("{:?}", e)),
+            ])
+            .unwrap();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(manager.hooked_count(), 2);
     }
 
     #[test]
-    #[ignore]
-    fn get_position() {
-        let manager = Mouse::new();
-        match manager.get_position() {
-            Ok(_) => {
-                let positions = vec![
-                    (0, 0),
-                    (100, 100),
-                    (250, 250),
-                    (325, 325),
-                    (400, 100),
-                    (100, 400),
-                ];
+    fn move_kind_routing() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.r#move(MoveKind::Absolute(100, 200)), Ok(()));
+        assert_eq!(manager.r#move(MoveKind::Relative(-50, 25)), Ok(()));
+        assert_eq!(manager.r#move(MoveKind::Percent(50.0, 50.0)), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(100, 200),
+                MouseEvent::RelativeMove(-50, 25),
+                MouseEvent::AbsoluteMove(960, 540),
+            ]
+        );
+    }
 
-                let mut x;
-                let mut y;
-                for position in positions.iter() {
-                    assert_eq!(manager.move_to(position.0, position.1), Ok(()));
-                    (x, y) = manager.get_position().unwrap();
-                    assert_eq!(x, position.0 as i32);
-                    assert_eq!(y, position.1 as i32);
-                }
+    #[test]
+    fn panicking_callback_does_not_stop_others() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        let (panic_sender, panic_receiver) = mpsc::channel();
+        manager
+            .on_callback_panic(Box::new(move |message| {
+                panic_sender.send(message.to_string()).unwrap()
+            }))
+            .unwrap();
+        manager
+            .hook(Box::new(|_| panic!("the first callback failed")))
+            .unwrap();
+        manager
+            .hook(Box::new(move |event| sender.send(*event).unwrap()))
+            .unwrap();
+
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        manager.inject(MouseEvent::Release(MouseButton::Left));
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+        assert_eq!(
+            panic_receiver.try_iter().collect::<Vec<_>>(),
+            vec!["the first callback failed"; 2]
+        );
+    }
+
+    #[test]
+    fn panicking_display_callback_does_not_stop_others() {
+        let (sender, receiver) = mpsc::channel();
+        let callbacks: Mutex<Vec<Box<dyn Fn() + Send>>> = Mutex::new(vec![
+            Box::new(|| panic!("the first callback failed")),
+            Box::new(move || sender.send(()).unwrap()),
+        ]);
+        hooks::notify_display_change(&callbacks);
+        hooks::notify_display_change(&callbacks);
+        assert_eq!(receiver.try_iter().count(), 2);
+        assert!(!callbacks.is_poisoned());
+    }
+
+    #[test]
+    fn event_history_evicts_oldest() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(manager.event_history(), vec![]);
+        manager.enable_event_history(2).unwrap();
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        manager.inject(MouseEvent::Release(MouseButton::Left));
+        manager.inject(MouseEvent::Scroll(ScrollDirection::Up));
+        assert_eq!(
+            manager.event_history(),
+            vec![
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Scroll(ScrollDirection::Up),
+            ]
+        );
+
+        manager.enable_event_history(1).unwrap();
+        assert_eq!(
+            manager.event_history(),
+            vec![MouseEvent::Scroll(ScrollDirection::Up)]
+        );
+    }
+
+    #[test]
+    fn click_with_movement() {
+        let manager = MockMouseManager::new();
+        let path = [(1, 0), (0, -2)];
+        assert_eq!(
+            manager.click_with_movement(&MouseButton::Left, &path),
+            Ok(())
+        );
+        assert_eq!(
+            manager.click_with_movement(&MouseButton::Right, &[]),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::RelativeMove(1, 0),
+                MouseEvent::RelativeMove(0, -2),
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn move_to_on_primary() {
+        let mut manager = MockMouseManager::new();
+        manager.primary_monitor = Monitor {
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 1024,
+        };
+        assert_eq!(manager.move_to_on_primary(10, 20), Ok(()));
+        assert_eq!(manager.events(), vec![MouseEvent::AbsoluteMove(1930, 20)]);
+    }
+
+    #[test]
+    fn swipe() {
+        let manager = MockMouseManager::new();
+        assert_eq!(
+            manager.swipe(ScrollDirection::Down, 0, Duration::from_millis(400)),
+            Ok(())
+        );
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(
+            manager.swipe(ScrollDirection::Down, 4, Duration::from_millis(400)),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![MouseEvent::Scroll(ScrollDirection::Down); 4]
+        );
+        assert_eq!(
+            manager.state().sleeps(),
+            vec![Duration::from_millis(100); 3]
+        );
+    }
+
+    #[test]
+    fn accel_curve_intervals() {
+        let ms = Duration::from_millis;
+        assert_eq!(AccelCurve::STEADY.intervals(0), vec![]);
+        assert_eq!(AccelCurve::STEADY.intervals(1), vec![]);
+        assert_eq!(AccelCurve::STEADY.intervals(3), vec![ms(150); 2]);
+        assert_eq!(AccelCurve::SPIN_UP.intervals(2), vec![ms(80)]);
+        assert_eq!(AccelCurve::SPIN_UP.intervals(3), vec![ms(80), ms(10)]);
+        assert_eq!(
+            AccelCurve::Linear {
+                first: ms(10),
+                last: ms(50)
             }
-            Err(error) => assert_eq!(error, Error::NotImplemented),
+            .intervals(6),
+            vec![ms(10), ms(20), ms(30), ms(40), ms(50)]
+        );
+
+        // A flick is fastest in the middle and symmetric
+        let flick = AccelCurve::FLICK.intervals(8);
+        assert_eq!(flick.len(), 7);
+        assert_eq!((flick[0], flick[3], flick[6]), (ms(60), ms(8), ms(60)));
+        for (a, b) in flick.iter().zip(flick.iter().rev()) {
+            assert_eq!(a, b);
         }
+        assert!(flick[..4].windows(2).all(|pair| pair[0] > pair[1]));
     }
 
     #[test]
-    #[ignore]
-    fn left_click() {
-        let manager = Mouse::new();
-        assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    fn scroll_with_acceleration() {
+        let ms = Duration::from_millis;
+        let mut manager = MockMouseManager::new();
+        let curve = AccelCurve::Linear {
+            first: ms(40),
+            last: ms(10),
+        };
+        assert_eq!(
+            manager.scroll_with_acceleration(ScrollDirection::Up, 0, curve),
+            Ok(())
+        );
+        assert_eq!(
+            manager.scroll_with_acceleration(ScrollDirection::Up, 4, curve),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![MouseEvent::Scroll(ScrollDirection::Up); 4]
+        );
+        assert_eq!(manager.state().sleeps(), vec![ms(40), ms(25), ms(10)]);
+
+        // The fast clicks are slowed down to the max scroll rate
+        manager.set_max_scroll_rate(Some(50));
+        assert_eq!(
+            manager.scroll_with_acceleration(ScrollDirection::Up, 4, curve),
+            Ok(())
+        );
+        assert_eq!(manager.state().sleeps()[3..], [ms(40), ms(25), ms(20)]);
     }
 
     #[test]
-    #[ignore]
-    fn scroll_down() {
-        let manager = Mouse::new();
-        for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
-            let sleep_duration = Duration::from_millis(250);
-            thread::sleep(sleep_duration);
-        }
+    fn scroll_vector_decomposes() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.scroll_vector(0, 0), Ok(()));
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(manager.scroll_vector(-1, 2), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Scroll(ScrollDirection::Up),
+                MouseEvent::Scroll(ScrollDirection::Up),
+                MouseEvent::Scroll(ScrollDirection::Left),
+            ]
+        );
     }
 
     #[test]
-    #[ignore]
-    fn scroll_up() {
-        let manager = Mouse::new();
+    fn scroll_detents() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.scroll_detents(&ScrollDirection::Up, 0), Ok(()));
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(manager.scroll_detents(&ScrollDirection::Left, 2), Ok(()));
+        assert_eq!(manager.scroll_detents(&ScrollDirection::Down, -1), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Scroll(ScrollDirection::Left),
+                MouseEvent::Scroll(ScrollDirection::Left),
+                MouseEvent::Scroll(ScrollDirection::Up),
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_event() {
+        assert_eq!(common::scroll_event(0, 0), None);
+        assert_eq!(
+            common::scroll_event(0, 1),
+            Some(MouseEvent::Scroll(ScrollDirection::Up))
+        );
+        assert_eq!(
+            common::scroll_event(-1, 0),
+            Some(MouseEvent::Scroll(ScrollDirection::Left))
+        );
+        assert_eq!(
+            common::scroll_event(0, -3),
+            Some(MouseEvent::ScrollDelta(0, -3))
+        );
+        assert_eq!(
+            common::scroll_event(1, 1),
+            Some(MouseEvent::ScrollDelta(1, 1))
+        );
+    }
+
+    #[test]
+    fn wheel_clicks() {
+        assert_eq!(common::wheel_clicks(0), 0);
+        assert_eq!(common::wheel_clicks(120), 1);
+        assert_eq!(common::wheel_clicks(-120), -1);
+        assert_eq!(common::wheel_clicks(360), 3);
+        assert_eq!(common::wheel_clicks(-480), -4);
+        // The partial notches of a high resolution wheel
+        assert_eq!(common::wheel_clicks(30), 1);
+        assert_eq!(common::wheel_clicks(-150), -2);
+    }
+
+    #[test]
+    fn scroll_event_from_lines() {
+        // `scroll_vector` on MacOS sends 5 lines per click, with the
+        // horizontal axis reversed
+        let from_vector =
+            |dx: i32, dy: i32| common::scroll_event_from_lines(dy as i64 * 5, -dx as i64 * 5, 5);
+        assert_eq!(from_vector(0, 0), None);
+        assert_eq!(from_vector(3, -2), Some(MouseEvent::ScrollDelta(3, -2)));
+        assert_eq!(from_vector(-1, 1), Some(MouseEvent::ScrollDelta(-1, 1)));
+        assert_eq!(
+            from_vector(0, -1),
+            Some(MouseEvent::Scroll(ScrollDirection::Down))
+        );
+        assert_eq!(
+            from_vector(1, 0),
+            Some(MouseEvent::Scroll(ScrollDirection::Right))
+        );
+        // The magnitude of a scroll on a single axis is kept
+        assert_eq!(from_vector(0, -4), Some(MouseEvent::ScrollDelta(0, -4)));
+        assert_eq!(from_vector(2, 0), Some(MouseEvent::ScrollDelta(2, 0)));
+        // A fast spin of a real wheel sends more lines at once
+        assert_eq!(
+            common::scroll_event_from_lines(12, 0, 5),
+            Some(MouseEvent::ScrollDelta(0, 3))
+        );
+        // A single line of a real wheel is still a whole click
+        assert_eq!(
+            common::scroll_event_from_lines(1, -1, 5),
+            Some(MouseEvent::ScrollDelta(1, 1))
+        );
+    }
+
+    #[test]
+    fn max_scroll_rate_paces_bulk_scrolls() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(manager.max_scroll_rate(), None);
+        assert_eq!(manager.scroll_vector(0, -3), Ok(()));
+        assert_eq!(manager.state().sleeps(), vec![]);
+
+        manager.set_max_scroll_rate(Some(50));
+        assert_eq!(manager.scroll_vector(2, -3), Ok(()));
+        assert_eq!(manager.events().len(), 8);
+        assert_eq!(manager.state().sleeps(), vec![Duration::from_millis(20); 4]);
+
+        // A faster swipe is slowed down to the limit, a slower one is kept
+        assert_eq!(
+            manager.swipe(ScrollDirection::Up, 3, Duration::from_millis(30)),
+            Ok(())
+        );
+        assert_eq!(
+            manager.swipe(ScrollDirection::Up, 3, Duration::from_millis(90)),
+            Ok(())
+        );
+        assert_eq!(
+            manager.state().sleeps()[4..],
+            [
+                Duration::from_millis(20),
+                Duration::from_millis(20),
+                Duration::from_millis(30),
+                Duration::from_millis(30),
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_fractional_accumulates() {
+        let manager = MockMouseManager::new();
         for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
-            let sleep_duration = Duration::from_millis(250);
-            thread::sleep(sleep_duration);
+            assert_eq!(manager.scroll_fractional(0.0, 0.1), Ok(()));
         }
+        assert_eq!(
+            manager.events(),
+            vec![MouseEvent::Scroll(ScrollDirection::Up)]
+        );
+
+        assert_eq!(manager.scroll_fractional(-2.5, 0.0), Ok(()));
+        assert_eq!(manager.scroll_fractional(-0.5, 0.0), Ok(()));
+        assert_eq!(
+            manager.events()[1..],
+            [MouseEvent::Scroll(ScrollDirection::Left); 3]
+        );
     }
 
     #[test]
-    #[ignore]
-    fn scroll_right() {
-        let manager = Mouse::new();
-        for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Right), Ok(()));
-            let sleep_duration = Duration::from_millis(250);
-            thread::sleep(sleep_duration);
+    fn click_sequence() {
+        let manager = MockMouseManager::new();
+        let inter_click = Duration::from_millis(35);
+        assert_eq!(
+            manager.click_sequence(&MouseButton::Right, 0, inter_click),
+            Ok(())
+        );
+        assert_eq!(manager.events(), vec![]);
+
+        assert_eq!(
+            manager.click_sequence(&MouseButton::Right, 3, inter_click),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            [
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+            .repeat(3)
+        );
+        assert_eq!(manager.state().sleeps(), vec![inter_click; 2]);
+    }
+
+    #[test]
+    fn buttons_as_map_keys() {
+        let buttons = [MouseButton::Right, MouseButton::Left, MouseButton::Middle];
+        let hash_map: HashMap<_, _> = buttons.iter().map(|b| (*b, format!("{:?}", b))).collect();
+        let btree_map: BTreeMap<_, _> = buttons.iter().map(|b| (*b, format!("{:?}", b))).collect();
+        for button in buttons {
+            assert_eq!(hash_map[&button], format!("{:?}", button));
+            assert_eq!(btree_map[&button], format!("{:?}", button));
         }
+        assert_eq!(
+            btree_map.keys().copied().collect::<Vec<_>>(),
+            vec![MouseButton::Left, MouseButton::Middle, MouseButton::Right]
+        );
+
+        let directions = [
+            ScrollDirection::Up,
+            ScrollDirection::Down,
+            ScrollDirection::Right,
+            ScrollDirection::Left,
+        ];
+        let direction_set: HashSet<_> = directions.iter().copied().collect();
+        assert_eq!(direction_set.len(), 4);
     }
 
     #[test]
-    #[ignore]
-    fn scroll_left() {
-        let manager = Mouse::new();
-        for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Left), Ok(()));
-            let sleep_duration = Duration::from_millis(250);
-            thread::sleep(sleep_duration);
+    fn click_active_window() {
+        let mut manager = MockMouseManager::new();
+        assert_eq!(
+            manager.click_active_window(20, 40, &MouseButton::Left),
+            Err(Error::NotImplemented)
+        );
+        assert_eq!(manager.events(), vec![]);
+
+        manager.active_window_origin = Some((300, 200));
+        assert_eq!(
+            manager.click_active_window(20, 40, &MouseButton::Left),
+            Ok(())
+        );
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(320, 240),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn move_relative_to_last_click() {
+        let manager = MockMouseManager::new();
+        assert!(manager.move_relative_to_last_click(50, 50).is_err());
+
+        assert_eq!(manager.move_to(100, 100), Ok(()));
+        assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+        assert_eq!(manager.move_to(500, 20), Ok(()));
+        assert_eq!(manager.move_relative_to_last_click(50, 50), Ok(()));
+        assert_eq!(manager.get_position(), Ok((150, 150)));
+    }
+
+    #[test]
+    fn hook_scoped() {
+        let mut mock = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        let id = {
+            let manager: &mut dyn MouseActions = &mut mock;
+            let guard = manager
+                .hook_scoped(Box::new(move |event| sender.send(*event).unwrap()))
+                .unwrap();
+            // The manager can still be used through the guard
+            assert_eq!(guard.move_to(10, 10), Ok(()));
+            guard.id()
+        };
+        assert_eq!(mock.hooked_count(), 0);
+        assert_eq!(mock.unhook(id), Err(Error::UnhookFailed));
+
+        // The sender was dropped with the callback
+        mock.inject(MouseEvent::Press(MouseButton::Left));
+        assert_eq!(receiver.iter().count(), 0);
+    }
+
+    #[test]
+    fn hook_labeled() {
+        let mut mock = MockMouseManager::new();
+        let manager: &mut dyn MouseActions = &mut mock;
+        let clicks = manager.hook_labeled("clicks", Box::new(|_| {})).unwrap();
+        let scrolls = manager
+            .hook_labeled(String::from("scrolls"), Box::new(|_| {}))
+            .unwrap();
+        assert_eq!(
+            manager.hooks_by_label(),
+            vec![
+                ("clicks".to_string(), clicks),
+                ("scrolls".to_string(), scrolls)
+            ]
+        );
+
+        assert_eq!(manager.unhook_label("clicks"), Ok(()));
+        assert_eq!(manager.unhook_label("clicks"), Err(Error::UnhookFailed));
+        assert_eq!(
+            manager.hooks_by_label(),
+            vec![("scrolls".to_string(), scrolls)]
+        );
+        // Unhooking by the id forgets the label as well
+        assert_eq!(manager.unhook(scrolls), Ok(()));
+        assert_eq!(manager.hooks_by_label(), vec![]);
+        assert_eq!(mock.hooked_count(), 0);
+    }
+
+    #[test]
+    fn hook_channel_bounded() {
+        let scrolls = |count: usize| {
+            [
+                ScrollDirection::Up,
+                ScrollDirection::Down,
+                ScrollDirection::Left,
+            ]
+            .into_iter()
+            .cycle()
+            .take(count)
+            .map(MouseEvent::Scroll)
+            .collect::<Vec<_>>()
+        };
+        let received = |receiver: &EventReceiver| {
+            std::iter::from_fn(|| receiver.try_recv()).collect::<Vec<_>>()
+        };
+
+        let mut manager = MockMouseManager::new();
+        assert!(manager
+            .hook_channel_bounded(0, OverflowPolicy::Block)
+            .is_err());
+
+        let (_, receiver) = manager
+            .hook_channel_bounded(2, OverflowPolicy::DropOldest)
+            .unwrap();
+        scrolls(3)
+            .into_iter()
+            .for_each(|event| manager.inject(event));
+        assert_eq!(received(&receiver), scrolls(3)[1..]);
+
+        let mut manager = MockMouseManager::new();
+        let (id, receiver) = manager
+            .hook_channel_bounded(2, OverflowPolicy::DropNewest)
+            .unwrap();
+        scrolls(3)
+            .into_iter()
+            .for_each(|event| manager.inject(event));
+        assert_eq!(received(&receiver), scrolls(2));
+        // The queued events can still be received after unhooking
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        assert_eq!(manager.unhook(id), Ok(()));
+        assert_eq!(receiver.recv(), Some(MouseEvent::Press(MouseButton::Left)));
+        assert_eq!(receiver.recv(), None);
+
+        let mut manager = MockMouseManager::new();
+        let (_, receiver) = manager
+            .hook_channel_bounded(2, OverflowPolicy::Block)
+            .unwrap();
+        let (sent, sends) = mpsc::channel();
+        let producer = thread::spawn(move || {
+            for event in scrolls(3) {
+                manager.inject(event);
+                let _ = sent.send(event);
+            }
+        });
+        // The producer waits for the receiver to make room for the third event
+        assert_eq!(sends.iter().take(2).collect::<Vec<_>>(), scrolls(2));
+        assert_eq!(
+            sends.recv_timeout(Duration::from_millis(100)),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        );
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(
+            receiver.recv(),
+            Some(MouseEvent::Scroll(ScrollDirection::Up))
+        );
+        assert_eq!(sends.recv(), Ok(scrolls(3)[2]));
+        producer.join().unwrap();
+        assert_eq!(received(&receiver), scrolls(3)[1..]);
+    }
+
+    #[test]
+    fn hook_scroll_coalesced() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        let id = manager
+            .hook_scroll_coalesced(
+                Duration::from_millis(200),
+                Box::new(move |direction, count| sender.send((direction, count)).unwrap()),
+            )
+            .unwrap();
+
+        for _ in 0..5 {
+            manager.inject(MouseEvent::Scroll(ScrollDirection::Down));
         }
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        let timeout = Duration::from_secs(2);
+        assert_eq!(
+            receiver.recv_timeout(timeout),
+            Ok((ScrollDirection::Down, 5))
+        );
+
+        // The change of direction reports the first group right away
+        manager.inject(MouseEvent::Scroll(ScrollDirection::Up));
+        manager.inject(MouseEvent::Scroll(ScrollDirection::Up));
+        manager.inject(MouseEvent::Scroll(ScrollDirection::Right));
+        assert_eq!(receiver.recv_timeout(timeout), Ok((ScrollDirection::Up, 2)));
+        assert_eq!(
+            receiver.recv_timeout(timeout),
+            Ok((ScrollDirection::Right, 1))
+        );
+
+        // Unhooking stops the thread, which drops the callback and its sender
+        assert_eq!(manager.unhook(id), Ok(()));
+        assert!(receiver.recv_timeout(timeout).is_err());
     }
 
     #[test]
-    #[ignore]
-    fn hook_and_unhook() {
-        let mut manager = Mouse::new();
-        assert_eq!(manager.unhook(5), Err(Error::UnhookFailed));
-        let hook_result = manager.hook(Box::new(|e| println!("{:?}", e)));
-        match hook_result {
-            Ok(id) => {
-                assert_eq!(manager.unhook(id), Ok(()));
+    fn hook_reactive_remaps_buttons() {
+        let mut manager = MockMouseManager::new();
+        manager
+            .hook_reactive(Box::new(|event, output| {
+                if *event == MouseEvent::Press(MouseButton::Middle) {
+                    output.move_to(10, 20).unwrap();
+                    output.click_button(&MouseButton::Left).unwrap();
+                }
+            }))
+            .unwrap();
 
-                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
-                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
-                manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
-                let id = manager.hook(Box::new(|e| println!("{:?}", e))).unwrap();
+        manager.inject(MouseEvent::Press(MouseButton::Right));
+        assert_eq!(manager.events(), vec![]);
+        manager.inject(MouseEvent::Press(MouseButton::Middle));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(10, 20),
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+    }
 
-                manager.unhook_all().unwrap();
-                assert_eq!(manager.unhook(id), Err(Error::UnhookFailed));
+    #[test]
+    fn hook_reactive_uses_the_settings() {
+        let mut manager = MockMouseManager::new();
+        manager
+            .hook_reactive(Box::new(|_, output| {
+                output.move_to(10, 20).unwrap();
+                output.click_button(&MouseButton::Left).unwrap();
+            }))
+            .unwrap();
+        // The settings that are changed after hooking apply as well
+        manager.set_coordinate_origin(CoordinateOrigin::BottomLeft);
+        manager.set_button_map(HashMap::from([(MouseButton::Left, MouseButton::Right)]));
+
+        manager.inject(MouseEvent::Press(MouseButton::Middle));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::AbsoluteMove(10, 1059),
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+            ]
+        );
+
+        let mut headless = HeadlessMouse::new(100, 100);
+        headless
+            .hook_reactive(Box::new(|event, output| {
+                if let MouseEvent::Press(MouseButton::Left) = event {
+                    output.move_to(50, 60).unwrap();
+                }
+            }))
+            .unwrap();
+        headless.press_button(&MouseButton::Left).unwrap();
+        assert_eq!(headless.get_position(), Ok((50, 60)));
+    }
+
+    #[test]
+    fn hook_exclusive() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        manager
+            .hook(Box::new(move |event| sender.send(*event).unwrap()))
+            .unwrap();
+        assert!(!manager.grabbed());
+
+        let first = manager.hook_exclusive(Box::new(|_| {})).unwrap();
+        let second = manager.hook_exclusive(Box::new(|_| {})).unwrap();
+        assert!(manager.grabbed());
+        // The passive hooks still receive the grabbed events
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(MouseEvent::Press(MouseButton::Left))
+        );
+
+        // The devices stay grabbed until the last exclusive hook is gone
+        manager.unhook(first).unwrap();
+        assert!(manager.grabbed());
+        manager.unhook(second).unwrap();
+        assert!(!manager.grabbed());
+
+        manager.hook_exclusive(Box::new(|_| {})).unwrap();
+        manager.unhook_all().unwrap();
+        assert!(!manager.grabbed());
+    }
+
+    #[test]
+    fn collect_events() {
+        let mut manager = MockMouseManager::new();
+        let clicks = [
+            MouseEvent::Press(MouseButton::Left),
+            MouseEvent::Release(MouseButton::Left),
+        ];
+        manager.inject_on_hook(&[clicks[0], clicks[1], clicks[0]]);
+        assert_eq!(manager.collect_events(2, None), Ok(clicks.to_vec()));
+        assert_eq!(manager.hooked_count(), 0);
+        assert_eq!(manager.collect_events(0, None), Ok(vec![]));
+
+        manager.inject_on_hook(&[
+            MouseEvent::RelativeMove(1, 1),
+            clicks[0],
+            MouseEvent::Scroll(ScrollDirection::Up),
+            clicks[0],
+        ]);
+        let presses = manager.collect_events_matching(
+            2,
+            None,
+            Box::new(|event| matches!(event, MouseEvent::Press(_))),
+        );
+        assert_eq!(presses, Ok(vec![clicks[0], clicks[0]]));
+
+        // The events that arrived in time are returned with the error
+        manager.inject_on_hook(&clicks);
+        assert_eq!(
+            manager.collect_events(3, Some(Duration::from_millis(10))),
+            Err(Error::EventsTimedOut(clicks.to_vec()))
+        );
+        assert_eq!(manager.hooked_count(), 0);
+    }
+
+    #[test]
+    fn hook_once() {
+        let mut manager = MockMouseManager::new();
+        let (sender, receiver) = mpsc::channel();
+        manager
+            .hook_once(Box::new(move |event| sender.send(*event).unwrap()))
+            .unwrap();
+        assert_eq!(manager.hooked_count(), 1);
+
+        manager.inject(MouseEvent::Press(MouseButton::Left));
+        manager.inject(MouseEvent::Release(MouseButton::Left));
+        assert_eq!(manager.hooked_count(), 0);
+        // The sender was dropped with the callback, so the channel is closed
+        assert_eq!(
+            receiver.iter().collect::<Vec<_>>(),
+            vec![MouseEvent::Press(MouseButton::Left)]
+        );
+
+        let id = manager.hook_once(Box::new(|_| {})).unwrap();
+        assert_eq!(manager.unhook(id), Ok(()));
+        assert_eq!(manager.unhook(id), Err(Error::UnhookFailed));
+    }
+
+    #[test]
+    fn move_analog() {
+        let mut manager = MockMouseManager::new();
+        let frame = Duration::from_millis(16);
+        assert!(manager.move_analog(1.0, 0.0, -1.0, frame).is_err());
+
+        // Inside of the deadzone
+        for _ in 0..100 {
+            assert_eq!(manager.move_analog(0.05, -0.05, 100.0, frame), Ok(()));
+        }
+        assert_eq!(manager.events(), vec![]);
+
+        // 0.32 pixels per frame, the fourth frame moves a pixel
+        for _ in 0..3 {
+            assert_eq!(manager.move_analog(0.2, 0.0, 100.0, frame), Ok(()));
+        }
+        assert_eq!(manager.events(), vec![]);
+        assert_eq!(manager.move_analog(0.2, 0.0, 100.0, frame), Ok(()));
+        assert_eq!(manager.events(), vec![MouseEvent::RelativeMove(1, 0)]);
+
+        // The 0.28 pixels that are left over to the right are kept
+        assert_eq!(manager.move_analog(-2.0, 1.0, 1000.0, frame), Ok(()));
+        assert_eq!(manager.events()[1], MouseEvent::RelativeMove(-15, 16));
+    }
+
+    #[test]
+    fn click_realistic_holds_within_range() {
+        let min = Duration::from_millis(70);
+        let max = Duration::from_millis(90);
+        let mut holds = vec![];
+        for _ in 0..2 {
+            let mut manager = MockMouseManager::new();
+            manager.seed_rng(7);
+            manager.set_click_hold_range(max, min);
+            for _ in 0..20 {
+                assert_eq!(manager.click_realistic(&MouseButton::Left), Ok(()));
             }
-            Err(err) => assert_eq!(Error::PermissionDenied, err),
+            let sleeps = manager.state().sleeps();
+            assert_eq!(sleeps.len(), 20);
+            assert!(sleeps.iter().all(|hold| (min..=max).contains(hold)));
+            holds.push(sleeps);
         }
+        // The same seed produces the same hold durations
+        assert_eq!(holds[0], holds[1]);
+    }
+
+    #[test]
+    fn button_map() {
+        let mut manager = MockMouseManager::new();
+        manager.set_button_map(HashMap::from([(MouseButton::Left, MouseButton::Right)]));
+        assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+        assert_eq!(manager.click_button(&MouseButton::Middle), Ok(()));
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Right),
+                MouseEvent::Release(MouseButton::Right),
+                MouseEvent::Press(MouseButton::Middle),
+                MouseEvent::Release(MouseButton::Middle),
+            ]
+        );
+
+        manager.reset();
+        assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
+        assert_eq!(
+            manager.events().last(),
+            Some(&MouseEvent::Press(MouseButton::Left))
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let manager = MockMouseManager::new();
+        manager.set_position(300, 200);
+        assert_eq!(
+            manager.long_press(&MouseButton::Left, Duration::from_millis(800), true),
+            Ok(())
+        );
+        // There are no moves during the hold
+        assert_eq!(
+            manager.events(),
+            vec![
+                MouseEvent::Press(MouseButton::Left),
+                MouseEvent::Release(MouseButton::Left),
+            ]
+        );
+        assert_eq!(manager.state().sleeps(), [Duration::from_millis(800)]);
+        assert_eq!(manager.get_position(), Ok((300, 200)));
+    }
+
+    #[test]
+    fn precise_sleep_spins_for_the_rest() {
+        let clock = MockClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+            sleeps: Mutex::new(Vec::new()),
+        };
+        common::precise_sleep(&clock, Duration::from_millis(10));
+        assert_eq!(
+            clock.sleeps(),
+            [Duration::from_millis(10) - common::SPIN_THRESHOLD]
+        );
+        assert_eq!(clock.now() - clock.start, Duration::from_millis(10));
+
+        // A duration within the threshold is spun entirely
+        common::precise_sleep(&clock, Duration::from_millis(1));
+        assert_eq!(clock.sleeps().len(), 1);
+        assert_eq!(clock.now() - clock.start, Duration::from_millis(11));
+    }
+
+    // It measures the real sleeps, which a loaded machine can not keep
+    #[test]
+    #[ignore]
+    fn precise_timing_is_closer_to_requested() {
+        let requested = Duration::from_micros(1500);
+        let median_error = |precise: bool| {
+            let state = MouseState::default();
+            *state.settings.precise_timing.lock().unwrap() = precise;
+            let mut errors: Vec<Duration> = (0..15)
+                .map(|_| {
+                    let start = Instant::now();
+                    state.sleep(requested);
+                    start.elapsed()
+                })
+                .inspect(|elapsed| assert!(*elapsed >= requested))
+                .map(|elapsed| elapsed - requested)
+                .collect();
+            errors.sort();
+            errors[errors.len() / 2]
+        };
+        // Compare the medians so a single preemption does not fail the test
+        assert!(median_error(true) <= median_error(false));
     }
 }
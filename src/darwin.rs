@@ -3,88 +3,177 @@
 /// for the darwin systems (MacOS)
 /// Uses the CoreGraphics (a.k.a Quartz) framework
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::common::{
+    convert_origin, modifier_flags, scroll_event, scroll_event_from_lines, CallbackId,
+    CallbackPanicFn, EventContext, EventKindSet, Modifier, Monitor, MouseActions, MouseButton,
+    MouseEvent, MouseEventContextFn, MouseEventFn, MouseEventOnceFn, MouseState, Priority,
+    ScrollDirection, ScrollGranularity,
+};
 use crate::error::Error;
-use std::collections::HashMap;
-use std::os::raw::{c_double, c_int, c_long, c_uint, c_ulong, c_void};
+use crate::hooks::{self, lock_ignore_poison, Hooks, Listener};
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::{c_char, c_double, c_int, c_long, c_uint, c_ulong, c_void};
 use std::ptr::null_mut;
-use std::sync::Mutex;
-use std::thread;
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
 
 static mut TAP_EVENT_REF: Option<CFTypeRef> = None;
-static mut CALLBACKS: Option<Mutex<HashMap<CallbackId, Box<dyn Fn(&MouseEvent) + Send>>>> = None;
+static HOOKS: Mutex<Hooks> = Mutex::new(Hooks::new());
+static DISPLAY_CALLBACKS: Mutex<Vec<Box<dyn Fn() + Send>>> = Mutex::new(Vec::new());
 
 pub struct DarwinMouseManager {
+    state: MouseState,
     callback_counter: CallbackId,
-    is_listening: bool,
+    listener: Option<Listener>,
+    is_watching_display: bool,
+    /// The source of the posted events, null unless `set_mark_synthetic(false)` is used
+    event_source: CGEventSourceRef,
+    /// Whether it was created by `output_handle`, the hooks are global so
+    /// they are left to the manager that created it
+    is_output: bool,
 }
 
 impl DarwinMouseManager {
     #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Box<dyn MouseActions> {
         Box::new(DarwinMouseManager {
+            state: MouseState::default(),
             callback_counter: 0,
-            is_listening: false,
+            listener: None,
+            is_watching_display: false,
+            event_source: null_mut(),
+            is_output: false,
         })
     }
 
+    /// Post the given event with the held modifiers of `hold_modifiers`
+    fn post_event(&self, event: CGEventRef) {
+        let modifiers = self.state.held_modifiers();
+        unsafe {
+            if !modifiers.is_empty() {
+                CGEventSetFlags(event, modifier_flags(&modifiers));
+            }
+            CGEventPost(CGEventTapLocation::CGHIDEventTap, event);
+        }
+    }
+
+    /// Warp the cursor to the given position, with the top-left origin
+    fn warp(&self, x: usize, y: usize) -> Result<(), Error> {
+        let cg_point = CGPoint {
+            x: x as f64,
+            y: y as f64,
+        };
+        unsafe {
+            let result = CGWarpMouseCursorPosition(cg_point);
+            if result != CGError::Success {
+                return Err(Error::CustomError(
+                    "Failed to move the mouse, CGError is not Success",
+                ));
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Get the position of the cursor, with the top-left origin
+    fn cursor_position(&self) -> Result<(i32, i32), Error> {
+        unsafe {
+            let event = CGEventCreate(null_mut());
+            if event == null_mut() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            let cursor = CGEventGetLocation(event);
+            CFRelease(event as CFTypeRef);
+            return Ok((cursor.x as i32, cursor.y as i32));
+        }
+    }
+
+    /// Move the cursor by the given offset with a mouse moved event that carries
+    /// the offset as its delta, like the events of a real mouse
+    fn move_by(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let (x, y) = self.cursor_position()?;
+        let position = CGPoint {
+            x: (x + x_offset) as c_double,
+            y: (y + y_offset) as c_double,
+        };
+        unsafe {
+            let event = CGEventCreateMouseEvent(
+                self.event_source,
+                CGEventType::MouseMoved,
+                position,
+                CGMouseButton::Left,
+            );
+            if event == null_mut() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            // CGEventField::mouseEventDeltaX = 4
+            // CGEventField::mouseEventDeltaY = 5
+            CGEventSetIntegerValueField(event, 4, x_offset as c_long);
+            CGEventSetIntegerValueField(event, 5, y_offset as c_long);
+            self.post_event(event);
+            CFRelease(event as CFTypeRef);
+        }
+        Ok(())
+    }
+
     fn create_mouse_event(
         &self,
         event_type: CGEventType,
         mouse_button: CGMouseButton,
     ) -> Result<(), Error> {
-        let (pos_x, pos_y) = self.get_position()?;
+        let (pos_x, pos_y) = self.cursor_position()?;
         let position = CGPoint {
             x: pos_x as c_double,
             y: pos_y as c_double,
         };
 
         unsafe {
-            let event = CGEventCreateMouseEvent(null_mut(), event_type, position, mouse_button);
+            let event =
+                CGEventCreateMouseEvent(self.event_source, event_type, position, mouse_button);
             if event == null_mut() {
                 return Err(Error::CGCouldNotCreateEvent);
             }
-            CGEventPost(CGEventTapLocation::CGHIDEventTap, event);
+            self.post_event(event);
             CFRelease(event as CFTypeRef);
         }
 
         Ok(())
     }
 
-    fn create_scroll_wheel_event(
-        &self,
-        distance: c_int,
-        direction: &ScrollDirection,
-    ) -> Result<(), Error> {
+    /// Post a scroll of the given lines, `vertical` is positive up and
+    /// `horizontal` is positive left
+    fn create_scroll_wheel_event(&self, vertical: c_int, horizontal: c_int) -> Result<(), Error> {
         unsafe {
-            let event = match direction {
-                ScrollDirection::Up | ScrollDirection::Down => CGEventCreateScrollWheelEvent(
-                    null_mut(),
-                    CGScrollEventUnit::Line,
-                    2,
-                    distance,
-                    0,
-                ),
-                ScrollDirection::Right | ScrollDirection::Left => CGEventCreateScrollWheelEvent(
-                    null_mut(),
-                    CGScrollEventUnit::Line,
-                    2,
-                    0,
-                    distance,
-                ),
-            };
+            let event = CGEventCreateScrollWheelEvent(
+                self.event_source,
+                CGScrollEventUnit::Line,
+                2,
+                vertical,
+                horizontal,
+            );
 
             if event == null_mut() {
                 return Err(Error::CGCouldNotCreateEvent);
             }
-            CGEventPost(CGEventTapLocation::CGHIDEventTap, event);
+            self.post_event(event);
             CFRelease(event as CFTypeRef);
         }
         Ok(())
     }
 
     fn start_listener(&mut self) -> Result<(), Error> {
-        thread::spawn(move || {
+        let (tx, rx) = mpsc::channel();
+        let mut listener = Listener::new();
+        listener.add_source("kCGHIDEventTap");
+        let schedule = match self.state.listener_priority() {
+            Priority::Normal => None,
+            Priority::High => Some((SCHED_OTHER, HIGH_PRIORITY)),
+            Priority::RealTime => Some((SCHED_RR, REALTIME_PRIORITY)),
+        };
+
+        listener.spawn(move |stop| {
             unsafe extern "C" fn mouse_on_event_callback(
                 _proxy: *const c_void,
                 event_type: CGEventType,
@@ -104,39 +193,47 @@ impl DarwinMouseManager {
                         Some(MouseEvent::AbsoluteMove(point.x as i32, point.y as i32))
                     }
                     CGEventType::ScrollWheel => {
-                        // CGEventField::scrollWheelEventPointDeltaAxis1 = 96
-                        // CGEventField::scrollWheelEventPointDeltaAxis2 = 97
-                        let delta_y = CGEventGetIntegerValueField(cg_event, 96);
-                        let delta_x = CGEventGetIntegerValueField(cg_event, 97);
-                        if delta_y > 0 {
-                            Some(MouseEvent::Scroll(ScrollDirection::Up))
-                        } else if delta_y < 0 {
-                            Some(MouseEvent::Scroll(ScrollDirection::Down))
-                        } else if delta_x < 0 {
-                            Some(MouseEvent::Scroll(ScrollDirection::Right))
-                        } else if delta_x > 0 {
-                            Some(MouseEvent::Scroll(ScrollDirection::Left))
-                        } else {
-                            // Probably axis3 wheel scrolled
-                            None
-                        }
+                        // The line deltas are converted to clicks, so a fast scroll
+                        // keeps its magnitude
+                        // CGEventField::scrollWheelEventDeltaAxis1 = 11
+                        // CGEventField::scrollWheelEventDeltaAxis2 = 12
+                        scroll_event_from_lines(
+                            CGEventGetIntegerValueField(cg_event, 11) as i64,
+                            CGEventGetIntegerValueField(cg_event, 12) as i64,
+                            LINES_PER_CLICK as i64,
+                        )
+                        .or_else(|| {
+                            // A small continuous scroll, e.g. of a trackpad, may not
+                            // have a whole line, only its direction is reported
+                            // CGEventField::scrollWheelEventPointDeltaAxis1 = 96
+                            // CGEventField::scrollWheelEventPointDeltaAxis2 = 97
+                            let delta_y = CGEventGetIntegerValueField(cg_event, 96);
+                            let delta_x = CGEventGetIntegerValueField(cg_event, 97);
+                            // Both of them are 0 if the axis3 wheel scrolled
+                            scroll_event(-delta_x.signum() as i32, delta_y.signum() as i32)
+                        })
                     }
                     _ => None,
                 };
 
-                match (mouse_event, &mut CALLBACKS) {
-                    (Some(event), Some(callbacks)) => {
-                        for callback in callbacks.lock().unwrap().values() {
-                            callback(&event);
-                        }
-                    }
-                    _ => {}
+                if let Some(event) = mouse_event {
+                    HOOKS.lock().unwrap().dispatch(&event);
                 }
 
                 cg_event
             }
 
             unsafe {
+                // The tap callback runs on the run loop of this thread, this is
+                // best effort since the higher priorities may need privileges
+                if let Some((policy, priority)) = schedule {
+                    let param = SchedParam {
+                        sched_priority: priority,
+                        opaque: [0; 4],
+                    };
+                    pthread_setschedparam(pthread_self(), policy, &param);
+                }
+
                 // Create the mouse listener hook
                 TAP_EVENT_REF = Some(CGEventTapCreate(
                     CGEventTapLocation::CGHIDEventTap,
@@ -154,76 +251,289 @@ impl DarwinMouseManager {
                     null_mut(),
                 ));
 
-                let loop_source =
-                    CFMachPortCreateRunLoopSource(null_mut(), TAP_EVENT_REF.unwrap(), 0);
+                let tap = TAP_EVENT_REF.unwrap();
+                if tap.is_null() {
+                    // The process is not trusted for accessibility
+                    TAP_EVENT_REF = None;
+                    tx.send(Err(Error::PermissionDenied)).unwrap();
+                    return;
+                }
+
+                let loop_source = CFMachPortCreateRunLoopSource(null_mut(), tap, 0);
                 let current_loop = CFRunLoopGetCurrent();
                 CFRunLoopAddSource(current_loop, loop_source, kCFRunLoopDefaultMode);
-                CGEventTapEnable(TAP_EVENT_REF.unwrap(), true);
-                CFRunLoopRun();
+                CGEventTapEnable(tap, true);
+                tx.send(Ok(())).unwrap();
+
+                // Run the loop in short slices, so the stop flag is checked regularly
+                while !stop.load(Ordering::Relaxed) {
+                    CFRunLoopRunInMode(kCFRunLoopDefaultMode, RUN_LOOP_SLICE_SECONDS, false);
+                }
+
+                // Release the tap event
+                CGEventTapEnable(tap, false);
+                CFRelease(loop_source as CFTypeRef);
+                CFRelease(tap);
+                TAP_EVENT_REF = None;
             }
         });
 
+        rx.recv().unwrap()?;
+        self.listener = Some(listener);
         Ok(())
     }
+
+    /// Stop the run loop of the listener thread, which releases the event tap
+    fn stop_listener(&mut self) -> Result<(), Error> {
+        self.listener.take().map_or(Ok(()), Listener::stop)
+    }
+
+    /// Get the bundle identifier of the frontmost application through
+    /// `NSWorkspace.sharedWorkspace.frontmostApplication.bundleIdentifier`
+    fn frontmost_app_id() -> Option<String> {
+        unsafe {
+            // The returned objects are autoreleased, they are only valid until
+            // the pool is popped
+            let pool = objc_autoreleasePoolPush();
+            let send = |receiver: ObjcId, selector: &[u8]| {
+                if receiver.is_null() {
+                    return null_mut();
+                }
+                objc_msgSend(
+                    receiver,
+                    sel_registerName(selector.as_ptr() as *const c_char),
+                )
+            };
+            let workspace = send(
+                objc_getClass(b"NSWorkspace\0".as_ptr() as *const c_char),
+                b"sharedWorkspace\0",
+            );
+            let application = send(workspace, b"frontmostApplication\0");
+            let bundle_id = send(application, b"bundleIdentifier\0");
+            let utf8 = send(bundle_id, b"UTF8String\0") as *const c_char;
+            let app_id =
+                (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned());
+            objc_autoreleasePoolPop(pool);
+            app_id
+        }
+    }
+
+    /// Get the visible frame of the primary screen through
+    /// `NSScreen.screens.firstObject.visibleFrame`, converted from the bottom-left
+    /// origin of AppKit to the top-left origin of CoreGraphics
+    fn visible_frame() -> Option<Monitor> {
+        unsafe {
+            let pool = objc_autoreleasePoolPush();
+            let send = |receiver: ObjcId, selector: &[u8]| {
+                if receiver.is_null() {
+                    return null_mut();
+                }
+                objc_msgSend(
+                    receiver,
+                    sel_registerName(selector.as_ptr() as *const c_char),
+                )
+            };
+            let screens = send(
+                objc_getClass(b"NSScreen\0".as_ptr() as *const c_char),
+                b"screens\0",
+            );
+            // The first screen is the primary one, which has the menu bar
+            let screen = send(screens, b"firstObject\0");
+            let area = (!screen.is_null()).then(|| {
+                let frame = send_rect(screen, b"frame\0");
+                let visible = send_rect(screen, b"visibleFrame\0");
+                let top = frame.origin.y + frame.size.height;
+                Monitor {
+                    x: visible.origin.x as i32,
+                    y: (top - visible.origin.y - visible.size.height) as i32,
+                    width: visible.size.width as usize,
+                    height: visible.size.height as usize,
+                }
+            });
+            objc_autoreleasePoolPop(pool);
+            area
+        }
+    }
 }
 
+unsafe extern "C" fn display_reconfiguration_callback(
+    _display: CGDirectDisplayID,
+    flags: CGDisplayChangeSummaryFlags,
+    _user_info: *mut c_void,
+) {
+    // The callback is invoked once before and once after the reconfiguration,
+    // only notify the callbacks when the new configuration is in place
+    if flags & K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG != 0 {
+        return;
+    }
+
+    hooks::notify_display_change(&DISPLAY_CALLBACKS);
+}
+
+// The event source is a thread-safe CoreFoundation object
+unsafe impl Send for DarwinMouseManager {}
+
 impl Drop for DarwinMouseManager {
     fn drop(&mut self) {
+        // There is no way to report the errors from drop
+        let _ = self.shutdown();
         unsafe {
-            match TAP_EVENT_REF {
-                Some(event_ref) => {
-                    // Release the tap event
-                    CFRelease(event_ref);
-                    TAP_EVENT_REF = None;
-                }
-                None => {}
+            if !self.event_source.is_null() {
+                CFRelease(self.event_source as CFTypeRef);
             }
         }
     }
 }
 
+impl fmt::Debug for DarwinMouseManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DarwinMouseManager")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
 impl MouseActions for DarwinMouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "macos-cgevent"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        unsafe {
+            if !self.event_source.is_null() {
+                // Each of the managers releases the source when it is dropped
+                CFRetain(self.event_source as CFTypeRef);
+            }
+        }
+        Ok(Box::new(DarwinMouseManager {
+            state: self.state.share(),
+            callback_counter: 0,
+            listener: None,
+            is_watching_display: false,
+            event_source: self.event_source,
+            is_output: true,
+        }))
+    }
+
     fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
-        let cg_point = CGPoint {
-            x: x as f64,
-            y: y as f64,
-        };
+        let y = convert_origin(self, y as i32)?.max(0) as usize;
+        if self.state.prefers_relative_moves() {
+            let (from_x, from_y) = self.cursor_position()?;
+            return self.move_by(x as i32 - from_x, y as i32 - from_y);
+        }
+        self.warp(x, y)
+    }
+
+    fn move_to_silent(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = convert_origin(self, y as i32)?.max(0) as usize;
+        self.warp(x, y)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y) = self.cursor_position()?;
+        Ok((x, convert_origin(self, y)?))
+    }
+
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
         unsafe {
-            let result = CGWarpMouseCursorPosition(cg_point);
-            if result != CGError::Success {
-                return Err(Error::CustomError(
-                    "Failed to move the mouse, CGError is not Success",
-                ));
+            let display = CGMainDisplayID();
+            Ok((CGDisplayPixelsWide(display), CGDisplayPixelsHigh(display)))
+        }
+    }
+
+    fn display_refresh_hz(&self) -> Result<f32, Error> {
+        unsafe {
+            let mode = CGDisplayCopyDisplayMode(CGMainDisplayID());
+            if mode.is_null() {
+                return Err(Error::CustomError("failed to get the display mode"));
+            }
+            let rate = CGDisplayModeGetRefreshRate(mode);
+            CGDisplayModeRelease(mode);
+            // The built-in displays report 0
+            if rate <= 0.0 {
+                return Err(Error::NotImplemented);
+            }
+            Ok(rate as f32)
+        }
+    }
+
+    fn double_click_time(&self) -> Result<Duration, Error> {
+        // `NSEvent.doubleClickInterval` follows the setting of the user, it is
+        // a number and not an object, so no autorelease pool is needed
+        let seconds = unsafe {
+            let class = objc_getClass(b"NSEvent\0".as_ptr() as *const c_char);
+            if class.is_null() {
+                return Err(Error::CustomError("failed to get the NSEvent class"));
             }
+            send_double(class, b"doubleClickInterval\0")
         };
+        Duration::try_from_secs_f64(seconds)
+            .map_err(|_| Error::CustomError("failed to get the double click interval"))
+    }
 
-        Ok(())
+    fn get_work_area(&self) -> Result<Monitor, Error> {
+        Self::visible_frame().ok_or(Error::CustomError(
+            "failed to get the visible frame of the screen",
+        ))
     }
 
-    fn get_position(&self) -> Result<(i32, i32), Error> {
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        // The main display is the one with the menu bar, the global
+        // coordinates are relative to its top-left corner
         unsafe {
-            let event = CGEventCreate(null_mut());
-            if event == null_mut() {
-                return Err(Error::CGCouldNotCreateEvent);
+            let bounds = CGDisplayBounds(CGMainDisplayID());
+            Ok(Monitor {
+                x: bounds.origin.x as i32,
+                y: bounds.origin.y as i32,
+                width: bounds.size.width as usize,
+                height: bounds.size.height as usize,
+            })
+        }
+    }
+
+    fn get_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        let mut displays = [0; MAX_DISPLAYS as usize];
+        let mut count = 0;
+        unsafe {
+            if CGGetActiveDisplayList(MAX_DISPLAYS, displays.as_mut_ptr(), &mut count)
+                != CGError::Success
+            {
+                return Err(Error::CustomError("failed to get the active displays"));
             }
-            let cursor = CGEventGetLocation(event);
-            CFRelease(event as CFTypeRef);
-            return Ok((cursor.x as i32, cursor.y as i32));
+            Ok(displays[..count as usize]
+                .iter()
+                .map(|display| {
+                    let bounds = CGDisplayBounds(*display);
+                    Monitor {
+                        x: bounds.origin.x as i32,
+                        y: bounds.origin.y as i32,
+                        width: bounds.size.width as usize,
+                        height: bounds.size.height as usize,
+                    }
+                })
+                .collect())
         }
     }
 
     fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let (event_type, mouse_button) = match button {
+        let (event_type, mouse_button) = match self.state.map_button(button) {
             MouseButton::Left => (CGEventType::LeftMouseDown, CGMouseButton::Left),
             MouseButton::Middle => (CGEventType::OtherMouseDown, CGMouseButton::Center),
             MouseButton::Right => (CGEventType::RightMouseDown, CGMouseButton::Right),
         };
         self.create_mouse_event(event_type, mouse_button)?;
+        if let Ok((x, y)) = self.get_position() {
+            self.state.remember_click(x, y);
+        }
         Ok(())
     }
 
     fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let (event_type, mouse_button) = match button {
+        let (event_type, mouse_button) = match self.state.map_button(button) {
             MouseButton::Left => (CGEventType::LeftMouseUp, CGMouseButton::Left),
             MouseButton::Middle => (CGEventType::OtherMouseUp, CGMouseButton::Center),
             MouseButton::Right => (CGEventType::RightMouseUp, CGMouseButton::Right),
@@ -232,79 +542,289 @@ impl MouseActions for DarwinMouseManager {
     }
 
     fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+        let (press_type, release_type, mouse_button) = match self.state.map_button(button) {
+            MouseButton::Left => (
+                CGEventType::LeftMouseDown,
+                CGEventType::LeftMouseUp,
+                CGMouseButton::Left,
+            ),
+            MouseButton::Middle => (
+                CGEventType::OtherMouseDown,
+                CGEventType::OtherMouseUp,
+                CGMouseButton::Center,
+            ),
+            MouseButton::Right => (
+                CGEventType::RightMouseDown,
+                CGEventType::RightMouseUp,
+                CGMouseButton::Right,
+            ),
+        };
+        let (pos_x, pos_y) = self.cursor_position()?;
+        let position = CGPoint {
+            x: pos_x as c_double,
+            y: pos_y as c_double,
+        };
+
+        // Create both of the events first, so they can be posted back to back
+        unsafe {
+            let press =
+                CGEventCreateMouseEvent(self.event_source, press_type, position, mouse_button);
+            if press.is_null() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            let release =
+                CGEventCreateMouseEvent(self.event_source, release_type, position, mouse_button);
+            if release.is_null() {
+                CFRelease(press as CFTypeRef);
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            self.post_event(press);
+            self.post_event(release);
+            CFRelease(press as CFTypeRef);
+            CFRelease(release as CFTypeRef);
+        }
+        self.state
+            .remember_click(pos_x, convert_origin(self, pos_y)?);
+
+        Ok(())
     }
 
     fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
-        let distance = match direction {
-            ScrollDirection::Up | ScrollDirection::Left => 5,
-            ScrollDirection::Down | ScrollDirection::Right => -5,
-        };
-        self.create_scroll_wheel_event(distance, direction)
+        match direction {
+            ScrollDirection::Up => self.create_scroll_wheel_event(LINES_PER_CLICK, 0),
+            ScrollDirection::Down => self.create_scroll_wheel_event(-LINES_PER_CLICK, 0),
+            ScrollDirection::Left => self.create_scroll_wheel_event(0, LINES_PER_CLICK),
+            ScrollDirection::Right => self.create_scroll_wheel_event(0, -LINES_PER_CLICK),
+        }
     }
 
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            self.start_listener()?;
-            self.is_listening = true;
+    fn hold_modifiers(&self, modifiers: &[Modifier]) -> Result<(), Error> {
+        // The flags are set on the events in `post_event`
+        self.state.set_held_modifiers(modifiers);
+        Ok(())
+    }
+
+    fn long_press(
+        &self,
+        button: &MouseButton,
+        hold: Duration,
+        keep_still: bool,
+    ) -> Result<(), Error> {
+        // The mouse keeps sending its events to the applications, only the
+        // cursor stops following it until they are associated again
+        if keep_still && unsafe { CGAssociateMouseAndMouseCursorPosition(0) } != CGError::Success {
+            return Err(Error::CustomError("failed to keep the cursor still"));
         }
+        let released = self.press_button(button).and_then(|_| {
+            self.state.sleep_precisely(hold);
+            self.release_button(button)
+        });
+        if keep_still {
+            unsafe {
+                CGAssociateMouseAndMouseCursorPosition(1);
+            }
+        }
+        released
+    }
 
-        let id = self.callback_counter;
+    fn scroll_vector(&self, dx: i32, dy: i32) -> Result<(), Error> {
+        if dx == 0 && dy == 0 {
+            return Ok(());
+        }
+        self.create_scroll_wheel_event(dy * LINES_PER_CLICK, -dx * LINES_PER_CLICK)
+    }
+
+    fn scroll_granularity(&self) -> Result<ScrollGranularity, Error> {
+        Ok(ScrollGranularity::Lines(LINES_PER_CLICK as f32))
+    }
+
+    fn high_res_scroll_available(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.state.reset();
+        // Dropping the event source does not fail
+        let _ = self.set_mark_synthetic(true);
+    }
+
+    fn set_mark_synthetic(&mut self, mark: bool) -> Result<(), Error> {
         unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => {
-                    callbacks.lock().unwrap().insert(id, callback);
-                }
-                None => {
-                    initialize_callbacks();
-                    return self.hook(callback);
+            if !self.event_source.is_null() {
+                CFRelease(self.event_source as CFTypeRef);
+                self.event_source = null_mut();
+            }
+            if !mark {
+                // Events of a source in the HID system state look like they
+                // are coming from the hardware
+                self.event_source = CGEventSourceCreate(K_CG_EVENT_SOURCE_STATE_HID_SYSTEM_STATE);
+                if self.event_source.is_null() {
+                    return Err(Error::CustomError("failed to create the event source"));
                 }
             }
         }
+        Ok(())
+    }
+
+    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn listener_sources(&self) -> Vec<String> {
+        self.listener
+            .as_ref()
+            .map(Listener::sources)
+            .unwrap_or_default()
+    }
+
+    fn hook_once(&mut self, callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert_once(id, callback);
         self.callback_counter += 1;
         Ok(id)
     }
 
+    fn hook_filtered_kinds(
+        &mut self,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert_filtered(id, kinds, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_with_context(&mut self, callback: MouseEventContextFn) -> Result<CallbackId, Error> {
+        self.hook(Box::new(move |event| {
+            let context = EventContext {
+                app_id: Self::frontmost_app_id(),
+                ..Default::default()
+            };
+            callback(event, &context)
+        }))
+    }
+
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => match callbacks.lock().unwrap().remove(&callback_id) {
-                    Some(_) => Ok(()),
-                    None => Err(Error::UnhookFailed),
-                },
-                None => {
-                    initialize_callbacks();
-                    self.unhook(callback_id)
-                }
-            }
-        }
+        self.state.forget_label(callback_id);
+        // The callback is dropped once the lock is released, see `RemovedCallbacks`
+        let removed = HOOKS.lock().unwrap().remove(callback_id);
+        removed.map(drop)
     }
 
     fn unhook_all(&mut self) -> Result<(), Error> {
-        unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => {
-                    callbacks.lock().unwrap().clear();
-                }
-                None => {
-                    initialize_callbacks();
-                    return self.unhook_all();
-                }
-            }
+        self.state.forget_labels();
+        let removed = lock_ignore_poison(&HOOKS).clear();
+        drop(removed);
+        Ok(())
+    }
+
+    fn enable_event_history(&mut self, capacity: usize) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        HOOKS.lock().unwrap().enable_history(capacity);
+        Ok(())
+    }
+
+    fn event_history(&self) -> Vec<MouseEvent> {
+        HOOKS.lock().unwrap().history()
+    }
+
+    fn on_callback_panic(&mut self, handler: CallbackPanicFn) -> Result<(), Error> {
+        HOOKS.lock().unwrap().set_panic_handler(handler);
+        Ok(())
+    }
+
+    fn seed_position(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        HOOKS.lock().unwrap().seed_position(x, y);
+        Ok(())
+    }
+
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        if self.listener.is_none() {
+            return Err(Error::CustomError(
+                "the position is only tracked while listening",
+            ));
+        }
+        HOOKS.lock().unwrap().position()
+    }
+
+    fn hook_is_active(&self, callback_id: CallbackId) -> bool {
+        self.listener.as_ref().is_some_and(Listener::is_running)
+            && HOOKS.lock().unwrap().contains(callback_id)
+    }
+
+    fn last_event_at(&self) -> Option<Instant> {
+        HOOKS.lock().unwrap().last_event_at()
+    }
+
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        if self.listener.is_none() {
+            self.start_listener()?;
         }
         Ok(())
     }
-}
 
-fn initialize_callbacks() {
-    unsafe {
-        match CALLBACKS {
-            Some(_) => {}
-            None => {
-                CALLBACKS = Some(Mutex::new(HashMap::new()));
+    fn system_idle_time(&self) -> Result<Duration, Error> {
+        let seconds = unsafe {
+            CGEventSourceSecondsSinceLastEventType(
+                K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION_STATE,
+                K_CG_ANY_INPUT_EVENT_TYPE,
+            )
+        };
+        Duration::try_from_secs_f64(seconds)
+            .map_err(|_| Error::CustomError("failed to get the time of the last input"))
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        if !self.is_output {
+            self.unhook_all()?;
+        }
+        if self.is_watching_display {
+            unsafe {
+                CGDisplayRemoveReconfigurationCallback(
+                    Some(display_reconfiguration_callback),
+                    null_mut(),
+                );
+            }
+            self.is_watching_display = false;
+            // The display callbacks are global, so leave them to the manager that added them
+            lock_ignore_poison(&DISPLAY_CALLBACKS).clear();
+        }
+        self.stop_listener()
+    }
+
+    fn on_display_change(&mut self, callback: Box<dyn Fn() + Send>) -> Result<(), Error> {
+        if !self.is_watching_display {
+            // The reconfiguration callbacks are delivered through the main run loop,
+            // so the application needs to be running one to receive them
+            unsafe {
+                let result = CGDisplayRegisterReconfigurationCallback(
+                    Some(display_reconfiguration_callback),
+                    null_mut(),
+                );
+                if result != CGError::Success {
+                    return Err(Error::CustomError(
+                        "Failed to register the display reconfiguration callback",
+                    ));
+                }
             }
+            self.is_watching_display = true;
         }
+
+        DISPLAY_CALLBACKS.lock().unwrap().push(callback);
+        Ok(())
     }
 }
 
@@ -325,17 +845,57 @@ enum CGError {
     Success = 0,
     TypeCheck = 1008,
 }
+#[derive(Clone, Copy)]
 #[repr(C)]
 pub struct CGPoint {
     x: c_double,
     y: c_double,
 }
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct CGSize {
+    width: c_double,
+    height: c_double,
+}
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
 enum CGEventSource {}
 enum CGEvent {}
 type CGEventSourceRef = *mut CGEventSource;
 type CGEventRef = *mut CGEvent;
 type CFTypeRef = *const c_void;
+type ObjcId = *mut c_void;
 type CGEventMask = c_ulong;
+type CGDirectDisplayID = c_uint;
+type CGDisplayChangeSummaryFlags = c_uint;
+type CGEventFlags = u64;
+type CGDisplayModeRef = *mut c_void;
+const RUN_LOOP_SLICE_SECONDS: c_double = 0.1;
+const K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG: CGDisplayChangeSummaryFlags = 1 << 0;
+type CGEventSourceStateID = c_int;
+const K_CG_EVENT_SOURCE_STATE_HID_SYSTEM_STATE: CGEventSourceStateID = 1;
+const K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION_STATE: CGEventSourceStateID = 0;
+/// `kCGAnyInputEventType`, it is not one of the `CGEventType`s that are sent
+const K_CG_ANY_INPUT_EVENT_TYPE: c_uint = !0;
+/// The lines every click of `scroll_wheel` is sent as
+const LINES_PER_CLICK: c_int = 5;
+/// The most displays `get_monitors` lists
+const MAX_DISPLAYS: u32 = 32;
+const SCHED_OTHER: c_int = 1;
+const SCHED_RR: c_int = 2;
+/// The default thread priority is 31, the highest one is 47
+const HIGH_PRIORITY: c_int = 40;
+const REALTIME_PRIORITY: c_int = 47;
+
+#[repr(C)]
+struct SchedParam {
+    sched_priority: c_int,
+    opaque: [c_char; 4],
+}
 
 #[repr(C)]
 enum CGEventType {
@@ -352,6 +912,7 @@ enum CGEventType {
     _OtherMouseDragged = 27,
 }
 
+#[derive(Clone, Copy)]
 #[repr(C)]
 enum CGMouseButton {
     Left = 0,
@@ -393,10 +954,24 @@ type CGEventTapCallback = Option<
     ) -> CGEventRef,
 >;
 
+type CGDisplayReconfigurationCallBack = Option<
+    unsafe extern "C" fn(
+        display: CGDirectDisplayID,
+        flags: CGDisplayChangeSummaryFlags,
+        user_info: *mut c_void,
+    ),
+>;
+
 #[link(name = "CoreGraphics", kind = "framework")]
 extern "C" {
     fn CGWarpMouseCursorPosition(new_cursor_position: CGPoint) -> CGError;
+    fn CGAssociateMouseAndMouseCursorPosition(connected: c_int) -> CGError;
     fn CGEventCreate(source: CGEventSourceRef) -> CGEventRef;
+    fn CGEventSourceCreate(state_id: CGEventSourceStateID) -> CGEventSourceRef;
+    fn CGEventSourceSecondsSinceLastEventType(
+        state_id: CGEventSourceStateID,
+        event_type: c_uint,
+    ) -> c_double;
     fn CGEventGetLocation(event: CGEventRef) -> CGPoint;
     fn CGEventCreateMouseEvent(
         source: CGEventSourceRef,
@@ -415,6 +990,7 @@ extern "C" {
         wheel2: c_int,
     ) -> CGEventRef;
     fn CGEventPost(tap: CGEventTapLocation, event: CGEventRef);
+    fn CGEventSetFlags(event: CGEventRef, flags: CGEventFlags);
     fn CGEventTapCreate(
         tap: CGEventTapLocation,
         place: CGEventTapPlacement,
@@ -425,12 +1001,39 @@ extern "C" {
     ) -> CFTypeRef;
     fn CGEventTapEnable(tap: *const c_void, enable: bool);
     fn CGEventGetIntegerValueField(event: CGEventRef, field: c_uint) -> c_long;
+    fn CGEventSetIntegerValueField(event: CGEventRef, field: c_uint, value: c_long);
+    fn CGMainDisplayID() -> CGDirectDisplayID;
+    fn CGDisplayPixelsWide(display: CGDirectDisplayID) -> usize;
+    fn CGDisplayPixelsHigh(display: CGDirectDisplayID) -> usize;
+    fn CGDisplayBounds(display: CGDirectDisplayID) -> CGRect;
+    fn CGDisplayCopyDisplayMode(display: CGDirectDisplayID) -> CGDisplayModeRef;
+    fn CGDisplayModeGetRefreshRate(mode: CGDisplayModeRef) -> c_double;
+    fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+    fn CGGetActiveDisplayList(
+        max_displays: u32,
+        active_displays: *mut CGDirectDisplayID,
+        display_count: *mut u32,
+    ) -> CGError;
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: CGDisplayReconfigurationCallBack,
+        user_info: *mut c_void,
+    ) -> CGError;
+    fn CGDisplayRemoveReconfigurationCallback(
+        callback: CGDisplayReconfigurationCallBack,
+        user_info: *mut c_void,
+    ) -> CGError;
+}
+extern "C" {
+    fn pthread_self() -> *mut c_void;
+    fn pthread_setschedparam(thread: *mut c_void, policy: c_int, param: *const SchedParam)
+        -> c_int;
 }
 #[link(name = "CoreFoundation", kind = "framework")]
 extern "C" {
     static kCFRunLoopDefaultMode: *const c_void;
 
     fn CFRelease(cf: CFTypeRef);
+    fn CFRetain(cf: CFTypeRef) -> CFTypeRef;
     fn CFMachPortCreateRunLoopSource(
         allocator: *mut c_void,
         tap: *const c_void,
@@ -438,5 +1041,61 @@ extern "C" {
     ) -> *mut c_void;
     fn CFRunLoopGetCurrent() -> *mut c_void;
     fn CFRunLoopAddSource(rl: *mut c_void, source: *mut c_void, mode: *const c_void);
-    fn CFRunLoopRun();
+    fn CFRunLoopRunInMode(
+        mode: *const c_void,
+        seconds: c_double,
+        return_after_source_handled: bool,
+    ) -> c_int;
+}
+/// Send a message without arguments that returns a rectangle, e.g. `frame`
+unsafe fn send_rect(receiver: ObjcId, selector: &[u8]) -> CGRect {
+    let selector = sel_registerName(selector.as_ptr() as *const c_char);
+    #[cfg(target_arch = "x86_64")]
+    {
+        let mut rect = CGRect {
+            origin: CGPoint { x: 0.0, y: 0.0 },
+            size: CGSize {
+                width: 0.0,
+                height: 0.0,
+            },
+        };
+        objc_msgSend_stret(&mut rect, receiver, selector);
+        rect
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        // The rectangle is returned in the registers on arm64
+        let send: unsafe extern "C" fn(ObjcId, ObjcId) -> CGRect =
+            std::mem::transmute(objc_msgSend as unsafe extern "C" fn(ObjcId, ObjcId) -> ObjcId);
+        send(receiver, selector)
+    }
+}
+
+/// Send a message without arguments that returns a floating point number,
+/// e.g. `doubleClickInterval`
+unsafe fn send_double(receiver: ObjcId, selector: &[u8]) -> c_double {
+    let selector = sel_registerName(selector.as_ptr() as *const c_char);
+    // The number is returned in a floating point register, which the plain
+    // `objc_msgSend` leaves alone on x86_64 as well as on arm64
+    let send: unsafe extern "C" fn(ObjcId, ObjcId) -> c_double =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn(ObjcId, ObjcId) -> ObjcId);
+    send(receiver, selector)
+}
+
+// NSWorkspace, NSScreen and NSEvent live in AppKit, linking it registers the classes with the runtime
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {}
+#[link(name = "objc")]
+extern "C" {
+    fn objc_getClass(name: *const c_char) -> ObjcId;
+    fn sel_registerName(name: *const c_char) -> ObjcId;
+    // Declared with the signature of the messages without arguments, which
+    // is the only kind that is sent
+    fn objc_msgSend(receiver: ObjcId, selector: ObjcId) -> ObjcId;
+    // The structures that do not fit into the registers are returned through
+    // this one on x86_64
+    #[cfg(target_arch = "x86_64")]
+    fn objc_msgSend_stret(result: *mut CGRect, receiver: ObjcId, selector: ObjcId);
+    fn objc_autoreleasePoolPush() -> *mut c_void;
+    fn objc_autoreleasePoolPop(pool: *mut c_void);
 }
@@ -1,3 +1,4 @@
+use crate::common::MouseEvent;
 use std::fmt;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -9,7 +10,27 @@ pub enum Error {
     InputIsBlocked,
     CGCouldNotCreateEvent,
     PermissionDenied,
+    /// The input was blocked because the session is locked or the secure
+    /// desktop is shown, e.g. a UAC prompt. The calling process can not inject
+    /// input into the secure desktop, so the automation has to wait until the
+    /// user unlocks the session. Only returned on Windows, where a failed
+    /// `SendInput` with no foreground window (`GetForegroundWindow` returns
+    /// null) means the desktop is locked
+    DesktopLocked,
+    /// The cursor did not arrive at the target of `move_to_verified` in time,
+    /// with the last position it was seen at
+    MoveNotConfirmed(i32, i32),
+    /// A line of a macro file or a script is not valid, with its line number and the reason
+    InvalidMacro(usize, &'static str),
+    /// There is no macro with the given name in the library
+    UnknownMacro(String),
+    /// The item at the given index of a batch operation failed with the given error
+    BatchFailed(usize, Box<Error>),
+    /// Fewer events than expected arrived in time, with the ones that did
+    EventsTimedOut(Vec<MouseEvent>),
     CustomError(&'static str),
+    /// None of the backends could be initialized, with the error of each one in order
+    NoBackendAvailable(Vec<Error>),
 }
 
 impl std::error::Error for Error {}
@@ -32,7 +53,36 @@ impl fmt::Display for Error {
             Error::PermissionDenied => {
                 "permission denied for this operation, plese try as super user"
             }
+            Error::DesktopLocked => {
+                "failed to send input, the session is locked or the secure desktop is shown"
+            }
+            Error::MoveNotConfirmed(x, y) => {
+                return write!(
+                    f,
+                    "the cursor did not arrive at the target, it was last seen at ({}, {})",
+                    x, y
+                );
+            }
+            Error::InvalidMacro(line, reason) => {
+                return write!(f, "invalid macro on line {}: {}", line, reason);
+            }
+            Error::UnknownMacro(name) => {
+                return write!(f, "there is no macro named {}", name);
+            }
+            Error::BatchFailed(index, err) => {
+                return write!(f, "the item at index {} failed: {}", index, err);
+            }
+            Error::EventsTimedOut(events) => {
+                return write!(f, "only {} of the events arrived in time", events.len());
+            }
             Error::CustomError(err_description) => err_description,
+            Error::NoBackendAvailable(errors) => {
+                write!(f, "none of the backends could be initialized")?;
+                for err in errors {
+                    write!(f, "; {}", err)?;
+                }
+                return Ok(());
+            }
         };
 
         write!(f, "{}", err_message)
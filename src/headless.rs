@@ -0,0 +1,265 @@
+///
+/// This module contains a mouse manager that simulates a cursor in
+/// memory, so the automation can run in CI without a display server
+///
+/// - The cursor is moved on a virtual screen of the given size, the moves
+///   are clamped to its edges like the platforms do
+/// - The pressed buttons and the scrolled wheel clicks are tracked
+/// - Named targets can be placed on the screen to stand for its content,
+///   the clicks that land on them are recorded for hit-testing
+/// - The hooked callbacks receive the events of the actions right away,
+///   on the thread that performs the action
+///
+use crate::common::{
+    self, CallbackId, Monitor, MouseActions, MouseButton, MouseEvent, MouseEventFn, MouseState,
+    ScrollDirection,
+};
+use crate::error::Error;
+use crate::hooks::Hooks;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A named area of the virtual screen, see `HeadlessMouse::add_target`
+#[derive(Clone)]
+struct Target {
+    name: String,
+    area: Monitor,
+}
+
+pub struct HeadlessMouse {
+    state: MouseState,
+    width: usize,
+    height: usize,
+    /// The cursor, the buttons and the clicks are shared with the managers of `output_handle`
+    position: Arc<Mutex<(i32, i32)>>,
+    /// The pressed buttons with the target each one was pressed on
+    pressed: Arc<Mutex<BTreeMap<MouseButton, Option<usize>>>>,
+    /// The scrolled wheel clicks, positive is right and up
+    scrolled: Arc<Mutex<(i32, i32)>>,
+    targets: Vec<Target>,
+    clicks: Arc<Mutex<Vec<(MouseButton, String)>>>,
+    hooks: Mutex<Hooks>,
+    callback_counter: CallbackId,
+}
+
+impl HeadlessMouse {
+    /// Create a cursor at the top-left corner of a virtual screen of the given
+    /// size, a size of 0 is treated as 1
+    pub fn new(width: usize, height: usize) -> Self {
+        HeadlessMouse {
+            state: MouseState::default(),
+            width: width.max(1),
+            height: height.max(1),
+            position: Arc::new(Mutex::new((0, 0))),
+            pressed: Arc::new(Mutex::new(BTreeMap::new())),
+            scrolled: Arc::new(Mutex::new((0, 0))),
+            targets: Vec::new(),
+            clicks: Arc::new(Mutex::new(Vec::new())),
+            hooks: Mutex::new(Hooks::new()),
+            callback_counter: 0,
+        }
+    }
+
+    /// Place a named target on the virtual screen, e.g. a button of the
+    /// simulated application. The targets that are added later are on top
+    pub fn add_target(&mut self, name: &str, x: i32, y: i32, width: usize, height: usize) {
+        self.targets.push(Target {
+            name: name.to_string(),
+            area: Monitor {
+                x,
+                y,
+                width,
+                height,
+            },
+        });
+    }
+
+    /// Get the name of the topmost target at the given position
+    pub fn target_at(&self, x: i32, y: i32) -> Option<&str> {
+        self.target_index_at(x, y)
+            .map(|index| self.targets[index].name.as_str())
+    }
+
+    /// Get the clicks that landed on a target, a click is a press and a
+    /// release of the same button on the same target
+    pub fn clicked_targets(&self) -> Vec<(MouseButton, String)> {
+        self.clicks.lock().unwrap().clone()
+    }
+
+    /// Check whether the given button is held down
+    pub fn is_button_pressed(&self, button: &MouseButton) -> bool {
+        self.pressed.lock().unwrap().contains_key(button)
+    }
+
+    /// Get the wheel clicks that were scrolled so far, positive `x` is
+    /// to the right and positive `y` is up
+    pub fn scrolled(&self) -> (i32, i32) {
+        *self.scrolled.lock().unwrap()
+    }
+
+    /// Get the position of the cursor with the top-left origin
+    fn position(&self) -> (i32, i32) {
+        *self.position.lock().unwrap()
+    }
+
+    fn target_index_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.targets
+            .iter()
+            .rposition(|target| target.area.contains(x, y))
+    }
+
+    /// Move the cursor to the given position, clamped to the screen, and
+    /// return the offset it actually moved by
+    fn place(&self, x: i64, y: i64) -> (i32, i32) {
+        let mut position = self.position.lock().unwrap();
+        let clamped = (
+            x.clamp(0, self.width as i64 - 1) as i32,
+            y.clamp(0, self.height as i64 - 1) as i32,
+        );
+        let offset = (clamped.0 - position.0, clamped.1 - position.1);
+        *position = clamped;
+        offset
+    }
+
+    fn dispatch(&self, event: MouseEvent) {
+        self.hooks.lock().unwrap().dispatch(&event);
+    }
+}
+
+impl fmt::Debug for HeadlessMouse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeadlessMouse")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("position", &*self.position.lock().unwrap())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MouseActions for HeadlessMouse {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "headless"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        // The output acts on the same cursor with the targets that are placed
+        // so far, its actions are not delivered to the hooks of this manager
+        Ok(Box::new(HeadlessMouse {
+            state: self.state.share(),
+            width: self.width,
+            height: self.height,
+            position: self.position.clone(),
+            pressed: self.pressed.clone(),
+            scrolled: self.scrolled.clone(),
+            targets: self.targets.clone(),
+            clicks: self.clicks.clone(),
+            hooks: Mutex::new(Hooks::new()),
+            callback_counter: 0,
+        }))
+    }
+
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0);
+        self.place(x as i64, y as i64);
+        let (x, y) = self.position();
+        self.dispatch(MouseEvent::AbsoluteMove(x, y));
+        Ok(())
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let (x, y) = self.position();
+        let (x_offset, y_offset) =
+            self.place(x as i64 + x_offset as i64, y as i64 + y_offset as i64);
+        if (x_offset, y_offset) != (0, 0) {
+            self.dispatch(MouseEvent::RelativeMove(x_offset, y_offset));
+        }
+        Ok(())
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y) = self.position();
+        Ok((x, common::convert_origin(self, y)?))
+    }
+
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
+        Ok((self.width, self.height))
+    }
+
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        Ok(Monitor {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        })
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let button = &self.state.map_button(button);
+        let (x, y) = self.position();
+        self.pressed
+            .lock()
+            .unwrap()
+            .insert(*button, self.target_index_at(x, y));
+        self.state
+            .remember_click(x, common::convert_origin(self, y)?);
+        self.dispatch(MouseEvent::Press(*button));
+        Ok(())
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let button = &self.state.map_button(button);
+        let (x, y) = self.position();
+        let pressed_on = self.pressed.lock().unwrap().remove(button);
+        if let Some(Some(target)) = pressed_on {
+            if self.target_index_at(x, y) == Some(target) {
+                let name = self.targets[target].name.clone();
+                self.clicks.lock().unwrap().push((*button, name));
+            }
+        }
+        self.dispatch(MouseEvent::Release(*button));
+        Ok(())
+    }
+
+    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
+        {
+            let mut scrolled = self.scrolled.lock().unwrap();
+            match direction {
+                ScrollDirection::Up => scrolled.1 += 1,
+                ScrollDirection::Down => scrolled.1 -= 1,
+                ScrollDirection::Right => scrolled.0 += 1,
+                ScrollDirection::Left => scrolled.0 -= 1,
+            }
+        }
+        self.dispatch(MouseEvent::Scroll(*direction));
+        Ok(())
+    }
+
+    fn hook(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        // The callbacks are invoked on the thread that performs the action while
+        // the hooks are locked, so they must not use this mouse manager
+        let id = self.callback_counter;
+        self.hooks.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        self.state.forget_label(callback_id);
+        // The callback is dropped once the lock is released, see `RemovedCallbacks`
+        let removed = self.hooks.lock().unwrap().remove(callback_id);
+        removed.map(drop)
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.state.forget_labels();
+        let removed = self.hooks.lock().unwrap().clear();
+        drop(removed);
+        Ok(())
+    }
+}
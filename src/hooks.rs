@@ -0,0 +1,296 @@
+///
+/// This module contains the hooked callbacks and the rest
+/// of the state that is shared between a mouse manager and
+/// its event listener
+///
+use crate::common::{
+    CallbackId, CallbackPanicFn, EventKindSet, MouseEvent, MouseEventFn, MouseEventOnceFn,
+};
+use crate::error::Error;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long `shutdown` waits for the listener threads to exit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub(crate) struct Hooks {
+    callbacks: BTreeMap<CallbackId, MouseEventFn>,
+    /// The callbacks that are removed once they receive an event
+    once_callbacks: BTreeMap<CallbackId, MouseEventOnceFn>,
+    /// The kinds of events the filtered callbacks receive, the rest receive all of them
+    kinds: BTreeMap<CallbackId, EventKindSet>,
+    /// The callbacks that want the events to be kept from the other applications
+    exclusive: BTreeSet<CallbackId>,
+    history: VecDeque<MouseEvent>,
+    history_capacity: usize,
+    /// The position that is estimated from the received move events
+    position: Option<(i32, i32)>,
+    panic_handler: Option<CallbackPanicFn>,
+    last_event_at: Option<Instant>,
+}
+
+/// The callbacks that were taken out of the hooks, they have to be dropped
+/// after the lock around the hooks is released since a callback can own a
+/// manager, e.g. the one of `hook_reactive`, whose drop locks them again
+#[must_use]
+pub(crate) struct RemovedCallbacks {
+    callbacks: Vec<MouseEventFn>,
+    once_callbacks: Vec<MouseEventOnceFn>,
+}
+
+impl Hooks {
+    pub const fn new() -> Self {
+        Hooks {
+            callbacks: BTreeMap::new(),
+            once_callbacks: BTreeMap::new(),
+            kinds: BTreeMap::new(),
+            exclusive: BTreeSet::new(),
+            history: VecDeque::new(),
+            history_capacity: 0,
+            position: None,
+            panic_handler: None,
+            last_event_at: None,
+        }
+    }
+
+    pub fn insert(&mut self, callback_id: CallbackId, callback: MouseEventFn) {
+        self.callbacks.insert(callback_id, callback);
+    }
+
+    pub fn insert_filtered(
+        &mut self,
+        callback_id: CallbackId,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) {
+        self.kinds.insert(callback_id, kinds);
+        self.callbacks.insert(callback_id, callback);
+    }
+
+    pub fn insert_exclusive(&mut self, callback_id: CallbackId, callback: MouseEventFn) {
+        self.exclusive.insert(callback_id);
+        self.callbacks.insert(callback_id, callback);
+    }
+
+    /// Check whether any of the callbacks is hooked exclusively
+    pub fn has_exclusive(&self) -> bool {
+        !self.exclusive.is_empty()
+    }
+
+    pub fn insert_once(&mut self, callback_id: CallbackId, callback: MouseEventOnceFn) {
+        self.once_callbacks.insert(callback_id, callback);
+    }
+
+    pub fn remove(&mut self, callback_id: CallbackId) -> Result<RemovedCallbacks, Error> {
+        self.kinds.remove(&callback_id);
+        self.exclusive.remove(&callback_id);
+        let removed = RemovedCallbacks {
+            callbacks: self.callbacks.remove(&callback_id).into_iter().collect(),
+            once_callbacks: self
+                .once_callbacks
+                .remove(&callback_id)
+                .into_iter()
+                .collect(),
+        };
+        if removed.callbacks.is_empty() && removed.once_callbacks.is_empty() {
+            Err(Error::UnhookFailed)
+        } else {
+            Ok(removed)
+        }
+    }
+
+    pub fn clear(&mut self) -> RemovedCallbacks {
+        self.kinds.clear();
+        self.exclusive.clear();
+        RemovedCallbacks {
+            callbacks: std::mem::take(&mut self.callbacks).into_values().collect(),
+            once_callbacks: std::mem::take(&mut self.once_callbacks)
+                .into_values()
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, callback_id: CallbackId) -> bool {
+        self.callbacks.contains_key(&callback_id) || self.once_callbacks.contains_key(&callback_id)
+    }
+
+    #[cfg(test)]
+    pub fn callback_count(&self) -> usize {
+        self.callbacks.len() + self.once_callbacks.len()
+    }
+
+    /// Keep the last `capacity` events, a capacity of 0 disables the history
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn history(&self) -> Vec<MouseEvent> {
+        self.history.iter().copied().collect()
+    }
+
+    pub fn set_panic_handler(&mut self, handler: CallbackPanicFn) {
+        self.panic_handler = Some(handler);
+    }
+
+    /// Set the starting point of the position estimate
+    pub fn seed_position(&mut self, x: i32, y: i32) {
+        self.position = Some((x, y));
+    }
+
+    pub fn position(&self) -> Result<(i32, i32), Error> {
+        self.position.ok_or(Error::CustomError(
+            "the position is not known yet, it needs to be seeded first",
+        ))
+    }
+
+    /// Record the given event and invoke all of the callbacks with it
+    pub fn dispatch(&mut self, event: &MouseEvent) {
+        self.last_event_at = Some(Instant::now());
+        match *event {
+            MouseEvent::AbsoluteMove(x, y) => self.position = Some((x, y)),
+            MouseEvent::RelativeMove(x_offset, y_offset) => {
+                if let Some((x, y)) = self.position {
+                    self.position = Some((x + x_offset, y + y_offset));
+                }
+            }
+            _ => {}
+        }
+
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(*event);
+        }
+
+        // A panicking callback must not take down the listener thread or poison
+        // the lock around the hooks, which would stop all of the callbacks
+        for (callback_id, callback) in &self.callbacks {
+            if self
+                .kinds
+                .get(callback_id)
+                .is_some_and(|kinds| !kinds.matches(event))
+            {
+                continue;
+            }
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(event))) {
+                self.report_panic(payload);
+            }
+        }
+        // The dispatches are serialized by the lock around the hooks, so only
+        // a single event can take the once callbacks
+        for callback in std::mem::take(&mut self.once_callbacks).into_values() {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(event))) {
+                self.report_panic(payload);
+            }
+        }
+    }
+
+    pub fn last_event_at(&self) -> Option<Instant> {
+        self.last_event_at
+    }
+
+    /// Pass the message of a panicking callback to the panic handler
+    pub fn report_panic(&self, payload: Box<dyn Any + Send>) {
+        let Some(handler) = &self.panic_handler else {
+            return;
+        };
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message,
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.as_str(),
+                None => "the callback panicked",
+            },
+        };
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(message)));
+    }
+}
+
+/// Wait for the given threads to exit, the ones that are still running after
+/// the shutdown timeout are detached and left running in the background
+pub(crate) fn join_with_timeout(threads: Vec<JoinHandle<()>>) -> Result<(), Error> {
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while threads.iter().any(|thread| !thread.is_finished()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut result = Ok(());
+    for thread in threads {
+        if thread.is_finished() {
+            let _ = thread.join();
+        } else {
+            result = Err(Error::CustomError(
+                "the listener threads did not exit in time",
+            ));
+        }
+    }
+    result
+}
+
+/// The background threads of a listener, they check the stop flag
+/// regularly and exit once it is set
+pub(crate) struct Listener {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    /// What the threads receive the events from, see `HookInfo::devices`
+    sources: Vec<String>,
+}
+
+impl Listener {
+    pub fn new() -> Self {
+        Listener {
+            stop: Arc::new(AtomicBool::new(false)),
+            threads: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// Record a source the threads receive the events from, e.g. a device file
+    pub fn add_source(&mut self, source: impl Into<String>) {
+        self.sources.push(source.into());
+    }
+
+    /// Get the sources that were recorded with `add_source`
+    pub fn sources(&self) -> Vec<String> {
+        self.sources.clone()
+    }
+
+    /// Spawn a thread of this listener, it is given the stop flag to check
+    pub fn spawn(&mut self, f: impl FnOnce(&AtomicBool) + Send + 'static) {
+        let stop = self.stop.clone();
+        self.threads.push(thread::spawn(move || f(&stop)));
+    }
+
+    /// Check whether any of the threads is still running
+    pub fn is_running(&self) -> bool {
+        self.threads.iter().any(|thread| !thread.is_finished())
+    }
+
+    /// Signal the threads to exit and wait for them
+    pub fn stop(self) -> Result<(), Error> {
+        self.stop.store(true, Ordering::Relaxed);
+        join_with_timeout(self.threads)
+    }
+}
+
+/// Lock the given mutex even if a callback panicked while holding it,
+/// so cleaning up in `Drop` does not panic
+pub(crate) fn lock_ignore_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Invoke the callbacks of the display changes, a panicking callback must not
+/// take down the watcher thread or unwind into the platform that called it
+pub(crate) fn notify_display_change(callbacks: &Mutex<Vec<Box<dyn Fn() + Send>>>) {
+    for callback in lock_ignore_poison(callbacks).iter() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(callback));
+    }
+}
@@ -25,8 +25,20 @@ pub mod windows;
 #[cfg(target_os = "windows")]
 pub use crate::windows::WindowsMouseManager as Mouse;
 
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub use crate::wasm::WasmMouseManager as Mouse;
+
 pub mod common;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod hooks;
+pub mod macros;
+#[cfg(test)]
+mod mock;
+mod rng;
 
 pub use common::MouseActions;
 
@@ -0,0 +1,267 @@
+///
+/// This module contains a library of named macros, i.e. lists of `Action`s,
+/// that are loaded from a text file and run with `MouseActions::run_macro`
+///
+/// Each macro starts with its name in square brackets, followed by one
+/// action per line. Empty lines and the lines starting with `#` are ignored
+///
+/// ```text
+/// # Open the context menu and pick the second entry
+/// [context_menu]
+/// move_to 400 300
+/// click right
+/// sleep 200
+/// move_relative 20 30
+/// click left
+///
+/// [scroll_page]
+/// scroll down
+/// scroll down
+/// ```
+///
+/// The actions are
+/// - `move_to <x> <y>`, `move_relative <x> <y>`
+/// - `click <button>`, `press <button>`, `release <button>`, where the button is
+///   `left`, `middle` or `right`
+/// - `scroll <direction>`, where the direction is `up`, `down`, `left` or `right`
+/// - `sleep <milliseconds>`
+/// - `drag <button> <x> <y>`
+///
+/// A script, see `parse_script`, is a single macro without the name. It also
+/// accepts the shorthands
+/// - `move <x> <y>` for `move_to`
+/// - `scroll <direction> <count>` to scroll multiple times
+/// - `drag <x> <y> <to_x> <to_y>` to drag with the left button between two points
+///
+/// With the `serde` feature a `MacroLibrary` is (de)serialized as a map of the
+/// names to the actions, so the macros can be kept in e.g. JSON or TOML files
+/// instead. The actions are named like the lines above and the sleeps are in
+/// milliseconds
+///
+/// ```json
+/// {
+///     "context_menu": [
+///         { "move_to": [400, 300] },
+///         { "click": "right" },
+///         { "sleep": 200 }
+///     ]
+/// }
+/// ```
+///
+use crate::common::{Action, MouseButton, ScrollDirection};
+use crate::error::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct MacroLibrary {
+    macros: BTreeMap<String, Vec<Action>>,
+}
+
+impl MacroLibrary {
+    /// Load the macros from the file at the given path, see `parse`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read_to_string(path)
+            .map_err(|_| Error::CustomError("failed to read the macro file"))?;
+        Self::parse(&source)
+    }
+
+    /// Parse the macros from the given text, all of the actions are validated
+    /// up front, so a library that loads does not fail halfway through a macro
+    ///
+    /// `Error::InvalidMacro` is returned with the line number (starting from 1)
+    /// of the first line that is not valid
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut macros = BTreeMap::new();
+        let mut current: Option<&mut Vec<Action>> = None;
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason| Error::InvalidMacro(index + 1, reason);
+
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .ok_or(invalid("expected a macro name in square brackets"))?;
+                if macros.contains_key(name) {
+                    return Err(invalid("the macro is defined more than once"));
+                }
+                current = Some(macros.entry(name.to_string()).or_default());
+            } else {
+                current
+                    .as_deref_mut()
+                    .ok_or(invalid("the action does not belong to a macro"))?
+                    .push(parse_action(line).map_err(invalid)?);
+            }
+        }
+        Ok(MacroLibrary { macros })
+    }
+
+    /// Get the actions of the macro with the given name
+    pub fn get(&self, name: &str) -> Option<&[Action]> {
+        self.macros.get(name).map(Vec::as_slice)
+    }
+
+    /// Get the names of all of the macros in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.macros.keys().map(String::as_str)
+    }
+}
+
+/// Parse the actions of a script, e.g. for `MouseActions::run_script`
+///
+/// The lines are the actions of a macro, see the module documentation, and
+/// the shorthands of the scripts. `Error::InvalidMacro` is returned with the
+/// line number (starting from 1) of the first line that is not valid
+///
+/// ```rust
+/// use mouce::common::{Action, MouseButton, ScrollDirection};
+/// use mouce::macros::parse_script;
+///
+/// let actions = parse_script("move 100 200\nclick left\nscroll down 2").unwrap();
+/// assert_eq!(
+///     actions,
+///     vec![
+///         Action::MoveTo(100, 200),
+///         Action::Click(MouseButton::Left),
+///         Action::Scroll(ScrollDirection::Down),
+///         Action::Scroll(ScrollDirection::Down),
+///     ]
+/// );
+/// ```
+pub fn parse_script(source: &str) -> Result<Vec<Action>, Error> {
+    let mut actions = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parse_script_line(line, &mut actions)
+            .map_err(|reason| Error::InvalidMacro(index + 1, reason))?;
+    }
+    Ok(actions)
+}
+
+fn parse_script_line(line: &str, actions: &mut Vec<Action>) -> Result<(), &'static str> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("move") => actions.push(Action::MoveTo(
+            coordinate(&mut words)?,
+            coordinate(&mut words)?,
+        )),
+        Some("scroll") if line.split_whitespace().count() == 3 => {
+            let direction = direction(&mut words)?;
+            let count: u16 = number(&mut words, "the scroll count must be a number up to 65535")?;
+            if count == 0 {
+                return Err("the scroll count must not be 0");
+            }
+            actions.extend((0..count).map(|_| Action::Scroll(direction)));
+        }
+        Some("drag") if line.split_whitespace().count() == 5 => {
+            let from = (coordinate(&mut words)?, coordinate(&mut words)?);
+            actions.push(Action::MoveTo(from.0, from.1));
+            actions.push(Action::Drag(
+                MouseButton::Left,
+                coordinate(&mut words)?,
+                coordinate(&mut words)?,
+            ));
+        }
+        _ => return parse_action(line).map(|action| actions.push(action)),
+    }
+    match words.next() {
+        Some(_) => Err("too many arguments"),
+        None => Ok(()),
+    }
+}
+
+fn parse_action(line: &str) -> Result<Action, &'static str> {
+    let mut words = line.split_whitespace();
+    let action = match words.next() {
+        Some("move_to") => Action::MoveTo(coordinate(&mut words)?, coordinate(&mut words)?),
+        Some("move_relative") => Action::MoveRelative(
+            number(&mut words, "expected an offset")?,
+            number(&mut words, "expected an offset")?,
+        ),
+        Some("click") => Action::Click(button(&mut words)?),
+        Some("press") => Action::Press(button(&mut words)?),
+        Some("release") => Action::Release(button(&mut words)?),
+        Some("scroll") => Action::Scroll(direction(&mut words)?),
+        Some("sleep") => Action::Sleep(Duration::from_millis(number(
+            &mut words,
+            "the sleep must be a positive number of milliseconds",
+        )?)),
+        Some("drag") => Action::Drag(
+            button(&mut words)?,
+            coordinate(&mut words)?,
+            coordinate(&mut words)?,
+        ),
+        _ => return Err("unknown action"),
+    };
+    match words.next() {
+        Some(_) => Err("too many arguments"),
+        None => Ok(action),
+    }
+}
+
+fn number<T: FromStr>(
+    words: &mut SplitWhitespace,
+    reason: &'static str,
+) -> Result<T, &'static str> {
+    words
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or(reason)
+}
+
+/// (De)serialize the duration of `Action::Sleep` as whole milliseconds, like
+/// the macro files do
+#[cfg(feature = "serde")]
+pub(crate) mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Parse a coordinate, a negative number is told apart from a missing one
+fn coordinate(words: &mut SplitWhitespace) -> Result<usize, &'static str> {
+    let word = words.next().ok_or("expected a number")?;
+    word.parse().map_err(|_| match word.parse::<i64>() {
+        Ok(_) => "the coordinates must not be negative",
+        Err(_) => "expected a number",
+    })
+}
+
+fn button(words: &mut SplitWhitespace) -> Result<MouseButton, &'static str> {
+    match words.next() {
+        Some("left") => Ok(MouseButton::Left),
+        Some("middle") => Ok(MouseButton::Middle),
+        Some("right") => Ok(MouseButton::Right),
+        _ => Err("expected left, middle or right as the button"),
+    }
+}
+
+fn direction(words: &mut SplitWhitespace) -> Result<ScrollDirection, &'static str> {
+    match words.next() {
+        Some("up") => Ok(ScrollDirection::Up),
+        Some("down") => Ok(ScrollDirection::Down),
+        Some("left") => Ok(ScrollDirection::Left),
+        Some("right") => Ok(ScrollDirection::Right),
+        _ => Err("expected up, down, left or right as the direction"),
+    }
+}
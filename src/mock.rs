@@ -0,0 +1,415 @@
+///
+/// This module contains a mouse manager that does not talk to
+/// any platform, it records the actions instead so the tests
+/// can verify them
+///
+use crate::common::{
+    self, CallbackId, CallbackPanicFn, EventKindSet, Modifier, Monitor, MouseActions, MouseButton,
+    MouseEvent, MouseEventFn, MouseEventOnceFn, MouseState, PointerDevice, ScrollDirection,
+};
+use crate::error::Error;
+use crate::hooks::{Hooks, Listener};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct MockMouseManager {
+    state: MouseState,
+    /// Shared with the managers of `output_handle`
+    events: Arc<Mutex<Vec<MouseEvent>>>,
+    position: Cell<(i32, i32)>,
+    /// The positions the cursor is found at next, see `script_positions`
+    scripted_positions: RefCell<VecDeque<(i32, i32)>>,
+    /// See `modifier_changes`
+    modifier_changes: RefCell<Vec<(usize, Vec<Modifier>)>>,
+    /// See `inject_on_hook`
+    pending_events: RefCell<Vec<MouseEvent>>,
+    display_size: (usize, usize),
+    /// Shared with the managers of `output_handle`, like the global hooks of
+    /// Windows and macOS
+    hooks: Arc<Mutex<Hooks>>,
+    /// Whether it was created by `output`, it leaves the hooks to the manager
+    /// that created it
+    is_output: bool,
+    callback_counter: CallbackId,
+    hook_calls: usize,
+    listener: Option<Listener>,
+    action_time: Cell<Duration>,
+    /// Set by the listener thread when it exits
+    pub listener_exited: Arc<AtomicBool>,
+    /// Make the n-th (zero based) call to `hook` fail
+    pub fail_hook_at: Option<usize>,
+    pub primary_monitor: Monitor,
+    /// The monitors next to the primary one
+    pub other_monitors: Vec<Monitor>,
+    /// The origin of the focused window, there is no focused window when it is `None`
+    pub active_window_origin: Option<(i32, i32)>,
+    /// The work area of the primary monitor, it is not known when it is `None`
+    pub work_area: Option<Monitor>,
+    /// The refresh rate of the display, it is not known when it is `None`
+    pub refresh_hz: Option<f32>,
+    /// The pointer devices `list_pointers` returns
+    pub pointers: Vec<PointerDevice>,
+    /// The master pointer that was set with `set_active_pointer`
+    pub active_pointer: Option<i32>,
+}
+
+impl MockMouseManager {
+    pub fn new() -> Self {
+        MockMouseManager {
+            state: MouseState::with_mock_clock(),
+            events: Arc::new(Mutex::new(Vec::new())),
+            position: Cell::new((0, 0)),
+            scripted_positions: RefCell::new(VecDeque::new()),
+            modifier_changes: RefCell::new(Vec::new()),
+            pending_events: RefCell::new(Vec::new()),
+            display_size: (1920, 1080),
+            hooks: Arc::new(Mutex::new(Hooks::new())),
+            is_output: false,
+            callback_counter: 0,
+            hook_calls: 0,
+            listener: None,
+            action_time: Cell::new(Duration::ZERO),
+            listener_exited: Arc::new(AtomicBool::new(false)),
+            fail_hook_at: None,
+            primary_monitor: Monitor {
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+            },
+            other_monitors: Vec::new(),
+            active_window_origin: None,
+            work_area: None,
+            refresh_hz: None,
+            pointers: Vec::new(),
+            active_pointer: None,
+        }
+    }
+
+    /// Create a manager that records to the same events and sees the same hooks
+    /// with the same settings, see `MouseActions::output_handle`
+    pub fn output(&self) -> MockMouseManager {
+        let mut output = MockMouseManager::new();
+        output.state = self.state.share();
+        output.events = self.events.clone();
+        output.hooks = self.hooks.clone();
+        output.is_output = true;
+        output.position.set(self.position.get());
+        output
+    }
+
+    /// Place the cursor without recording a move
+    pub fn set_position(&self, x: i32, y: i32) {
+        self.position.set((x, y));
+    }
+
+    /// Make the next calls to `get_position` find the cursor at the given
+    /// positions one after the other, as if the user moved it in between
+    pub fn script_positions(&self, positions: &[(i32, i32)]) {
+        self.scripted_positions.borrow_mut().extend(positions);
+    }
+
+    /// Get the actions that were performed so far
+    pub fn events(&self) -> Vec<MouseEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Get the modifiers that were held with each `hold_modifiers` call, along
+    /// with the number of the actions that were performed before the call
+    pub fn modifier_changes(&self) -> Vec<(usize, Vec<Modifier>)> {
+        self.modifier_changes.borrow().clone()
+    }
+
+    /// Check whether the devices would be grabbed, see `MouseActions::hook_exclusive`
+    pub fn grabbed(&self) -> bool {
+        self.hooks.lock().unwrap().has_exclusive()
+    }
+
+    /// Get the number of callbacks that are currently hooked
+    pub fn hooked_count(&self) -> usize {
+        self.hooks.lock().unwrap().callback_count()
+    }
+
+    /// Deliver the given event to the hooks, as if it came from the platform
+    pub fn inject(&self, event: MouseEvent) {
+        self.hooks.lock().unwrap().dispatch(&event);
+    }
+
+    /// Deliver the given events to the hooks right after the next callback is
+    /// hooked, as if they arrived while the caller waits for them
+    pub fn inject_on_hook(&self, events: &[MouseEvent]) {
+        self.pending_events.borrow_mut().extend(events);
+    }
+
+    /// Get the position of the cursor with the top-left origin, the scripted
+    /// positions are taken first
+    fn cursor_position(&self) -> (i32, i32) {
+        if let Some(position) = self.scripted_positions.borrow_mut().pop_front() {
+            self.position.set(position);
+        }
+        self.position.get()
+    }
+
+    /// Start an idle listener thread, like the platforms do on the first hook
+    fn start_listener(&mut self) {
+        let mut listener = Listener::new();
+        listener.add_source("mock-device");
+        let exited = self.listener_exited.clone();
+        listener.spawn(move |stop| {
+            while !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            exited.store(true, Ordering::Relaxed);
+        });
+        self.listener = Some(listener);
+    }
+
+    /// Make every recorded action advance the mock clock by the given duration
+    pub fn slow_actions(&self, duration: Duration) {
+        self.action_time.set(duration);
+    }
+
+    fn record(&self, event: MouseEvent) {
+        self.events.lock().unwrap().push(event);
+        self.state.advance(self.action_time.get());
+    }
+}
+
+impl Drop for MockMouseManager {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+impl MouseActions for MockMouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        Ok(Box::new(self.output()))
+    }
+
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0) as usize;
+        if self.state.prefers_relative_moves() {
+            let (from_x, from_y) = self.cursor_position();
+            return self.move_relative(x as i32 - from_x, y as i32 - from_y);
+        }
+        self.position.set((x as i32, y as i32));
+        self.record(MouseEvent::AbsoluteMove(x as i32, y as i32));
+        Ok(())
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let (x, y) = self.position.get();
+        self.position.set((x + x_offset, y + y_offset));
+        self.record(MouseEvent::RelativeMove(x_offset, y_offset));
+        Ok(())
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y) = self.cursor_position();
+        Ok((x, common::convert_origin(self, y)?))
+    }
+
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
+        Ok(self.display_size)
+    }
+
+    fn display_refresh_hz(&self) -> Result<f32, Error> {
+        self.refresh_hz.ok_or(Error::NotImplemented)
+    }
+
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        Ok(self.primary_monitor)
+    }
+
+    fn get_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        let mut monitors = vec![self.primary_monitor];
+        monitors.extend(&self.other_monitors);
+        Ok(monitors)
+    }
+
+    fn get_active_window_origin(&self) -> Result<(i32, i32), Error> {
+        self.active_window_origin.ok_or(Error::NotImplemented)
+    }
+
+    fn get_work_area(&self) -> Result<Monitor, Error> {
+        self.work_area.ok_or(Error::NotImplemented)
+    }
+
+    fn list_pointers(&self) -> Result<Vec<PointerDevice>, Error> {
+        Ok(self.pointers.clone())
+    }
+
+    fn set_active_pointer(&mut self, device: &PointerDevice) -> Result<(), Error> {
+        self.active_pointer = Some(common::active_master(&self.pointers, device)?);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.state.reset();
+        self.active_pointer = None;
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (x, y) = self.position.get();
+        self.state
+            .remember_click(x, common::convert_origin(self, y)?);
+        self.record(MouseEvent::Press(self.state.map_button(button)));
+        Ok(())
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.record(MouseEvent::Release(self.state.map_button(button)));
+        Ok(())
+    }
+
+    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
+        self.record(MouseEvent::Scroll(*direction));
+        Ok(())
+    }
+
+    fn hold_modifiers(&self, modifiers: &[Modifier]) -> Result<(), Error> {
+        self.modifier_changes
+            .borrow_mut()
+            .push((self.events.lock().unwrap().len(), modifiers.to_vec()));
+        self.state.set_held_modifiers(modifiers);
+        Ok(())
+    }
+
+    fn hook(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        let call = self.hook_calls;
+        self.hook_calls += 1;
+        if self.fail_hook_at == Some(call) {
+            return Err(Error::PermissionDenied);
+        }
+
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.hooks.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        let pending = self.pending_events.take();
+        pending.into_iter().for_each(|event| self.inject(event));
+        Ok(id)
+    }
+
+    fn listener_sources(&self) -> Vec<String> {
+        self.listener
+            .as_ref()
+            .map(Listener::sources)
+            .unwrap_or_default()
+    }
+
+    fn hook_exclusive(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.hooks.lock().unwrap().insert_exclusive(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_once(&mut self, callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.hooks.lock().unwrap().insert_once(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_filtered_kinds(
+        &mut self,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.hooks
+            .lock()
+            .unwrap()
+            .insert_filtered(id, kinds, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        self.state.forget_label(callback_id);
+        // The callback is dropped once the lock is released, see `RemovedCallbacks`
+        let removed = self.hooks.lock().unwrap().remove(callback_id);
+        removed.map(drop)
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.state.forget_labels();
+        let removed = self.hooks.lock().unwrap().clear();
+        drop(removed);
+        Ok(())
+    }
+
+    fn seed_position(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        self.hooks.lock().unwrap().seed_position(x, y);
+        Ok(())
+    }
+
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        if self.listener.is_none() {
+            return Err(Error::CustomError(
+                "the position is only tracked while listening",
+            ));
+        }
+        self.hooks.lock().unwrap().position()
+    }
+
+    fn hook_is_active(&self, callback_id: CallbackId) -> bool {
+        self.listener.as_ref().is_some_and(Listener::is_running)
+            && self.hooks.lock().unwrap().contains(callback_id)
+    }
+
+    fn last_event_at(&self) -> Option<Instant> {
+        self.hooks.lock().unwrap().last_event_at()
+    }
+
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        if self.listener.is_none() {
+            self.start_listener();
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        let _ = common::release_modifiers(self);
+        if !self.is_output {
+            self.unhook_all()?;
+        }
+        self.listener.take().map_or(Ok(()), Listener::stop)
+    }
+
+    fn enable_event_history(&mut self, capacity: usize) -> Result<(), Error> {
+        self.hooks.lock().unwrap().enable_history(capacity);
+        Ok(())
+    }
+
+    fn event_history(&self) -> Vec<MouseEvent> {
+        self.hooks.lock().unwrap().history()
+    }
+
+    fn on_callback_panic(&mut self, handler: CallbackPanicFn) -> Result<(), Error> {
+        self.hooks.lock().unwrap().set_panic_handler(handler);
+        Ok(())
+    }
+}
@@ -2,20 +2,27 @@
 /// This module contains the mouse action functions
 /// for the unix-like systems
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::common::{
+    self, CallbackId, EventContext, MouseActions, MouseButton, MouseEvent, Priority, ScrollSource,
+};
 use crate::error::Error;
+use crate::hooks::{Hooks, Listener};
 use crate::nix::uinput::{
-    InputEvent, TimeVal, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, EV_KEY, EV_REL, REL_HWHEEL, REL_WHEEL,
-    REL_X, REL_Y,
+    InputEvent, TimeVal, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, EV_KEY, EV_REL, REL_DIAL, REL_HWHEEL,
+    REL_WHEEL, REL_X, REL_Y,
 };
 use glob::glob;
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::mem::size_of;
+use std::os::raw::{c_int, c_short, c_uint, c_ulong};
 use std::os::unix::io::AsRawFd;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::time::Duration;
 
 #[cfg(feature = "x11")]
 use std::{process::Command, str::from_utf8};
@@ -24,7 +31,41 @@ mod x11;
 
 mod uinput;
 
-type Callbacks = Arc<Mutex<HashMap<CallbackId, Box<dyn Fn(&MouseEvent) + Send>>>>;
+thread_local! {
+    /// The kind of the device the event that is being dispatched came from,
+    /// it is set on the thread of the listener that invokes the callbacks
+    static SCROLL_SOURCE: Cell<ScrollSource> = const { Cell::new(ScrollSource::Unknown) };
+}
+
+type Callbacks = Arc<Mutex<Hooks>>;
+type RawCallbacks = Arc<Mutex<BTreeMap<CallbackId, RawEvdevEventFn>>>;
+
+/// An input event exactly as it is read from an evdev device file
+///
+/// This is not portable, it is intended for advanced Linux tooling that needs
+/// the information `MouseEvent` can not represent, e.g. high resolution
+/// scrolling (`REL_WHEEL_HI_RES`) or touchpad and tablet events. See the
+/// `linux/input-event-codes.h` kernel header for the meaning of the fields
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawEvdevEvent {
+    /// The time the kernel generated the event at, relative to the unix epoch
+    pub time: Duration,
+    pub r#type: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+pub type RawEvdevEventFn = Box<dyn Fn(&RawEvdevEvent) + Send>;
+
+/// A backend of the unix-like systems, see `NixMouseManager::with_fallback`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// XTest, it needs the `x11` feature and a running X server
+    X11,
+    /// A virtual device in `/dev/uinput`, it works on Wayland as well
+    /// but needs the permission to write to the file
+    UInput,
+}
 
 pub struct NixMouseManager {}
 
@@ -45,13 +86,224 @@ impl NixMouseManager {
             return Box::new(uinput::UInputMouseManager::new());
         }
     }
+
+    /// Initialize the first backend of the given order that works, instead of
+    /// guessing the backend from the session type like `new` does
+    ///
+    /// If none of them works, `Error::NoBackendAvailable` is returned with the
+    /// error of every backend in the same order
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::nix::Backend;
+    ///
+    /// let manager = Mouse::with_fallback(&[Backend::X11, Backend::UInput]);
+    /// match manager {
+    ///     Ok(manager) => println!("Using {}", manager.backend_name()),
+    ///     Err(err) => println!("{err}"),
+    /// }
+    /// ```
+    pub fn with_fallback(order: &[Backend]) -> Result<Box<dyn MouseActions>, Error> {
+        common::first_available(order, |backend| match backend {
+            #[cfg(feature = "x11")]
+            Backend::X11 => Ok(Box::new(x11::X11MouseManager::try_new()?)),
+            #[cfg(not(feature = "x11"))]
+            Backend::X11 => Err(Error::NotImplemented),
+            Backend::UInput => Ok(Box::new(uinput::UInputMouseManager::try_new()?)),
+        })
+    }
 }
 
 /// Start the event listener for nix systems
-fn start_nix_listener(callbacks: &Callbacks) -> Result<(), Error> {
+fn start_nix_listener(
+    callbacks: &Callbacks,
+    raw_callbacks: &RawCallbacks,
+    priority: Priority,
+    grab: &Arc<AtomicBool>,
+) -> Result<Listener, Error> {
     let (tx, rx) = mpsc::channel();
+    let mut listener = Listener::new();
+
+    for path in mouse_device_paths() {
+        let event = match File::options().read(true).open(&path) {
+            Ok(file) => file,
+            Err(_) => {
+                // Do not leave the threads of the already opened files behind
+                let _ = listener.stop();
+                return Err(Error::PermissionDenied);
+            }
+        };
+
+        listener.add_source(path);
+        let source = device_scroll_source(&event);
+        // Create a thread for this mouse-event file, the file is
+        // closed when the thread exits
+        let tx = tx.clone();
+        let grab = grab.clone();
+        listener.spawn(move |stop| {
+            set_thread_priority(priority);
+            // The grab is released when the file is closed
+            let mut grabbed = false;
+            let mut poll_fd = PollFd {
+                fd: event.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            };
+            while !stop.load(Ordering::Relaxed) {
+                let wants_grab = grab.load(Ordering::Relaxed);
+                if wants_grab != grabbed {
+                    unsafe {
+                        ioctl(event.as_raw_fd(), EVIOCGRAB, wants_grab as c_int);
+                    }
+                    grabbed = wants_grab;
+                }
+                // Do not block on read, so the stop flag is checked regularly
+                if unsafe { poll(&mut poll_fd, 1, POLL_TIMEOUT_MS) } <= 0 {
+                    continue;
+                }
+
+                let mut buffer = InputEvent {
+                    time: TimeVal {
+                        tv_sec: 0,
+                        tv_usec: 0,
+                    },
+                    r#type: 0,
+                    code: 0,
+                    value: 0,
+                };
+                unsafe {
+                    read(event.as_raw_fd(), &mut buffer, size_of::<InputEvent>());
+                }
+                if tx.send((buffer, source)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let callbacks = callbacks.clone();
+    let raw_callbacks = raw_callbacks.clone();
+    // Create a thread for handling the callbacks, it exits
+    // once all of the file threads exit
+    listener.spawn(move |_| {
+        set_thread_priority(priority);
+        for (received, source) in rx {
+            // The raw callbacks receive every event, including the ones
+            // that can not be represented as a MouseEvent.
+            // The casts are needed since c_ulong is not 64 bits wide on every target
+            #[allow(clippy::unnecessary_cast)]
+            let raw_event = RawEvdevEvent {
+                time: Duration::new(
+                    received.time.tv_sec as u64,
+                    received.time.tv_usec as u32 * 1000,
+                ),
+                r#type: received.r#type,
+                code: received.code,
+                value: received.value,
+            };
+            // Like the hooks, a panicking raw callback must not take down the thread
+            for callback in raw_callbacks.lock().unwrap().values() {
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(&raw_event)))
+                {
+                    callbacks.lock().unwrap().report_panic(payload);
+                }
+            }
+
+            // Invoke all given callbacks with the constructed mouse event
+            if let Some(mouse_event) = decode_event(&received) {
+                SCROLL_SOURCE.with(|current| current.set(source));
+                callbacks.lock().unwrap().dispatch(&mouse_event);
+            }
+        }
+    });
+
+    Ok(listener)
+}
+
+/// Construct the library's `MouseEvent` from an evdev event, the events it
+/// can not represent are `None`
+///
+/// The decoded axes are
+/// - `REL_X` and `REL_Y` as `RelativeMove`
+/// - `REL_WHEEL` and `REL_HWHEEL`, the tilt of the wheel, as `Scroll`, or as
+///   `ScrollDelta` when a fast spin reports several notches at once
+/// - `REL_DIAL` as `Dial`
+///
+/// The high resolution wheel axes repeat the notches of `REL_WHEEL` and
+/// `REL_HWHEEL` in finer steps, they are only passed to the raw hooks
+fn decode_event(received: &InputEvent) -> Option<MouseEvent> {
+    let r#type = received.r#type as i32;
+    let val = received.value;
+
+    if r#type == EV_KEY {
+        let code = received.code as i32;
+        let button = if code == BTN_LEFT {
+            MouseButton::Left
+        } else if code == BTN_RIGHT {
+            MouseButton::Right
+        } else if code == BTN_MIDDLE {
+            MouseButton::Middle
+        } else {
+            // Ignore the unknown mouse buttons
+            return None;
+        };
+
+        if val == 1 {
+            Some(MouseEvent::Press(button))
+        } else {
+            Some(MouseEvent::Release(button))
+        }
+    } else if r#type == EV_REL {
+        let code = received.code as u32;
+        // The wheel values are in notches, a fast spin may report several
+        if code == REL_WHEEL {
+            common::scroll_event(0, val)
+        } else if code == REL_HWHEEL {
+            common::scroll_event(val, 0)
+        } else if code == REL_DIAL {
+            (val != 0).then_some(MouseEvent::Dial(val))
+        } else if code == REL_X {
+            Some(MouseEvent::RelativeMove(val, 0))
+        } else if code == REL_Y {
+            Some(MouseEvent::RelativeMove(0, val))
+        } else {
+            None
+        }
+    } else {
+        // Ignore other unknown events
+        None
+    }
+}
+
+/// Raise the scheduling priority of the calling thread, this is best effort
+/// since it needs privileges
+///
+/// Only Linux sets the nice value of the calling thread, the other systems
+/// would set it for the whole process, so their threads keep the normal one
+#[cfg(target_os = "linux")]
+fn set_thread_priority(priority: Priority) {
+    match priority {
+        Priority::Normal => {}
+        Priority::High => unsafe {
+            setpriority(PRIO_PROCESS, 0, HIGH_PRIORITY_NICE);
+        },
+        Priority::RealTime => unsafe {
+            let param = SchedParam {
+                sched_priority: REALTIME_PRIORITY,
+            };
+            pthread_setschedparam(pthread_self(), SCHED_RR, &param);
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_thread_priority(_priority: Priority) {}
 
-    let mut previous_paths = vec![];
+/// Get the paths of the event files of all of the mice, without duplicates
+fn mouse_device_paths() -> Vec<String> {
+    let mut paths = vec![];
     // Read all the mouse events listed under /dev/input/by-id and
     // /dev/input/by-path. These directories are collections of symlinks
     // to /dev/input/event*
@@ -81,97 +333,76 @@ fn start_nix_listener(callbacks: &Callbacks) -> Result<(), Error> {
         }
 
         let path = file.display().to_string();
-
-        if previous_paths.contains(&path) {
-            continue;
+        if !paths.contains(&path) {
+            paths.push(path);
         }
+    }
+    paths
+}
 
-        previous_paths.push(path.clone());
-
-        let event = match File::options().read(true).open(path) {
-            Ok(file) => file,
-            Err(_) => return Err(Error::PermissionDenied),
+/// Check whether any of the mice has a high resolution wheel, i.e. reports
+/// `REL_WHEEL_HI_RES` or `REL_HWHEEL_HI_RES`
+///
+/// The mice that can not be opened, e.g. without the permission to read
+/// `/dev/input`, are not counted
+fn high_res_wheel_present() -> bool {
+    mouse_device_paths().into_iter().any(|path| {
+        let Ok(device) = File::options().read(true).open(path) else {
+            return false;
         };
+        relative_axes(&device)
+            .is_some_and(|axes| axes & (1 << REL_WHEEL_HI_RES | 1 << REL_HWHEEL_HI_RES) != 0)
+    })
+}
 
-        // Create a thread for this mouse-event file
-        let tx = tx.clone();
-        thread::spawn(move || loop {
-            let mut buffer = InputEvent {
-                time: TimeVal {
-                    tv_sec: 0,
-                    tv_usec: 0,
-                },
-                r#type: 0,
-                code: 0,
-                value: 0,
-            };
-            unsafe {
-                read(event.as_raw_fd(), &mut buffer, size_of::<InputEvent>());
-            }
-            tx.send(buffer).unwrap();
-        });
+/// Get the bits of the relative axes the device reports, e.g. `1 << REL_WHEEL`
+fn relative_axes(device: &File) -> Option<u16> {
+    let mut bits = [0u8; 2];
+    if unsafe { ioctl(device.as_raw_fd(), EVIOCGBIT_REL, bits.as_mut_ptr()) } < 0 {
+        return None;
     }
+    Some(u16::from_le_bytes(bits))
+}
 
-    let callbacks = callbacks.clone();
-    // Create a thread for handling the callbacks
-    thread::spawn(move || {
-        for received in rx {
-            // Construct the library's MouseEvent
-            let r#type = received.r#type as i32;
-            let code = received.code as i32;
-            let val = received.value as i32;
-
-            let mouse_event = if r#type == EV_KEY {
-                let button = if code == BTN_LEFT {
-                    MouseButton::Left
-                } else if code == BTN_RIGHT {
-                    MouseButton::Right
-                } else if code == BTN_MIDDLE {
-                    MouseButton::Middle
-                } else {
-                    // Ignore the unknown mouse buttons
-                    continue;
-                };
+/// Find out the kind of the device the scrolls of the given file come from
+fn device_scroll_source(device: &File) -> ScrollSource {
+    let mut keys = [0u8; KEY_BITS_LEN];
+    if unsafe { ioctl(device.as_raw_fd(), EVIOCGBIT_KEY, keys.as_mut_ptr()) } < 0 {
+        return ScrollSource::Unknown;
+    }
+    classify_scroll_source(&keys, relative_axes(device).unwrap_or(0))
+}
 
-                if received.value == 1 {
-                    MouseEvent::Press(button)
-                } else {
-                    MouseEvent::Release(button)
-                }
-            } else if r#type == EV_REL {
-                let code = received.code as u32;
-                if code == REL_WHEEL {
-                    MouseEvent::Scroll(if received.value > 0 {
-                        ScrollDirection::Up
-                    } else {
-                        ScrollDirection::Down
-                    })
-                } else if code == REL_HWHEEL {
-                    MouseEvent::Scroll(if received.value > 0 {
-                        ScrollDirection::Right
-                    } else {
-                        ScrollDirection::Left
-                    })
-                } else if code == REL_X {
-                    MouseEvent::RelativeMove(val, 0)
-                } else if code == REL_Y {
-                    MouseEvent::RelativeMove(0, val)
-                } else {
-                    continue;
-                }
-            } else {
-                // Ignore other unknown events
-                continue;
-            };
+/// Classify a device by the bits of its keys and relative axes, a device that
+/// detects fingers is a touchpad, a device with a wheel axis is a mouse
+fn classify_scroll_source(keys: &[u8], relative_axes: u16) -> ScrollSource {
+    let finger = BTN_TOOL_FINGER as usize;
+    if keys
+        .get(finger / 8)
+        .is_some_and(|byte| byte & (1 << (finger % 8)) != 0)
+    {
+        ScrollSource::Touchpad
+    } else if relative_axes & (1 << REL_WHEEL | 1 << REL_HWHEEL) != 0 {
+        ScrollSource::Wheel
+    } else {
+        ScrollSource::Unknown
+    }
+}
 
-            // Invoke all given callbacks with the constructed mouse event
-            for callback in callbacks.lock().unwrap().values() {
-                callback(&mouse_event);
-            }
-        }
-    });
+/// Get the context of an event that is being dispatched by the listener, only
+/// the scrolls carry the kind of their device
+fn event_context(event: &MouseEvent) -> EventContext {
+    scroll_context(event, SCROLL_SOURCE.with(Cell::get))
+}
 
-    Ok(())
+fn scroll_context(event: &MouseEvent, source: ScrollSource) -> EventContext {
+    match event {
+        MouseEvent::Scroll(_) | MouseEvent::ScrollDelta(..) => EventContext {
+            scroll_source: source,
+            ..Default::default()
+        },
+        _ => EventContext::default(),
+    }
 }
 
 fn is_x11() -> bool {
@@ -197,6 +428,168 @@ fn is_x11() -> bool {
     false
 }
 
+/// How long the listener threads block before they check the stop flag again
+const POLL_TIMEOUT_MS: c_int = 100;
+const POLLIN: c_short = 0x001;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+/// _IOW('E', 0x90, int), grabs the device so only this file receives its events
+const EVIOCGRAB: c_ulong = 0x40044590;
+/// _IOC(_IOC_READ, 'E', 0x20 + EV_REL, 2), gets the bits of the relative axes
+/// up to REL_MAX (0x0f) of the device
+const EVIOCGBIT_REL: c_ulong = 0x80024522;
+/// _IOC(_IOC_READ, 'E', 0x20 + EV_KEY, 96), gets the bits of the keys up to
+/// KEY_MAX (0x2ff) of the device
+const EVIOCGBIT_KEY: c_ulong = 0x80604521;
+const KEY_BITS_LEN: usize = 96;
+const BTN_TOOL_FINGER: c_uint = 0x145;
+const REL_WHEEL_HI_RES: c_uint = 0x0b;
+const REL_HWHEEL_HI_RES: c_uint = 0x0c;
+#[cfg(target_os = "linux")]
+const PRIO_PROCESS: c_int = 0;
+#[cfg(target_os = "linux")]
+const HIGH_PRIORITY_NICE: c_int = -10;
+#[cfg(target_os = "linux")]
+const SCHED_RR: c_int = 2;
+/// A low real-time priority (1 to 99), it is still above all of the normal threads
+#[cfg(target_os = "linux")]
+const REALTIME_PRIORITY: c_int = 10;
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct SchedParam {
+    sched_priority: c_int,
+}
+
 extern "C" {
     fn read(fd: i32, buf: *mut InputEvent, count: usize) -> i32;
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    #[cfg(target_os = "linux")]
+    fn setpriority(which: c_int, who: c_uint, prio: c_int) -> c_int;
+    #[cfg(all(test, target_os = "linux"))]
+    fn getpriority(which: c_int, who: c_uint) -> c_int;
+    #[cfg(target_os = "linux")]
+    fn pthread_self() -> c_ulong;
+    #[cfg(target_os = "linux")]
+    fn pthread_setschedparam(thread: c_ulong, policy: c_int, param: *const SchedParam) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::ScrollDirection;
+    use std::thread;
+
+    fn record(r#type: c_int, code: u16, value: i32) -> InputEvent {
+        InputEvent {
+            time: TimeVal {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            r#type: r#type as u16,
+            code,
+            value,
+        }
+    }
+
+    #[test]
+    fn classify_scroll_source() {
+        let mut touchpad_keys = [0u8; KEY_BITS_LEN];
+        touchpad_keys[BTN_TOOL_FINGER as usize / 8] |= 1 << (BTN_TOOL_FINGER % 8);
+        let mouse_keys = [0u8; KEY_BITS_LEN];
+        let wheel = (1 << REL_X | 1 << REL_Y | 1 << REL_WHEEL) as u16;
+        assert_eq!(
+            super::classify_scroll_source(&touchpad_keys, wheel),
+            ScrollSource::Touchpad
+        );
+        assert_eq!(
+            super::classify_scroll_source(&mouse_keys, wheel),
+            ScrollSource::Wheel
+        );
+        assert_eq!(
+            super::classify_scroll_source(&mouse_keys, (1 << REL_HWHEEL) as u16),
+            ScrollSource::Wheel
+        );
+        assert_eq!(
+            super::classify_scroll_source(&[], (1 << REL_X) as u16),
+            ScrollSource::Unknown
+        );
+    }
+
+    #[test]
+    fn scroll_context() {
+        for source in [ScrollSource::Wheel, ScrollSource::Touchpad] {
+            let scroll = super::decode_event(&record(EV_REL, REL_WHEEL as u16, 1)).unwrap();
+            assert_eq!(super::scroll_context(&scroll, source).scroll_source, source);
+            let fast_scroll = super::decode_event(&record(EV_REL, REL_HWHEEL as u16, 3)).unwrap();
+            assert_eq!(
+                super::scroll_context(&fast_scroll, source).scroll_source,
+                source
+            );
+            let moved = super::decode_event(&record(EV_REL, REL_X as u16, 1)).unwrap();
+            assert_eq!(
+                super::scroll_context(&moved, source),
+                EventContext::default()
+            );
+        }
+    }
+
+    #[test]
+    fn decode_event() {
+        let decoded: Vec<_> = [
+            record(EV_REL, REL_X as u16, -3),
+            record(EV_REL, REL_WHEEL as u16, -1),
+            record(EV_REL, REL_HWHEEL as u16, 1),
+            record(EV_REL, REL_HWHEEL as u16, -2),
+            record(EV_REL, REL_DIAL as u16, 4),
+            record(EV_REL, REL_DIAL as u16, 0),
+            record(EV_REL, REL_WHEEL_HI_RES as u16, -120),
+            record(EV_KEY, BTN_MIDDLE as u16, 1),
+            record(EV_KEY, BTN_MIDDLE as u16, 0),
+            record(EV_KEY, 0x113, 1),
+            record(0, 0, 0),
+        ]
+        .iter()
+        .filter_map(super::decode_event)
+        .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                MouseEvent::RelativeMove(-3, 0),
+                MouseEvent::Scroll(ScrollDirection::Down),
+                MouseEvent::Scroll(ScrollDirection::Right),
+                MouseEvent::ScrollDelta(-2, 0),
+                MouseEvent::Dial(4),
+                MouseEvent::Press(MouseButton::Middle),
+                MouseEvent::Release(MouseButton::Middle),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn thread_priority_leaves_the_process_alone() {
+        let process_nice = unsafe { getpriority(PRIO_PROCESS, 0) };
+        let nice_of = |priority| {
+            thread::spawn(move || {
+                set_thread_priority(priority);
+                unsafe { getpriority(PRIO_PROCESS, 0) }
+            })
+            .join()
+            .unwrap()
+        };
+        assert_eq!(nice_of(Priority::Normal), process_nice);
+        // Lowering the nice value needs privileges, the thread keeps the
+        // nice value of the process without them
+        let high_nice = nice_of(Priority::High);
+        assert!(high_nice == HIGH_PRIORITY_NICE || high_nice == process_nice);
+        assert_eq!(unsafe { getpriority(PRIO_PROCESS, 0) }, process_nice);
+    }
 }
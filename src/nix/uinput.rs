@@ -5,39 +5,65 @@
 /// - Unsupported mouse actions
 ///     - get_position is not available on uinput
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::common::{
+    self, CallbackId, CallbackPanicFn, EventKindSet, MouseActions, MouseButton, MouseEvent,
+    MouseEventContextFn, MouseEventFn, MouseEventOnceFn, MouseState, ScrollDirection,
+    ScrollGranularity,
+};
 use crate::error::Error;
-use crate::nix::Callbacks;
-use std::collections::HashMap;
+use crate::hooks::{lock_ignore_poison, Hooks, Listener};
+use crate::nix::{high_res_wheel_present, Callbacks, RawCallbacks, RawEvdevEventFn};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
+use std::io::ErrorKind;
 use std::mem::size_of;
 use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_ushort};
 use std::os::unix::prelude::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const UINPUT_MAX_NAME_SIZE: usize = 80;
 
 pub struct UInputMouseManager {
-    uinput_file: File,
+    state: MouseState,
+    /// Shared with the managers of `output_handle`
+    device: Arc<Device>,
     callbacks: Callbacks,
+    raw_callbacks: RawCallbacks,
     callback_counter: CallbackId,
-    is_listening: bool,
+    listener: Option<Listener>,
+    /// Set while an exclusive hook is hooked, the listener grabs the devices then
+    grab: Arc<AtomicBool>,
 }
 
 impl UInputMouseManager {
     pub fn new() -> Self {
-        let manager = UInputMouseManager {
-            uinput_file: File::options()
+        Self::try_new().expect("uinput file can not be opened")
+    }
+
+    /// Create the virtual device, failing if `/dev/uinput` can not be opened
+    pub fn try_new() -> Result<Self, Error> {
+        let uinput_file =
+            File::options()
                 .write(true)
                 .open("/dev/uinput")
-                .expect("uinput file can not be opened"),
-            callbacks: Arc::new(Mutex::new(HashMap::new())),
+                .map_err(|err| match err.kind() {
+                    ErrorKind::PermissionDenied => Error::PermissionDenied,
+                    _ => Error::CustomError("uinput file can not be opened"),
+                })?;
+        let manager = UInputMouseManager {
+            state: MouseState::default(),
+            device: Arc::new(Device(uinput_file)),
+            callbacks: Arc::new(Mutex::new(Hooks::new())),
+            raw_callbacks: Arc::new(Mutex::new(BTreeMap::new())),
             callback_counter: 0,
-            is_listening: false,
+            listener: None,
+            grab: Arc::new(AtomicBool::new(false)),
         };
-        let fd = manager.uinput_file.as_raw_fd();
+        let fd = manager.device.0.as_raw_fd();
         unsafe {
             // For press events (also needed for mouse movement)
             ioctl(fd, UI_SET_EVBIT, EV_KEY);
@@ -88,7 +114,7 @@ impl UInputMouseManager {
         // the event, otherwise it will not notice the event we are about to send.
         thread::sleep(Duration::from_millis(300));
 
-        manager
+        Ok(manager)
     }
 
     /// Write the given event to the uinput file
@@ -102,7 +128,7 @@ impl UInputMouseManager {
             code: code as u16,
             value,
         };
-        let fd = self.uinput_file.as_raw_fd();
+        let fd = self.device.0.as_raw_fd();
 
         unsafe {
             let count = size_of::<InputEvent>();
@@ -145,7 +171,18 @@ impl UInputMouseManager {
 
 impl Drop for UInputMouseManager {
     fn drop(&mut self) {
-        let fd = self.uinput_file.as_raw_fd();
+        // There is no way to report the errors from drop
+        let _ = self.shutdown();
+    }
+}
+
+/// The virtual device of uinput, it is destroyed once the last of the managers
+/// that send to it is dropped
+struct Device(File);
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        let fd = self.0.as_raw_fd();
         unsafe {
             // Destroy the device, the file is closed automatically by the File module
             ioctl(fd, UI_DEV_DESTROY as c_ulong);
@@ -153,15 +190,44 @@ impl Drop for UInputMouseManager {
     }
 }
 
+impl fmt::Debug for UInputMouseManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UInputMouseManager")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
 impl MouseActions for UInputMouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "uinput"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        Ok(Box::new(UInputMouseManager {
+            state: self.state.share(),
+            device: self.device.clone(),
+            callbacks: Arc::new(Mutex::new(Hooks::new())),
+            raw_callbacks: Arc::new(Mutex::new(BTreeMap::new())),
+            callback_counter: 0,
+            listener: None,
+            grab: Arc::new(AtomicBool::new(false)),
+        }))
+    }
+
     fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
         // For some reason, absolute mouse move events are not working on uinput
         // (as I understand those events are intended for touch events)
         //
         // As a work around solution; first set the mouse to top left, then
         // call relative move function to simulate an absolute move event
+        let y = common::convert_origin(self, y as i32)?.max(0);
         self.move_relative(i32::MIN, i32::MIN)?;
-        self.move_relative(x as i32, y as i32)
+        self.move_relative(x as i32, y)
     }
 
     fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
@@ -174,28 +240,23 @@ impl MouseActions for UInputMouseManager {
     }
 
     fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => BTN_LEFT,
-            MouseButton::Right => BTN_RIGHT,
-            MouseButton::Middle => BTN_MIDDLE,
-        };
-        self.emit(EV_KEY, btn, 1)?;
+        self.emit(EV_KEY, uinput_button(&self.state.map_button(button)), 1)?;
         self.syncronize()
     }
 
     fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => BTN_LEFT,
-            MouseButton::Right => BTN_RIGHT,
-            MouseButton::Middle => BTN_MIDDLE,
-        };
-        self.emit(EV_KEY, btn, 0)?;
+        self.emit(EV_KEY, uinput_button(&self.state.map_button(button)), 0)?;
         self.syncronize()
     }
 
     fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+        // Each event still needs its own report so the press and the release are
+        // not merged, but there is no need to wait between them
+        let btn = uinput_button(&self.state.map_button(button));
+        self.emit(EV_KEY, btn, 1)?;
+        self.emit(EV_SYN, SYN_REPORT, 0)?;
+        self.emit(EV_KEY, btn, 0)?;
+        self.syncronize()
     }
 
     fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
@@ -209,11 +270,26 @@ impl MouseActions for UInputMouseManager {
         self.syncronize()
     }
 
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            super::start_nix_listener(&self.callbacks)?;
-            self.is_listening = true;
+    fn scroll_granularity(&self) -> Result<ScrollGranularity, Error> {
+        Ok(ScrollGranularity::Notch)
+    }
+
+    fn high_res_scroll_available(&self) -> bool {
+        high_res_wheel_present()
+    }
+
+    fn set_mark_synthetic(&mut self, mark: bool) -> Result<(), Error> {
+        // The events come from a virtual kernel device, they are not marked
+        // in any way and there is no way to mark them
+        if mark {
+            Err(Error::NotImplemented)
+        } else {
+            Ok(())
         }
+    }
+
+    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
 
         let id = self.callback_counter;
         self.callbacks.lock().unwrap().insert(id, callback);
@@ -221,17 +297,156 @@ impl MouseActions for UInputMouseManager {
         Ok(id)
     }
 
+    fn listener_sources(&self) -> Vec<String> {
+        self.listener
+            .as_ref()
+            .map(Listener::sources)
+            .unwrap_or_default()
+    }
+
+    fn hook_exclusive(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks
+            .lock()
+            .unwrap()
+            .insert_exclusive(id, callback);
+        self.grab.store(true, Ordering::Relaxed);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_once(&mut self, callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks.lock().unwrap().insert_once(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_raw_evdev(&mut self, callback: RawEvdevEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.raw_callbacks.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_with_context(&mut self, callback: MouseEventContextFn) -> Result<CallbackId, Error> {
+        self.hook(Box::new(move |event| {
+            callback(event, &super::event_context(event))
+        }))
+    }
+
+    fn hook_filtered_kinds(
+        &mut self,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks
+            .lock()
+            .unwrap()
+            .insert_filtered(id, kinds, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        match self.callbacks.lock().unwrap().remove(&callback_id) {
-            Some(_) => Ok(()),
-            None => Err(Error::UnhookFailed),
+        self.state.forget_label(callback_id);
+        // The callbacks are dropped once the locks are released, see `RemovedCallbacks`
+        let raw_callback = self.raw_callbacks.lock().unwrap().remove(&callback_id);
+        if raw_callback.is_some() {
+            return Ok(());
         }
+        let mut callbacks = self.callbacks.lock().unwrap();
+        let removed = callbacks.remove(callback_id);
+        self.grab
+            .store(callbacks.has_exclusive(), Ordering::Relaxed);
+        drop(callbacks);
+        removed.map(drop)
     }
 
     fn unhook_all(&mut self) -> Result<(), Error> {
-        self.callbacks.lock().unwrap().clear();
+        self.state.forget_labels();
+        let removed = lock_ignore_poison(&self.callbacks).clear();
+        let raw_callbacks = std::mem::take(&mut *lock_ignore_poison(&self.raw_callbacks));
+        drop((removed, raw_callbacks));
+        self.grab.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn seed_position(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        self.callbacks.lock().unwrap().seed_position(x, y);
+        Ok(())
+    }
+
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        if self.listener.is_none() {
+            return Err(Error::CustomError(
+                "the position is only tracked while listening",
+            ));
+        }
+        self.callbacks.lock().unwrap().position()
+    }
+
+    fn hook_is_active(&self, callback_id: CallbackId) -> bool {
+        self.listener.as_ref().is_some_and(Listener::is_running)
+            && self.callbacks.lock().unwrap().contains(callback_id)
+    }
+
+    fn last_event_at(&self) -> Option<Instant> {
+        self.callbacks.lock().unwrap().last_event_at()
+    }
+
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        if self.listener.is_none() {
+            self.listener = Some(super::start_nix_listener(
+                &self.callbacks,
+                &self.raw_callbacks,
+                self.state.listener_priority(),
+                &self.grab,
+            )?);
+        }
         Ok(())
     }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        self.unhook_all()?;
+        self.listener.take().map_or(Ok(()), Listener::stop)
+    }
+
+    fn enable_event_history(&mut self, capacity: usize) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        self.callbacks.lock().unwrap().enable_history(capacity);
+        Ok(())
+    }
+
+    fn event_history(&self) -> Vec<MouseEvent> {
+        self.callbacks.lock().unwrap().history()
+    }
+
+    fn on_callback_panic(&mut self, handler: CallbackPanicFn) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().set_panic_handler(handler);
+        Ok(())
+    }
+}
+
+/// Get the uinput button code of the given button
+fn uinput_button(button: &MouseButton) -> c_int {
+    match button {
+        MouseButton::Left => BTN_LEFT,
+        MouseButton::Right => BTN_RIGHT,
+        MouseButton::Middle => BTN_MIDDLE,
+    }
 }
 
 /// ioctl and uinput definitions
@@ -248,6 +463,7 @@ pub const REL_X: c_uint = 0x00;
 pub const REL_Y: c_uint = 0x01;
 pub const REL_WHEEL: c_uint = 0x08;
 pub const REL_HWHEEL: c_uint = 0x06;
+pub const REL_DIAL: c_uint = 0x07;
 pub const BTN_LEFT: c_int = 0x110;
 pub const BTN_RIGHT: c_int = 0x111;
 pub const BTN_MIDDLE: c_int = 0x112;
@@ -2,60 +2,101 @@
 /// This module contains the mouse action functions
 /// for the unix-like systems that use X11
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::common::{
+    self, CallbackId, CallbackPanicFn, CursorClip, CursorShape, EventKindSet, Modifier, Monitor,
+    MouseActions, MouseButton, MouseEvent, MouseEventContextFn, MouseEventFn, MouseEventOnceFn,
+    MouseState, PointerDevice, PointerRole, ScrollDirection, ScrollGranularity,
+};
 use crate::error::Error;
-use crate::nix::Callbacks;
-use std::collections::HashMap;
-use std::os::raw::{c_char, c_int, c_uint, c_ulong};
-use std::sync::{Arc, Mutex};
+use crate::hooks::{self, lock_ignore_poison, Hooks, Listener};
+use crate::nix::{
+    high_res_wheel_present, poll, Callbacks, PollFd, RawCallbacks, RawEvdevEventFn, POLLIN,
+    POLL_TIMEOUT_MS,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type DisplayCallbacks = Arc<Mutex<Vec<Box<dyn Fn() + Send>>>>;
 
 pub struct X11MouseManager {
+    state: MouseState,
     display: *mut Display,
+    /// The screen the output goes to, see `MouseActions::set_target_screen`
+    screen: c_int,
+    /// The root window of the screen
     window: Window,
     callbacks: Callbacks,
+    raw_callbacks: RawCallbacks,
     callback_counter: CallbackId,
-    is_listening: bool,
+    listener: Option<Listener>,
+    /// Set while an exclusive hook is hooked, the listener grabs the devices then
+    grab: Arc<AtomicBool>,
+    display_callbacks: DisplayCallbacks,
+    display_watcher: Option<Listener>,
+    /// The client pointer of the connection before `set_active_pointer` changed it
+    default_pointer: Option<c_int>,
+    /// The pointer mapping of the server, it is refreshed on MappingNotify so
+    /// the clicks do not wait for a round trip to read it each
+    pointer_mapping: RefCell<Vec<u8>>,
 }
 
 impl X11MouseManager {
     pub fn new() -> Self {
+        Self::try_new().expect("failed to open the X display")
+    }
+
+    /// Connect to the X server, failing if there is none
+    pub fn try_new() -> Result<Self, Error> {
         unsafe {
             let display = XOpenDisplay(&0);
+            if display.is_null() {
+                return Err(Error::CustomError("failed to open the X display"));
+            }
             let window = XDefaultRootWindow(display);
-            X11MouseManager {
+            Ok(X11MouseManager {
+                state: MouseState::default(),
                 display,
+                screen: XDefaultScreen(display),
                 window,
-                callbacks: Arc::new(Mutex::new(HashMap::new())),
+                callbacks: Arc::new(Mutex::new(Hooks::new())),
+                raw_callbacks: Arc::new(Mutex::new(BTreeMap::new())),
                 callback_counter: 0,
-                is_listening: false,
-            }
+                listener: None,
+                grab: Arc::new(AtomicBool::new(false)),
+                display_callbacks: Arc::new(Mutex::new(Vec::new())),
+                display_watcher: None,
+                default_pointer: None,
+                pointer_mapping: RefCell::new(read_pointer_mapping(display)),
+            })
         }
     }
 
     fn button_event(&self, button: &MouseButton, is_press: bool) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => 1,
-            MouseButton::Middle => 2,
-            MouseButton::Right => 3,
-        };
+        let btn = self.physical_button(x11_button(&self.state.map_button(button)));
         unsafe {
             XTestFakeButtonEvent(self.display, btn, is_press, 0);
             XFlush(self.display);
         }
         Ok(())
     }
-}
 
-impl MouseActions for X11MouseManager {
-    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+    /// Warp the pointer to the given position, with the top-left origin
+    fn warp(&self, x: i32, y: i32) {
         unsafe {
-            XWarpPointer(self.display, 0, self.window, 0, 0, 0, 0, x as i32, y as i32);
+            XWarpPointer(self.display, 0, self.window, 0, 0, 0, 0, x, y);
             XFlush(self.display);
         }
-        Ok(())
     }
 
-    fn get_position(&self) -> Result<(i32, i32), Error> {
+    /// Get the position of the pointer on the screen, with the top-left origin
+    fn pointer_position(&self) -> Result<(i32, i32), Error> {
         let mut x = 0;
         let mut y = 0;
         let mut void = 0;
@@ -84,8 +125,637 @@ impl MouseActions for X11MouseManager {
         Ok((x, y))
     }
 
+    /// Check whether the X server supports XInput 2
+    fn has_xinput2(&self) -> bool {
+        let mut opcode = 0;
+        let mut event_base = 0;
+        let mut error_base = 0;
+        let mut major = 2;
+        let mut minor = 0;
+        unsafe {
+            // The XInput requests fail with an X error if the extension is missing
+            XQueryExtension(
+                self.display,
+                c"XInputExtension".as_ptr(),
+                &mut opcode,
+                &mut event_base,
+                &mut error_base,
+            ) != 0
+                && XIQueryVersion(self.display, &mut major, &mut minor) == 0
+        }
+    }
+
+    /// Move the pointer by the given offset with a native relative motion
+    fn move_by(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        unsafe {
+            XTestFakeRelativeMotionEvent(self.display, x_offset, y_offset, 0);
+            XFlush(self.display);
+        }
+        Ok(())
+    }
+
+    /// Press or release the key of the given modifier
+    fn send_modifier(&self, modifier: &Modifier, is_press: bool) -> Result<(), Error> {
+        let keysym = match modifier {
+            Modifier::Shift => XK_SHIFT_L,
+            Modifier::Control => XK_CONTROL_L,
+            Modifier::Alt => XK_ALT_L,
+            Modifier::Meta => XK_SUPER_L,
+        };
+        unsafe {
+            let keycode = XKeysymToKeycode(self.display, keysym);
+            if keycode == 0 {
+                return Err(Error::CustomError("the modifier key is not mapped"));
+            }
+            XTestFakeKeyEvent(self.display, keycode as c_uint, is_press, 0);
+        }
+        Ok(())
+    }
+
+    /// Get the physical button that is mapped to the given logical button
+    ///
+    /// XTest sends physical buttons, which the server then maps to the logical
+    /// ones, e.g. with the buttons of a left-handed user swapped the primary
+    /// (logical 1) button is the physical button 3
+    fn physical_button(&self, logical: c_uint) -> c_uint {
+        // The server sends MappingNotify to all of the clients, checking for
+        // it only looks at the events that already arrived
+        let mut event = XEvent { pad: [0; 24] };
+        let mut changed = false;
+        while unsafe { XCheckTypedEvent(self.display, MAPPING_NOTIFY, &mut event) } != 0 {
+            changed = true;
+        }
+        if changed {
+            *self.pointer_mapping.borrow_mut() = read_pointer_mapping(self.display);
+        }
+        self.pointer_mapping
+            .borrow()
+            .iter()
+            .position(|mapped| *mapped as c_uint == logical)
+            .map_or(logical, |index| index as c_uint + 1)
+    }
+
+    /// Remember the current position as the position of the last click
+    fn remember_click(&self) {
+        if let Ok((x, y)) = self.get_position() {
+            self.state.remember_click(x, y);
+        }
+    }
+}
+
+// The display connection is only used by the thread that owns the manager,
+// the listener threads open connections of their own
+unsafe impl Send for X11MouseManager {}
+
+impl Drop for X11MouseManager {
+    fn drop(&mut self) {
+        // There is no way to report the errors from drop
+        let _ = self.shutdown();
+        if !self.display.is_null() {
+            unsafe {
+                XCloseDisplay(self.display);
+            }
+        }
+    }
+}
+
+/// Get the mapping of the physical buttons to the logical ones
+fn read_pointer_mapping(display: *mut Display) -> Vec<u8> {
+    // The core protocol allows at most 255 buttons
+    let mut mapping = vec![0; 256];
+    let count =
+        unsafe { XGetPointerMapping(display, mapping.as_mut_ptr(), mapping.len() as c_int) };
+    mapping.truncate(count.max(0) as usize);
+    mapping
+}
+
+/// Get the X11 button number of the given button
+fn x11_button(button: &MouseButton) -> c_uint {
+    match button {
+        MouseButton::Left => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Right => 3,
+    }
+}
+
+/// Start a thread that listens to the XRandR screen change events
+/// and invokes the display callbacks for each of them
+fn start_display_watcher(callbacks: &DisplayCallbacks) -> Result<Listener, Error> {
+    let (tx, rx) = mpsc::channel();
+    let callbacks = callbacks.clone();
+    let mut watcher = Listener::new();
+
+    watcher.spawn(move |stop| unsafe {
+        // Xlib connections should not be shared between threads,
+        // so the watcher opens its own connection
+        let display = XOpenDisplay(&0);
+        if display.is_null() {
+            tx.send(Err(Error::CustomError("failed to open the X display")))
+                .unwrap();
+            return;
+        }
+
+        let mut event_base = 0;
+        let mut error_base = 0;
+        if XRRQueryExtension(display, &mut event_base, &mut error_base) == 0 {
+            // The X server does not support the XRandR extension
+            XCloseDisplay(display);
+            tx.send(Err(Error::NotImplemented)).unwrap();
+            return;
+        }
+
+        XRRSelectInput(
+            display,
+            XDefaultRootWindow(display),
+            RR_SCREEN_CHANGE_NOTIFY_MASK,
+        );
+        tx.send(Ok(())).unwrap();
+
+        let mut event = XEvent { pad: [0; 24] };
+        let mut poll_fd = PollFd {
+            fd: XConnectionNumber(display),
+            events: POLLIN,
+            revents: 0,
+        };
+        while !stop.load(Ordering::Relaxed) {
+            while XPending(display) > 0 {
+                XNextEvent(display, &mut event);
+                if event.r#type == event_base + RR_SCREEN_CHANGE_NOTIFY {
+                    // Let Xlib know about the new screen configuration
+                    XRRUpdateConfiguration(&mut event);
+                    hooks::notify_display_change(&callbacks);
+                }
+            }
+            // Do not block on XNextEvent, so the stop flag is checked regularly
+            poll(&mut poll_fd, 1, POLL_TIMEOUT_MS);
+        }
+        XCloseDisplay(display);
+    });
+
+    rx.recv().unwrap()?;
+    Ok(watcher)
+}
+
+impl fmt::Debug for X11MouseManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("X11MouseManager")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MouseActions for X11MouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        // Xlib connections should not be shared between threads,
+        // so the output opens its own connection
+        let mut output = X11MouseManager::try_new()?;
+        output.state = self.state.share();
+        output.screen = self.screen;
+        output.window = self.window;
+        if self.default_pointer.is_some() {
+            // The client pointer is a setting of the connection
+            unsafe {
+                let mut active = 0;
+                XIGetClientPointer(self.display, 0, &mut active);
+                XISetClientPointer(output.display, 0, active);
+            }
+            output.default_pointer = self.default_pointer;
+        }
+        Ok(Box::new(output))
+    }
+
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0);
+        if self.state.prefers_relative_moves() {
+            let (from_x, from_y) = self.pointer_position()?;
+            return self.move_by(x as i32 - from_x, y - from_y);
+        }
+        self.warp(x as i32, y);
+        Ok(())
+    }
+
+    fn move_to_silent(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0);
+        self.warp(x as i32, y);
+        Ok(())
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y) = self.pointer_position()?;
+        Ok((x, common::convert_origin(self, y)?))
+    }
+
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
+        unsafe {
+            let width = XDisplayWidth(self.display, self.screen);
+            let height = XDisplayHeight(self.display, self.screen);
+            Ok((width as usize, height as usize))
+        }
+    }
+
+    fn get_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        unsafe {
+            let resources = XRRGetScreenResourcesCurrent(self.display, self.window);
+            if resources.is_null() {
+                return Err(Error::CustomError(
+                    "failed to get the XRandR screen resources",
+                ));
+            }
+            let mut monitors = Vec::new();
+            for index in 0..(*resources).ncrtc as usize {
+                let crtc = XRRGetCrtcInfo(self.display, resources, *(*resources).crtcs.add(index));
+                if crtc.is_null() {
+                    continue;
+                }
+                // The disabled crtcs have no size
+                if (*crtc).width > 0 && (*crtc).height > 0 {
+                    monitors.push(Monitor {
+                        x: (*crtc).x,
+                        y: (*crtc).y,
+                        width: (*crtc).width as usize,
+                        height: (*crtc).height as usize,
+                    });
+                }
+                XRRFreeCrtcInfo(crtc);
+            }
+            XRRFreeScreenResources(resources);
+
+            if monitors.is_empty() {
+                monitors.push(self.get_primary_monitor()?);
+            }
+            Ok(monitors)
+        }
+    }
+
+    fn set_target_screen(&mut self, screen: i32) -> Result<(), Error> {
+        unsafe {
+            if screen < 0 || screen >= XScreenCount(self.display) {
+                return Err(Error::CustomError("the X screen does not exist"));
+            }
+            self.screen = screen;
+            self.window = XRootWindow(self.display, screen);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.state.reset();
+        unsafe {
+            self.screen = XDefaultScreen(self.display);
+            self.window = XRootWindow(self.display, self.screen);
+            if let Some(default) = self.default_pointer.take() {
+                XISetClientPointer(self.display, 0, default);
+                XFlush(self.display);
+            }
+        }
+    }
+
+    fn display_refresh_hz(&self) -> Result<f32, Error> {
+        unsafe {
+            let config = XRRGetScreenInfo(self.display, self.window);
+            if config.is_null() {
+                return Err(Error::CustomError(
+                    "failed to get the XRandR screen configuration",
+                ));
+            }
+            let rate = XRRConfigCurrentRate(config);
+            XRRFreeScreenConfigInfo(config);
+            if rate <= 0 {
+                return Err(Error::NotImplemented);
+            }
+            Ok(rate as f32)
+        }
+    }
+
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        unsafe {
+            // Fall back to the whole X screen if XRandR does not know the primary output
+            let primary = XRRGetOutputPrimary(self.display, self.window);
+            if primary == 0 {
+                let (width, height) = self.get_display_size()?;
+                return Ok(Monitor {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                });
+            }
+
+            let resources = XRRGetScreenResourcesCurrent(self.display, self.window);
+            if resources.is_null() {
+                return Err(Error::CustomError(
+                    "failed to get the XRandR screen resources",
+                ));
+            }
+            let output = XRRGetOutputInfo(self.display, resources, primary);
+            if output.is_null() {
+                XRRFreeScreenResources(resources);
+                return Err(Error::CustomError("failed to get the XRandR output info"));
+            }
+            let crtc = XRRGetCrtcInfo(self.display, resources, (*output).crtc);
+            XRRFreeOutputInfo(output);
+            XRRFreeScreenResources(resources);
+            if crtc.is_null() {
+                return Err(Error::CustomError("failed to get the XRandR crtc info"));
+            }
+
+            let monitor = Monitor {
+                x: (*crtc).x,
+                y: (*crtc).y,
+                width: (*crtc).width as usize,
+                height: (*crtc).height as usize,
+            };
+            XRRFreeCrtcInfo(crtc);
+            Ok(monitor)
+        }
+    }
+
+    fn get_pointer_mapping(&self) -> Result<Vec<u8>, Error> {
+        let mapping = read_pointer_mapping(self.display);
+        *self.pointer_mapping.borrow_mut() = mapping.clone();
+        Ok(mapping)
+    }
+
+    fn double_click_time(&self) -> Result<Duration, Error> {
+        // The same resource and default as the X Toolkit Intrinsics use
+        const DEFAULT_MULTI_CLICK_TIME: Duration = Duration::from_millis(200);
+        unsafe {
+            let value = XGetDefault(self.display, c"mouce".as_ptr(), c"multiClickTime".as_ptr());
+            if value.is_null() {
+                return Ok(DEFAULT_MULTI_CLICK_TIME);
+            }
+            let millis = CStr::from_ptr(value)
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse().ok());
+            Ok(millis.map_or(DEFAULT_MULTI_CLICK_TIME, Duration::from_millis))
+        }
+    }
+
+    fn get_work_area(&self) -> Result<Monitor, Error> {
+        unsafe {
+            // Only look the atom up, it does not exist when no window manager set it
+            let atom = XInternAtom(self.display, c"_NET_WORKAREA".as_ptr(), 1);
+            if atom == 0 {
+                return Err(Error::CustomError(
+                    "the window manager does not report the work area",
+                ));
+            }
+
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut item_count = 0;
+            let mut bytes_after = 0;
+            let mut property: *mut c_uchar = null_mut();
+            // The property has x, y, width and height for every desktop,
+            // only the ones of the first desktop are read
+            let status = XGetWindowProperty(
+                self.display,
+                self.window,
+                atom,
+                0,
+                4,
+                0,
+                XA_CARDINAL,
+                &mut actual_type,
+                &mut actual_format,
+                &mut item_count,
+                &mut bytes_after,
+                &mut property,
+            );
+            if status != 0 || property.is_null() {
+                return Err(Error::CustomError("failed to get the work area"));
+            }
+            // 32 bit properties are returned as an array of longs
+            let area = if item_count == 4 && actual_format == 32 {
+                let values = std::slice::from_raw_parts(property as *const c_long, 4);
+                Some(Monitor {
+                    x: values[0] as i32,
+                    y: values[1] as i32,
+                    width: values[2] as usize,
+                    height: values[3] as usize,
+                })
+            } else {
+                None
+            };
+            XFree(property as *mut c_void);
+            area.ok_or(Error::CustomError("the work area is not valid"))
+        }
+    }
+
+    fn list_pointers(&self) -> Result<Vec<PointerDevice>, Error> {
+        if !self.has_xinput2() {
+            return Err(Error::NotImplemented);
+        }
+        unsafe {
+            let mut count = 0;
+            let devices = XIQueryDevice(self.display, XI_ALL_DEVICES, &mut count);
+            if devices.is_null() {
+                return Err(Error::CustomError("failed to query the input devices"));
+            }
+            let pointers = std::slice::from_raw_parts(devices, count.max(0) as usize)
+                .iter()
+                .filter_map(|info| pointer_device(info))
+                .collect();
+            XIFreeDeviceInfo(devices);
+            Ok(pointers)
+        }
+    }
+
+    fn set_active_pointer(&mut self, device: &PointerDevice) -> Result<(), Error> {
+        // An id that is not a master pointer would end the process with an X error
+        let master = common::active_master(&self.list_pointers()?, device)?;
+        unsafe {
+            if self.default_pointer.is_none() {
+                let mut default = 0;
+                XIGetClientPointer(self.display, 0, &mut default);
+                self.default_pointer = Some(default);
+            }
+            // The window `None` sets the client pointer of this connection
+            XISetClientPointer(self.display, 0, master);
+            XFlush(self.display);
+        }
+        Ok(())
+    }
+
+    fn get_active_window_origin(&self) -> Result<(i32, i32), Error> {
+        unsafe {
+            // Only look the atom up, it does not exist when no window manager set it
+            let atom = XInternAtom(self.display, c"_NET_ACTIVE_WINDOW".as_ptr(), 1);
+            if atom == 0 {
+                return Err(Error::CustomError(
+                    "the window manager does not report the active window",
+                ));
+            }
+
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut item_count = 0;
+            let mut bytes_after = 0;
+            let mut property: *mut c_uchar = null_mut();
+            let status = XGetWindowProperty(
+                self.display,
+                self.window,
+                atom,
+                0,
+                1,
+                0,
+                XA_WINDOW,
+                &mut actual_type,
+                &mut actual_format,
+                &mut item_count,
+                &mut bytes_after,
+                &mut property,
+            );
+            if status != 0 || property.is_null() {
+                return Err(Error::CustomError("failed to get the active window"));
+            }
+            // 32 bit properties are returned as an array of longs
+            let window = if item_count == 1 && actual_format == 32 {
+                *(property as *const Window)
+            } else {
+                0
+            };
+            XFree(property as *mut c_void);
+            if window == 0 {
+                return Err(Error::CustomError("there is no active window"));
+            }
+
+            let (mut x, mut y) = (0, 0);
+            let mut child = 0;
+            if XTranslateCoordinates(
+                self.display,
+                window,
+                self.window,
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            ) == 0
+            {
+                return Err(Error::X11PointerWindowMismatch);
+            }
+            Ok((x, y))
+        }
+    }
+
+    fn get_cursor_shape(&self) -> Result<CursorShape, Error> {
+        unsafe {
+            // Returns null when the server does not support XFixes
+            let image = XFixesGetCursorImage(self.display);
+            if image.is_null() {
+                return Err(Error::NotImplemented);
+            }
+            let name = if (*image).name.is_null() {
+                None
+            } else {
+                CStr::from_ptr((*image).name).to_str().ok()
+            };
+            let shape = cursor_shape(name.unwrap_or_default());
+            XFree(image as *mut c_void);
+            Ok(shape)
+        }
+    }
+
+    fn confine_cursor(
+        &self,
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+    ) -> Result<CursorClip, Error> {
+        unsafe {
+            // The grab is made on its own connection, closing the connection
+            // releases the grab and destroys the confining window
+            let display = XOpenDisplay(&0);
+            if display.is_null() {
+                return Err(Error::CustomError("failed to open the X display"));
+            }
+            let root = XDefaultRootWindow(display);
+
+            // An invisible window that is not managed by the window manager
+            let mut attributes = std::mem::zeroed::<XSetWindowAttributes>();
+            attributes.override_redirect = 1;
+            let window = XCreateWindow(
+                display,
+                root,
+                x,
+                y,
+                width as c_uint,
+                height as c_uint,
+                0,
+                0,
+                INPUT_ONLY,
+                null_mut(),
+                CW_OVERRIDE_REDIRECT,
+                &mut attributes,
+            );
+            // The confining window has to be viewable when the pointer is grabbed
+            XMapWindow(display, window);
+            XSync(display, 0);
+
+            let result = XGrabPointer(
+                display,
+                root,
+                1,
+                0,
+                GRAB_MODE_ASYNC,
+                GRAB_MODE_ASYNC,
+                window,
+                0,
+                CURRENT_TIME,
+            );
+            if result != GRAB_SUCCESS {
+                XCloseDisplay(display);
+                return Err(Error::CustomError("failed to grab the pointer"));
+            }
+            XFlush(display);
+
+            Ok(CursorClip::new(move || {
+                XCloseDisplay(display);
+            }))
+        }
+    }
+
+    fn is_pointer_grabbed(&self) -> Result<bool, Error> {
+        unsafe {
+            let root = XDefaultRootWindow(self.display);
+            match XGrabPointer(
+                self.display,
+                root,
+                0,
+                0,
+                GRAB_MODE_ASYNC,
+                GRAB_MODE_ASYNC,
+                0,
+                0,
+                CURRENT_TIME,
+            ) {
+                GRAB_SUCCESS => {
+                    XUngrabPointer(self.display, CURRENT_TIME);
+                    XFlush(self.display);
+                    Ok(false)
+                }
+                // A frozen pointer is held by a synchronous grab of another client
+                ALREADY_GRABBED | GRAB_FROZEN => Ok(true),
+                _ => Err(Error::CustomError("failed to test grab the pointer")),
+            }
+        }
+    }
+
     fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.button_event(button, true)
+        self.button_event(button, true)?;
+        self.remember_click();
+        Ok(())
     }
 
     fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
@@ -93,17 +763,24 @@ impl MouseActions for X11MouseManager {
     }
 
     fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+        // Queue both of the events and flush them to the X server together
+        let btn = self.physical_button(x11_button(&self.state.map_button(button)));
+        unsafe {
+            XTestFakeButtonEvent(self.display, btn, true, 0);
+            XTestFakeButtonEvent(self.display, btn, false, 0);
+            XFlush(self.display);
+        }
+        self.remember_click();
+        Ok(())
     }
 
     fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
-        let btn = match direction {
+        let btn = self.physical_button(match direction {
             ScrollDirection::Up => 4,
             ScrollDirection::Down => 5,
             ScrollDirection::Left => 6,
             ScrollDirection::Right => 7,
-        };
+        });
         unsafe {
             XTestFakeButtonEvent(self.display, btn, true, 0);
             XTestFakeButtonEvent(self.display, btn, false, 0);
@@ -112,11 +789,56 @@ impl MouseActions for X11MouseManager {
         Ok(())
     }
 
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            super::start_nix_listener(&self.callbacks)?;
-            self.is_listening = true;
+    fn scroll_granularity(&self) -> Result<ScrollGranularity, Error> {
+        Ok(ScrollGranularity::Notch)
+    }
+
+    fn high_res_scroll_available(&self) -> bool {
+        high_res_wheel_present()
+    }
+
+    fn hold_modifiers(&self, modifiers: &[Modifier]) -> Result<(), Error> {
+        let held = self.state.held_modifiers();
+        // Remember which keys are down after a failed key, so a later call
+        // releases the ones that were pressed
+        let mut now_held = held.clone();
+        let result = held
+            .iter()
+            .filter(|modifier| !modifiers.contains(modifier))
+            .try_for_each(|modifier| {
+                self.send_modifier(modifier, false)?;
+                now_held.retain(|held| held != modifier);
+                Ok(())
+            })
+            .and_then(|_| {
+                modifiers
+                    .iter()
+                    .filter(|modifier| !held.contains(modifier))
+                    .try_for_each(|modifier| {
+                        self.send_modifier(modifier, true)?;
+                        now_held.push(*modifier);
+                        Ok(())
+                    })
+            });
+        unsafe {
+            XFlush(self.display);
         }
+        self.state.set_held_modifiers(&now_held);
+        result
+    }
+
+    fn set_mark_synthetic(&mut self, mark: bool) -> Result<(), Error> {
+        // XTest events are processed like the events of a real device, their
+        // `send_event` flag is never set and there is no way to set it
+        if mark {
+            Err(Error::NotImplemented)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
 
         let id = self.callback_counter;
         self.callbacks.lock().unwrap().insert(id, callback);
@@ -124,15 +846,186 @@ impl MouseActions for X11MouseManager {
         Ok(id)
     }
 
+    fn listener_sources(&self) -> Vec<String> {
+        self.listener
+            .as_ref()
+            .map(Listener::sources)
+            .unwrap_or_default()
+    }
+
+    fn hook_exclusive(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks
+            .lock()
+            .unwrap()
+            .insert_exclusive(id, callback);
+        self.grab.store(true, Ordering::Relaxed);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_once(&mut self, callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks.lock().unwrap().insert_once(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_raw_evdev(&mut self, callback: RawEvdevEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.raw_callbacks.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_with_context(&mut self, callback: MouseEventContextFn) -> Result<CallbackId, Error> {
+        self.hook(Box::new(move |event| {
+            callback(event, &super::event_context(event))
+        }))
+    }
+
+    fn hook_filtered_kinds(
+        &mut self,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        self.callbacks
+            .lock()
+            .unwrap()
+            .insert_filtered(id, kinds, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        match self.callbacks.lock().unwrap().remove(&callback_id) {
-            Some(_) => Ok(()),
-            None => Err(Error::UnhookFailed),
+        self.state.forget_label(callback_id);
+        // The callbacks are dropped once the locks are released, see `RemovedCallbacks`
+        let raw_callback = self.raw_callbacks.lock().unwrap().remove(&callback_id);
+        if raw_callback.is_some() {
+            return Ok(());
         }
+        let mut callbacks = self.callbacks.lock().unwrap();
+        let removed = callbacks.remove(callback_id);
+        self.grab
+            .store(callbacks.has_exclusive(), Ordering::Relaxed);
+        drop(callbacks);
+        removed.map(drop)
     }
 
     fn unhook_all(&mut self) -> Result<(), Error> {
-        self.callbacks.lock().unwrap().clear();
+        self.state.forget_labels();
+        let removed = lock_ignore_poison(&self.callbacks).clear();
+        let raw_callbacks = std::mem::take(&mut *lock_ignore_poison(&self.raw_callbacks));
+        drop((removed, raw_callbacks));
+        self.grab.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn enable_event_history(&mut self, capacity: usize) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        self.callbacks.lock().unwrap().enable_history(capacity);
+        Ok(())
+    }
+
+    fn event_history(&self) -> Vec<MouseEvent> {
+        self.callbacks.lock().unwrap().history()
+    }
+
+    fn on_callback_panic(&mut self, handler: CallbackPanicFn) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().set_panic_handler(handler);
+        Ok(())
+    }
+
+    fn seed_position(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        self.callbacks.lock().unwrap().seed_position(x, y);
+        Ok(())
+    }
+
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        if self.listener.is_none() {
+            return Err(Error::CustomError(
+                "the position is only tracked while listening",
+            ));
+        }
+        self.callbacks.lock().unwrap().position()
+    }
+
+    fn hook_is_active(&self, callback_id: CallbackId) -> bool {
+        self.listener.as_ref().is_some_and(Listener::is_running)
+            && self.callbacks.lock().unwrap().contains(callback_id)
+    }
+
+    fn last_event_at(&self) -> Option<Instant> {
+        self.callbacks.lock().unwrap().last_event_at()
+    }
+
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        if self.listener.is_none() {
+            self.listener = Some(super::start_nix_listener(
+                &self.callbacks,
+                &self.raw_callbacks,
+                self.state.listener_priority(),
+                &self.grab,
+            )?);
+        }
+        Ok(())
+    }
+
+    fn system_idle_time(&self) -> Result<Duration, Error> {
+        unsafe {
+            let mut event_base = 0;
+            let mut error_base = 0;
+            if XScreenSaverQueryExtension(self.display, &mut event_base, &mut error_base) == 0 {
+                return Err(Error::NotImplemented);
+            }
+            let info = XScreenSaverAllocInfo();
+            if info.is_null() {
+                return Err(Error::CustomError(
+                    "failed to allocate the screen saver info",
+                ));
+            }
+            let status = XScreenSaverQueryInfo(self.display, self.window, info);
+            let idle = (*info).idle;
+            XFree(info as *mut c_void);
+            if status == 0 {
+                return Err(Error::CustomError(
+                    "failed to get the time of the last input",
+                ));
+            }
+            // `c_ulong` is only 32 bits wide on some of the targets
+            #[allow(clippy::unnecessary_cast)]
+            Ok(Duration::from_millis(idle as u64))
+        }
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        let _ = common::release_modifiers(self);
+        self.unhook_all()?;
+        lock_ignore_poison(&self.display_callbacks).clear();
+
+        let listener = self.listener.take().map_or(Ok(()), Listener::stop);
+        let display_watcher = self.display_watcher.take().map_or(Ok(()), Listener::stop);
+        listener.and(display_watcher)
+    }
+
+    fn on_display_change(&mut self, callback: Box<dyn Fn() + Send>) -> Result<(), Error> {
+        if self.display_watcher.is_none() {
+            self.display_watcher = Some(start_display_watcher(&self.display_callbacks)?);
+        }
+
+        self.display_callbacks.lock().unwrap().push(callback);
         Ok(())
     }
 }
@@ -141,15 +1034,53 @@ impl MouseActions for X11MouseManager {
 enum _XDisplay {}
 type Display = _XDisplay;
 type Window = c_ulong;
+type KeySym = c_ulong;
 
-#[derive(Debug)]
 #[repr(C)]
-struct XEvent {
+union XEvent {
     r#type: c_int,
     xbutton: XButtonEvent,
+    // XEvent is defined as a union padded to 24 longs
+    pad: [c_long; 24],
 }
 
-#[derive(Debug)]
+const INPUT_ONLY: c_uint = 2;
+const CW_OVERRIDE_REDIRECT: c_ulong = 1 << 9;
+const GRAB_MODE_ASYNC: c_int = 1;
+const GRAB_SUCCESS: c_int = 0;
+const ALREADY_GRABBED: c_int = 1;
+const GRAB_FROZEN: c_int = 4;
+const CURRENT_TIME: Time = 0;
+const MAPPING_NOTIFY: c_int = 34;
+const XA_WINDOW: Atom = 33;
+const XA_CARDINAL: Atom = 6;
+const XK_SHIFT_L: KeySym = 0xffe1;
+const XK_CONTROL_L: KeySym = 0xffe3;
+const XK_ALT_L: KeySym = 0xffe9;
+const XK_SUPER_L: KeySym = 0xffeb;
+type Atom = c_ulong;
+
+#[repr(C)]
+struct XSetWindowAttributes {
+    background_pixmap: c_ulong,
+    background_pixel: c_ulong,
+    border_pixmap: c_ulong,
+    border_pixel: c_ulong,
+    bit_gravity: c_int,
+    win_gravity: c_int,
+    backing_store: c_int,
+    backing_planes: c_ulong,
+    backing_pixel: c_ulong,
+    save_under: c_int,
+    event_mask: c_long,
+    do_not_propagate_mask: c_long,
+    override_redirect: c_int,
+    colormap: c_ulong,
+    cursor: c_ulong,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 struct XButtonEvent {
     r#type: c_int,
@@ -165,11 +1096,29 @@ struct XButtonEvent {
     same_screen: bool,
 }
 
+/// Map the name of a cursor of the cursor theme to its shape, cursors that
+/// were created by the applications themselves have no name
+fn cursor_shape(name: &str) -> CursorShape {
+    match name {
+        "left_ptr" | "default" | "arrow" | "top_left_arrow" => CursorShape::Arrow,
+        "watch" | "wait" | "left_ptr_watch" | "progress" => CursorShape::Wait,
+        "xterm" | "text" | "ibeam" => CursorShape::IBeam,
+        "hand1" | "hand2" | "hand" | "pointer" | "pointing_hand" => CursorShape::Hand,
+        _ => CursorShape::Unknown,
+    }
+}
+
 // Xlib function definitions
 #[link(name = "X11")]
 extern "C" {
     fn XOpenDisplay(display: *const c_char) -> *mut Display;
     fn XDefaultRootWindow(display: *mut Display) -> Window;
+    fn XDefaultScreen(display: *mut Display) -> c_int;
+    fn XKeysymToKeycode(display: *mut Display, keysym: KeySym) -> c_uchar;
+    fn XScreenCount(display: *mut Display) -> c_int;
+    fn XRootWindow(display: *mut Display, screen_number: c_int) -> Window;
+    fn XDisplayWidth(display: *mut Display, screen_number: c_int) -> c_int;
+    fn XDisplayHeight(display: *mut Display, screen_number: c_int) -> c_int;
     fn XWarpPointer(
         display: *mut Display,
         src_w: Window,
@@ -182,7 +1131,87 @@ extern "C" {
         dest_y: c_int,
     ) -> c_int;
 
+    fn XCloseDisplay(display: *mut Display) -> c_int;
+    #[allow(clippy::too_many_arguments)]
+    fn XCreateWindow(
+        display: *mut Display,
+        parent: Window,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        border_width: c_uint,
+        depth: c_int,
+        class: c_uint,
+        visual: *mut c_void,
+        valuemask: c_ulong,
+        attributes: *mut XSetWindowAttributes,
+    ) -> Window;
+    fn XMapWindow(display: *mut Display, window: Window) -> c_int;
+    fn XSync(display: *mut Display, discard: c_int) -> c_int;
+    #[allow(clippy::too_many_arguments)]
+    fn XGrabPointer(
+        display: *mut Display,
+        grab_window: Window,
+        owner_events: c_int,
+        event_mask: c_uint,
+        pointer_mode: c_int,
+        keyboard_mode: c_int,
+        confine_to: Window,
+        cursor: c_ulong,
+        time: Time,
+    ) -> c_int;
+    fn XUngrabPointer(display: *mut Display, time: Time) -> c_int;
     fn XFlush(display: *mut Display) -> c_int;
+    fn XFree(data: *mut c_void) -> c_int;
+    fn XInternAtom(display: *mut Display, atom_name: *const c_char, only_if_exists: c_int) -> Atom;
+    fn XQueryExtension(
+        display: *mut Display,
+        name: *const c_char,
+        major_opcode_return: *mut c_int,
+        first_event_return: *mut c_int,
+        first_error_return: *mut c_int,
+    ) -> c_int;
+    #[allow(clippy::too_many_arguments)]
+    fn XGetWindowProperty(
+        display: *mut Display,
+        window: Window,
+        property: Atom,
+        long_offset: c_long,
+        long_length: c_long,
+        delete: c_int,
+        req_type: Atom,
+        actual_type_return: *mut Atom,
+        actual_format_return: *mut c_int,
+        nitems_return: *mut c_ulong,
+        bytes_after_return: *mut c_ulong,
+        prop_return: *mut *mut c_uchar,
+    ) -> c_int;
+    #[allow(clippy::too_many_arguments)]
+    fn XTranslateCoordinates(
+        display: *mut Display,
+        src_w: Window,
+        dest_w: Window,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x_return: *mut c_int,
+        dest_y_return: *mut c_int,
+        child_return: *mut Window,
+    ) -> c_int;
+    fn XGetPointerMapping(display: *mut Display, map_return: *mut c_uchar, nmap: c_int) -> c_int;
+    fn XGetDefault(
+        display: *mut Display,
+        program: *const c_char,
+        option: *const c_char,
+    ) -> *const c_char;
+    fn XNextEvent(display: *mut Display, event_return: *mut XEvent) -> c_int;
+    fn XPending(display: *mut Display) -> c_int;
+    fn XCheckTypedEvent(
+        display: *mut Display,
+        event_type: c_int,
+        event_return: *mut XEvent,
+    ) -> c_int;
+    fn XConnectionNumber(display: *mut Display) -> c_int;
     fn XQueryPointer(
         display: *mut Display,
         window: Window,
@@ -205,4 +1234,192 @@ extern "C" {
         is_press: bool,
         delay: c_ulong,
     ) -> c_int;
+    fn XTestFakeKeyEvent(
+        dpy: *mut Display,
+        keycode: c_uint,
+        is_press: bool,
+        delay: c_ulong,
+    ) -> c_int;
+    fn XTestFakeRelativeMotionEvent(dpy: *mut Display, x: c_int, y: c_int, delay: c_ulong)
+        -> c_int;
+}
+
+// XFixes definitions
+#[allow(dead_code)]
+#[repr(C)]
+struct XFixesCursorImage {
+    x: c_short,
+    y: c_short,
+    width: c_ushort,
+    height: c_ushort,
+    xhot: c_ushort,
+    yhot: c_ushort,
+    cursor_serial: c_ulong,
+    pixels: *mut c_ulong,
+    atom: c_ulong,
+    name: *const c_char,
+}
+
+// XFixes function definitions
+#[link(name = "Xfixes")]
+extern "C" {
+    fn XFixesGetCursorImage(dpy: *mut Display) -> *mut XFixesCursorImage;
+}
+
+// X Screen Saver definitions
+#[repr(C)]
+struct XScreenSaverInfo {
+    window: Window,
+    state: c_int,
+    kind: c_int,
+    til_or_since: c_ulong,
+    /// The milliseconds since the last input
+    idle: c_ulong,
+    event_mask: c_ulong,
+}
+
+// X Screen Saver function definitions
+#[link(name = "Xss")]
+extern "C" {
+    fn XScreenSaverQueryExtension(
+        dpy: *mut Display,
+        event_base_return: *mut c_int,
+        error_base_return: *mut c_int,
+    ) -> c_int;
+    fn XScreenSaverAllocInfo() -> *mut XScreenSaverInfo;
+    fn XScreenSaverQueryInfo(
+        dpy: *mut Display,
+        drawable: Window,
+        saver_info: *mut XScreenSaverInfo,
+    ) -> c_int;
+}
+
+/// Get the pointer device of the given XInput 2 device, `None` if it is not
+/// a pointer. The floating devices are pointers if they have buttons
+unsafe fn pointer_device(info: &XIDeviceInfo) -> Option<PointerDevice> {
+    let role = match info.r#use {
+        XI_MASTER_POINTER => PointerRole::Master,
+        XI_SLAVE_POINTER => PointerRole::Slave,
+        XI_FLOATING_SLAVE => {
+            let classes =
+                std::slice::from_raw_parts(info.classes, info.num_classes.max(0) as usize);
+            if !classes
+                .iter()
+                .any(|class| (**class).r#type == XI_BUTTON_CLASS)
+            {
+                return None;
+            }
+            PointerRole::Floating
+        }
+        _ => return None,
+    };
+    Some(PointerDevice {
+        id: info.deviceid,
+        name: CStr::from_ptr(info.name).to_string_lossy().into_owned(),
+        role,
+        master: (role == PointerRole::Slave).then_some(info.attachment),
+    })
+}
+
+// XInput 2 definitions
+const XI_ALL_DEVICES: c_int = 0;
+const XI_MASTER_POINTER: c_int = 1;
+const XI_SLAVE_POINTER: c_int = 3;
+const XI_FLOATING_SLAVE: c_int = 5;
+const XI_BUTTON_CLASS: c_int = 1;
+#[repr(C)]
+struct XIAnyClassInfo {
+    r#type: c_int,
+    sourceid: c_int,
+}
+
+#[repr(C)]
+struct XIDeviceInfo {
+    deviceid: c_int,
+    name: *mut c_char,
+    r#use: c_int,
+    attachment: c_int,
+    enabled: c_int,
+    num_classes: c_int,
+    classes: *mut *mut XIAnyClassInfo,
+}
+
+// XInput 2 function definitions
+#[link(name = "Xi")]
+extern "C" {
+    fn XIQueryVersion(
+        display: *mut Display,
+        major_version_inout: *mut c_int,
+        minor_version_inout: *mut c_int,
+    ) -> c_int;
+    fn XIQueryDevice(
+        display: *mut Display,
+        deviceid: c_int,
+        ndevices_return: *mut c_int,
+    ) -> *mut XIDeviceInfo;
+    fn XIFreeDeviceInfo(info: *mut XIDeviceInfo);
+    fn XISetClientPointer(display: *mut Display, window: Window, deviceid: c_int) -> c_int;
+    fn XIGetClientPointer(display: *mut Display, window: Window, deviceid: *mut c_int) -> c_int;
+}
+
+// XRandR definitions
+const RR_SCREEN_CHANGE_NOTIFY: c_int = 0;
+const RR_SCREEN_CHANGE_NOTIFY_MASK: c_int = 1 << 0;
+enum XRRScreenConfiguration {}
+type RROutput = c_ulong;
+type RRCrtc = c_ulong;
+type Time = c_ulong;
+// Only the leading fields that are used are defined, these structs are
+// always allocated and freed by XRandR
+#[repr(C)]
+struct XRRScreenResources {
+    timestamp: Time,
+    config_timestamp: Time,
+    ncrtc: c_int,
+    crtcs: *mut RRCrtc,
+}
+
+#[repr(C)]
+struct XRROutputInfo {
+    timestamp: Time,
+    crtc: RRCrtc,
+}
+
+#[repr(C)]
+struct XRRCrtcInfo {
+    timestamp: Time,
+    x: c_int,
+    y: c_int,
+    width: c_uint,
+    height: c_uint,
+}
+
+// XRandR function definitions
+#[link(name = "Xrandr")]
+extern "C" {
+    fn XRRQueryExtension(
+        dpy: *mut Display,
+        event_base_return: *mut c_int,
+        error_base_return: *mut c_int,
+    ) -> c_int;
+    fn XRRSelectInput(dpy: *mut Display, window: Window, mask: c_int);
+    fn XRRUpdateConfiguration(event: *mut XEvent) -> c_int;
+    fn XRRGetOutputPrimary(dpy: *mut Display, window: Window) -> RROutput;
+    fn XRRGetScreenResourcesCurrent(dpy: *mut Display, window: Window) -> *mut XRRScreenResources;
+    fn XRRFreeScreenResources(resources: *mut XRRScreenResources);
+    fn XRRGetOutputInfo(
+        dpy: *mut Display,
+        resources: *mut XRRScreenResources,
+        output: RROutput,
+    ) -> *mut XRROutputInfo;
+    fn XRRFreeOutputInfo(output_info: *mut XRROutputInfo);
+    fn XRRGetCrtcInfo(
+        dpy: *mut Display,
+        resources: *mut XRRScreenResources,
+        crtc: RRCrtc,
+    ) -> *mut XRRCrtcInfo;
+    fn XRRFreeCrtcInfo(crtc_info: *mut XRRCrtcInfo);
+    fn XRRGetScreenInfo(dpy: *mut Display, window: Window) -> *mut XRRScreenConfiguration;
+    fn XRRConfigCurrentRate(config: *mut XRRScreenConfiguration) -> c_short;
+    fn XRRFreeScreenConfigInfo(config: *mut XRRScreenConfiguration);
 }
@@ -0,0 +1,68 @@
+///
+/// This module contains a small seedable random number generator
+/// (splitmix64), it is only meant for humanizing the mouse actions
+///
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Create a generator that is seeded with the current time
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_time() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        Rng::new(seed)
+    }
+
+    /// The system time is not available on wasm32-unknown-unknown, reading it
+    /// panics, so a fixed seed is used instead. `seed_rng` can still be used
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_time() -> Self {
+        Rng::new(0x2545F4914F6CDD1D)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number between `min` (inclusive) and `max` (exclusive)
+    pub fn f64_between(&mut self, min: f64, max: f64) -> f64 {
+        // The top 53 bits fill the mantissa of a number in [0, 1)
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        min + unit * (max - min)
+    }
+
+    /// Get a random duration between `min` and `max` (both inclusive)
+    pub fn duration_between(&mut self, min: Duration, max: Duration) -> Duration {
+        let span = max.saturating_sub(min).as_nanos() as u64;
+        if span == 0 {
+            return min;
+        }
+        min + Duration::from_nanos(self.next_u64() % (span + 1))
+    }
+
+    /// Get a random offset with a magnitude between `min` and `max` (both
+    /// inclusive) and a random sign
+    pub fn offset_between(&mut self, min: u32, max: u32) -> i32 {
+        let span = max.saturating_sub(min) as u64;
+        let magnitude = (min as u64 + self.next_u64() % (span + 1)) as i32;
+        if self.next_u64() >> 63 == 0 {
+            magnitude
+        } else {
+            -magnitude
+        }
+    }
+}
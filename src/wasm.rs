@@ -0,0 +1,120 @@
+///
+/// This module contains a stub mouse manager for the WebAssembly targets
+/// (wasm32), it exists so that code that uses mouce can be compiled for the
+/// browser as well
+///
+/// - Unsupported mouse actions
+///     - All of the actions return `Error::NotImplemented`, a browser does not
+///       allow moving the real cursor or listening to mouse events outside of
+///       the page
+///     - So do the functions that wait, read the clock or start a thread before
+///       their first action, e.g. `dispatch_many` and `schedule`, the main
+///       thread of a browser can not block, `Instant::now` panics and there are
+///       no threads without the `atomics` target feature
+///
+use crate::common::{
+    Action, CallbackId, MouseActions, MouseButton, MouseEvent, MouseState, ScrollDirection,
+};
+use crate::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub struct WasmMouseManager {
+    state: MouseState,
+}
+
+impl WasmMouseManager {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> Box<dyn MouseActions> {
+        Box::new(WasmMouseManager {
+            state: MouseState::default(),
+        })
+    }
+}
+
+impl fmt::Debug for WasmMouseManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WasmMouseManager")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl MouseActions for WasmMouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "wasm-stub"
+    }
+
+    fn supported_buttons(&self) -> &'static [MouseButton] {
+        &[]
+    }
+
+    fn move_to(&self, _x: usize, _y: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn press_button(&self, _button: &MouseButton) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn release_button(&self, _button: &MouseButton) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn scroll_wheel(&self, _direction: &ScrollDirection) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn hook(&mut self, _callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn unhook(&mut self, _callback_id: CallbackId) -> Result<(), Error> {
+        Err(Error::UnhookFailed)
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn click_button_delayed(
+        &self,
+        _button: &MouseButton,
+        _press_delay: Option<Duration>,
+        _release_delay: Option<Duration>,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn execute(&self, _actions: &[Action]) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn sample_positions(
+        &self,
+        _interval: Duration,
+        _count: usize,
+    ) -> Result<Vec<(i32, i32)>, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn dispatch_many(&self, _events: &[(Duration, MouseEvent)], _speed: f32) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn dispatch_at(&self, _event: MouseEvent, _when: Instant) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn schedule(&self, _event: MouseEvent, _when: Instant) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+}
@@ -3,59 +3,127 @@
 /// for the windows operating system
 /// Uses the User32 system library
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::common::{
+    self, CallbackId, CallbackPanicFn, CursorClip, CursorShape, EventKindSet, Modifier, Monitor,
+    MouseActions, MouseButton, MouseEvent, MouseEventFn, MouseEventOnceFn, MouseState, Priority,
+    ScrollDirection, ScrollGranularity,
+};
 use crate::error::Error;
-use std::collections::HashMap;
+use crate::hooks::{self, lock_ignore_poison, Hooks};
+use std::ffi::CStr;
+use std::fmt;
 use std::mem::size_of;
-use std::os::raw::{c_int, c_long, c_short, c_uint, c_ulong, c_ushort};
-use std::ptr::null_mut;
-use std::sync::Mutex;
-use std::thread;
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uint, c_ulong, c_ushort, c_void};
+use std::ptr::{null, null_mut};
+use std::sync::{mpsc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 static mut HOOK: HHook = null_mut();
-type MouseEventFn = Box<dyn Fn(&MouseEvent) + Send>;
-static mut CALLBACKS: Option<Mutex<HashMap<CallbackId, MouseEventFn>>> = None;
+static HOOKS: Mutex<Hooks> = Mutex::new(Hooks::new());
+static DISPLAY_CALLBACKS: Mutex<Vec<Box<dyn Fn() + Send>>> = Mutex::new(Vec::new());
 
 pub struct WindowsMouseManager {
+    state: MouseState,
     callback_counter: CallbackId,
-    is_listening: bool,
+    /// The thread that runs the hook and its message pump
+    listener: Option<PumpThread>,
+    display_watcher: Option<PumpThread>,
+    /// Whether it was created by `output_handle`, the hooks are global so
+    /// they are left to the manager that created it
+    is_output: bool,
+}
+
+/// A thread that runs a message pump, it exits once WM_QUIT is posted to it
+struct PumpThread {
+    thread_id: DWord,
+    handle: JoinHandle<()>,
+}
+
+impl PumpThread {
+    fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+
+    /// End the message pump and wait for the thread to exit
+    fn stop(self) -> Result<(), Error> {
+        unsafe {
+            PostThreadMessageA(self.thread_id, WM_QUIT, 0, null_mut());
+        }
+        hooks::join_with_timeout(vec![self.handle])
+    }
 }
 
 impl WindowsMouseManager {
     #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Box<dyn MouseActions> {
         Box::new(WindowsMouseManager {
+            state: MouseState::default(),
             callback_counter: 0,
-            is_listening: false,
+            listener: None,
+            display_watcher: None,
+            is_output: false,
         })
     }
 
+    /// Warp the cursor to the given position without sending an input
+    fn set_cursor_pos(&self, x: usize, y: usize) -> Result<(), Error> {
+        unsafe {
+            let result = SetCursorPos(x as c_int, y as c_int);
+            if result == 0 {
+                return Err(Error::CustomError("failed to set the cursor position"));
+            }
+        }
+        Ok(())
+    }
+
     fn send_input(&self, event: WindowsMouseEvent, mouse_data: i32) -> Result<(), Error> {
         let (x, y) = self.get_position_raw()?;
-        let mut input = Input {
-            r#type: INPUT_MOUSE,
-            mi: MouseInput {
-                dx: x,
-                dy: y,
-                mouse_data,
-                dw_flags: event as DWord,
-                time: 0,
-                dw_extra_info: unsafe { GetMessageExtraInfo() as *mut c_ulong },
-            },
-        };
+        self.send_inputs(&mut [create_input(x, y, event, mouse_data)])
+    }
 
+    /// Move the cursor by the given offset with a native relative move
+    fn move_by(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        self.send_inputs(&mut [create_input(
+            x_offset as c_long,
+            y_offset as c_long,
+            WindowsMouseEvent::Move,
+            0,
+        )])
+    }
+
+    /// Send all of the given inputs with a single `SendInput` call, so they
+    /// are inserted into the input stream without any interruption
+    fn send_inputs(&self, inputs: &mut [Input]) -> Result<(), Error> {
         unsafe {
-            let result = SendInput(1, &mut input, size_of::<Input>() as i32);
-            // If the function returns 0, it means the input was blocked by another thread
+            let result = SendInput(
+                inputs.len() as c_uint,
+                inputs.as_mut_ptr(),
+                size_of::<Input>() as i32,
+            );
             if result == 0 {
-                return Err(Error::InputIsBlocked);
+                return Err(blocked_input_error());
             }
         }
         Ok(())
     }
 
+    /// Install the low level mouse hook on a dedicated thread
+    ///
+    /// `WH_MOUSE_LL` hooks are called in the context of the thread that installed
+    /// them, and only while that thread is waiting for messages. If the hook was
+    /// installed on the caller's thread, a thread without a message pump (e.g. an
+    /// async runtime worker) would never receive any events, so the listener
+    /// thread installs the hook and runs its own `GetMessage`/`DispatchMessage` loop
     fn start_listener(&mut self) -> Result<(), Error> {
-        thread::spawn(move || {
+        let (tx, rx) = mpsc::channel();
+        let priority = match self.state.listener_priority() {
+            Priority::Normal => THREAD_PRIORITY_NORMAL,
+            Priority::High => THREAD_PRIORITY_HIGHEST,
+            Priority::RealTime => THREAD_PRIORITY_TIME_CRITICAL,
+        };
+
+        let handle = thread::spawn(move || {
             unsafe extern "system" fn low_level_mouse_handler(
                 code: c_int,
                 param: WParam,
@@ -75,37 +143,123 @@ impl WindowsMouseManager {
                     WM_LBUTTONUP => Some(MouseEvent::Release(MouseButton::Left)),
                     WM_MBUTTONUP => Some(MouseEvent::Release(MouseButton::Middle)),
                     WM_RBUTTONUP => Some(MouseEvent::Release(MouseButton::Right)),
+                    // The wheel delta is signed, positive scrolls up and right
                     WM_MOUSEWHEEL => {
-                        let delta = get_delta(lpdata) / WHEEL_DELTA as u16;
-                        match delta {
-                            1 => Some(MouseEvent::Scroll(ScrollDirection::Up)),
-                            _ => Some(MouseEvent::Scroll(ScrollDirection::Down)),
-                        }
+                        common::scroll_event(0, common::wheel_clicks(get_delta(lpdata) as i16))
                     }
                     WM_MOUSEHWHEEL => {
-                        let delta = get_delta(lpdata) / WHEEL_DELTA as u16;
-                        match delta {
-                            1 => Some(MouseEvent::Scroll(ScrollDirection::Right)),
-                            _ => Some(MouseEvent::Scroll(ScrollDirection::Left)),
-                        }
+                        common::scroll_event(common::wheel_clicks(get_delta(lpdata) as i16), 0)
                     }
                     _ => None,
                 };
 
-                if let (Some(event), Some(callbacks)) = (mouse_event, &mut CALLBACKS) {
-                    for callback in callbacks.get_mut().unwrap().values() {
-                        callback(&event);
-                    }
+                if let Some(event) = mouse_event {
+                    HOOKS.lock().unwrap().dispatch(&event);
                 }
 
                 CallNextHookEx(HOOK, code, param, lpdata)
             }
             unsafe {
+                // The hook procedure runs on this thread, so it is the one to raise
+                SetThreadPriority(GetCurrentThread(), priority);
                 HOOK = SetWindowsHookExA(WH_MOUSE_LL, Some(low_level_mouse_handler), null_mut(), 0);
-                GetMessageA(null_mut(), null_mut(), 0, 0);
+                if HOOK.is_null() {
+                    tx.send(Err(Error::CustomError("failed to install the mouse hook")))
+                        .unwrap();
+                    return;
+                }
+                tx.send(Ok(GetCurrentThreadId())).unwrap();
+
+                // GetMessageA returns 0 once WM_QUIT is received and -1 on errors
+                let mut message = std::mem::zeroed::<Msg>();
+                while GetMessageA(&mut message, null_mut(), 0, 0) > 0 {
+                    DispatchMessageA(&message);
+                }
+
+                // Remove the procedure installed in the hook chain
+                UnhookWindowsHookEx(HOOK);
+                HOOK = null_mut();
             }
         });
 
+        let thread_id = rx.recv().unwrap()?;
+        self.listener = Some(PumpThread { thread_id, handle });
+        Ok(())
+    }
+
+    /// Stop the message pump of the listener thread, which removes the hook
+    fn stop_listener(&mut self) -> Result<(), Error> {
+        self.listener.take().map_or(Ok(()), PumpThread::stop)
+    }
+
+    fn start_display_watcher(&mut self) -> Result<(), Error> {
+        let (tx, rx) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            unsafe extern "system" fn window_procedure(
+                hwnd: HWND,
+                message: c_uint,
+                param: WParam,
+                lpdata: LParam,
+            ) -> LResult {
+                if message == WM_DISPLAYCHANGE {
+                    hooks::notify_display_change(&DISPLAY_CALLBACKS);
+                }
+
+                DefWindowProcA(hwnd, message, param, lpdata)
+            }
+
+            unsafe {
+                // WM_DISPLAYCHANGE is only broadcasted to top-level windows, so create
+                // a hidden (not message-only) window that receives it
+                let class_name = c"mouce-display-watcher".as_ptr();
+                let instance = GetModuleHandleA(null());
+                let class = WndClassA {
+                    style: 0,
+                    lpfn_wnd_proc: Some(window_procedure),
+                    cb_cls_extra: 0,
+                    cb_wnd_extra: 0,
+                    h_instance: instance,
+                    h_icon: null_mut(),
+                    h_cursor: null_mut(),
+                    hbr_background: null_mut(),
+                    lpsz_menu_name: null(),
+                    lpsz_class_name: class_name,
+                };
+                RegisterClassA(&class);
+
+                let hwnd = CreateWindowExA(
+                    0,
+                    class_name,
+                    class_name,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    null_mut(),
+                    null_mut(),
+                    instance,
+                    null_mut(),
+                );
+                if hwnd.is_null() {
+                    tx.send(Err(Error::CustomError(
+                        "failed to create the display watcher window",
+                    )))
+                    .unwrap();
+                    return;
+                }
+                tx.send(Ok(GetCurrentThreadId())).unwrap();
+
+                let mut message = std::mem::zeroed::<Msg>();
+                while GetMessageA(&mut message, null_mut(), 0, 0) > 0 {
+                    DispatchMessageA(&message);
+                }
+            }
+        });
+
+        let thread_id = rx.recv().unwrap()?;
+        self.display_watcher = Some(PumpThread { thread_id, handle });
         Ok(())
     }
 
@@ -122,133 +276,810 @@ impl WindowsMouseManager {
         }
         Ok((out.x, out.y))
     }
-}
 
-impl Drop for WindowsMouseManager {
-    fn drop(&mut self) {
+    /// Get the area of the virtual desktop, which spans all of the monitors
+    fn virtual_desktop(&self) -> Result<Monitor, Error> {
         unsafe {
-            if HOOK.is_null() {
-                // Remove the procedure installed in the hook chain
-                UnhookWindowsHookEx(HOOK);
+            let width = GetSystemMetrics(SM_CXVIRTUALSCREEN);
+            let height = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+            if width == 0 || height == 0 {
+                return Err(Error::CustomError("failed to get the virtual desktop size"));
             }
+            Ok(Monitor {
+                x: GetSystemMetrics(SM_XVIRTUALSCREEN),
+                y: GetSystemMetrics(SM_YVIRTUALSCREEN),
+                width: width as usize,
+                height: height as usize,
+            })
         }
     }
 }
 
+impl Drop for WindowsMouseManager {
+    fn drop(&mut self) {
+        // There is no way to report the errors from drop
+        let _ = self.shutdown();
+    }
+}
+
+impl fmt::Debug for WindowsMouseManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WindowsMouseManager")
+            .field("backend", &self.backend_name())
+            .finish_non_exhaustive()
+    }
+}
+
 impl MouseActions for WindowsMouseManager {
+    fn state(&self) -> &MouseState {
+        &self.state
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "windows-sendinput"
+    }
+
+    fn output_handle(&self) -> Result<Box<dyn MouseActions + Send>, Error> {
+        Ok(Box::new(WindowsMouseManager {
+            state: self.state.share(),
+            callback_counter: 0,
+            listener: None,
+            display_watcher: None,
+            is_output: true,
+        }))
+    }
+
     fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0) as usize;
+        if self.state.prefers_relative_moves() {
+            let (from_x, from_y) = self.get_position_raw()?;
+            return self.move_by(x as i32 - from_x as i32, y as i32 - from_y as i32);
+        }
+        self.set_cursor_pos(x, y)
+    }
+
+    fn move_to_silent(&self, x: usize, y: usize) -> Result<(), Error> {
+        let y = common::convert_origin(self, y as i32)?.max(0) as usize;
+        self.set_cursor_pos(x, y)
+    }
+
+    fn move_to_raw_normalized(&self, nx: u16, ny: u16) -> Result<(), Error> {
+        self.send_inputs(&mut [raw_normalized_input(nx, ny)])
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y) = self.get_position_raw()?;
+        Ok((x, common::convert_origin(self, y)?))
+    }
+
+    fn move_to_normalized(&self, x: f64, y: f64) -> Result<(), Error> {
+        self.send_inputs(&mut [create_input(
+            common::to_normalized_absolute(x) as c_long,
+            common::to_normalized_absolute(y) as c_long,
+            WindowsMouseEvent::VirtualDeskMove,
+            0,
+        )])
+    }
+
+    fn get_display_size(&self) -> Result<(usize, usize), Error> {
         unsafe {
-            let result = SetCursorPos(x as c_int, y as c_int);
-            if result == 0 {
-                return Err(Error::CustomError("failed to set the cursor position"));
+            let width = GetSystemMetrics(SM_CXSCREEN);
+            let height = GetSystemMetrics(SM_CYSCREEN);
+            // GetSystemMetrics returns 0 if it fails
+            if width == 0 || height == 0 {
+                return Err(Error::CustomError("failed to get the display size"));
             }
+            Ok((width as usize, height as usize))
+        }
+    }
+
+    fn atomic(&self, events: &[MouseEvent]) -> Result<(), Error> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        if events
+            .iter()
+            .any(|event| matches!(event, MouseEvent::Dial(_)))
+        {
+            return Err(Error::NotImplemented);
+        }
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| match *event {
+                MouseEvent::Press(button) => MouseEvent::Press(self.state.map_button(&button)),
+                MouseEvent::Release(button) => MouseEvent::Release(self.state.map_button(&button)),
+                event => event,
+            })
+            .collect();
+        self.send_inputs(&mut batch_inputs(&events, &self.virtual_desktop()?))
+    }
+
+    fn set_dpi_awareness(&mut self) -> Result<(), Error> {
+        let set_awareness: SetProcessDpiAwarenessContextFn = unsafe {
+            std::mem::transmute(
+                system_function(c"user32.dll", c"SetProcessDpiAwarenessContext")
+                    .ok_or(Error::NotImplemented)?,
+            )
+        };
+        let result = unsafe { set_awareness(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+        if result == 0 {
+            return Err(Error::CustomError(
+                "failed to set the DPI awareness, it may already be set",
+            ));
         }
         Ok(())
     }
 
-    fn get_position(&self) -> Result<(i32, i32), Error> {
-        match self.get_position_raw() {
-            Ok((x, y)) => Ok((x, y)),
-            Err(e) => Err(e),
+    fn get_dpi_for_point(&self, x: i32, y: i32) -> Result<u32, Error> {
+        let get_dpi: GetDpiForMonitorFn = unsafe {
+            std::mem::transmute(
+                system_function(c"shcore.dll", c"GetDpiForMonitor").ok_or(Error::NotImplemented)?,
+            )
+        };
+        let mut dpi_x: c_uint = 0;
+        let mut dpi_y: c_uint = 0;
+        unsafe {
+            let monitor = MonitorFromPoint(
+                Point {
+                    x: x as c_long,
+                    y: y as c_long,
+                },
+                MONITOR_DEFAULTTONEAREST,
+            );
+            if get_dpi(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) != 0 {
+                return Err(Error::CustomError("failed to get the DPI of the monitor"));
+            }
         }
+        Ok(dpi_x)
     }
 
-    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let event = match button {
-            MouseButton::Left => WindowsMouseEvent::LeftDown,
-            MouseButton::Middle => WindowsMouseEvent::MiddleDown,
-            MouseButton::Right => WindowsMouseEvent::RightDown,
+    fn display_refresh_hz(&self) -> Result<f32, Error> {
+        let mut mode: DevModeA = unsafe { std::mem::zeroed() };
+        mode.dm_size = size_of::<DevModeA>() as Word;
+        if unsafe { EnumDisplaySettingsA(null(), ENUM_CURRENT_SETTINGS, &mut mode) } == 0 {
+            return Err(Error::CustomError("failed to get the display settings"));
+        }
+        // 0 and 1 stand for the default refresh rate of the hardware
+        if mode.dm_display_frequency <= 1 {
+            return Err(Error::NotImplemented);
+        }
+        Ok(mode.dm_display_frequency as f32)
+    }
+
+    fn get_primary_monitor(&self) -> Result<Monitor, Error> {
+        // The origin of the virtual screen is always the top-left corner
+        // of the primary monitor
+        let (width, height) = self.get_display_size()?;
+        Ok(Monitor {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        })
+    }
+
+    fn get_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        unsafe extern "system" fn add_monitor(
+            _monitor: *mut c_void,
+            _hdc: *mut c_void,
+            rect: *mut Rect,
+            data: LParam,
+        ) -> c_int {
+            let monitors = &mut *(data as *mut Vec<Monitor>);
+            let rect = &*rect;
+            monitors.push(Monitor {
+                x: rect.left as i32,
+                y: rect.top as i32,
+                width: (rect.right - rect.left) as usize,
+                height: (rect.bottom - rect.top) as usize,
+            });
+            // Continue the enumeration
+            1
+        }
+
+        let mut monitors: Vec<Monitor> = Vec::new();
+        let result = unsafe {
+            EnumDisplayMonitors(
+                null_mut(),
+                null(),
+                Some(add_monitor),
+                &mut monitors as *mut Vec<Monitor> as LParam,
+            )
         };
+        if result == 0 {
+            return Err(Error::CustomError("failed to enumerate the monitors"));
+        }
+        Ok(monitors)
+    }
 
-        self.send_input(event, 0)
+    fn double_click_time(&self) -> Result<Duration, Error> {
+        let millis = unsafe { GetDoubleClickTime() };
+        Ok(Duration::from_millis(millis as u64))
     }
 
-    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let event = match button {
-            MouseButton::Left => WindowsMouseEvent::LeftUp,
-            MouseButton::Middle => WindowsMouseEvent::MiddleUp,
-            MouseButton::Right => WindowsMouseEvent::RightUp,
+    fn double_click_distance(&self) -> Result<u32, Error> {
+        let width = unsafe { GetSystemMetrics(SM_CXDOUBLECLK) };
+        // GetSystemMetrics returns 0 if it fails
+        if width == 0 {
+            return Err(Error::CustomError(
+                "failed to get the double click distance",
+            ));
+        }
+        Ok(width as u32)
+    }
+
+    fn drag_threshold(&self) -> Result<(u32, u32), Error> {
+        let (width, height) = unsafe { (GetSystemMetrics(SM_CXDRAG), GetSystemMetrics(SM_CYDRAG)) };
+        if width == 0 || height == 0 {
+            return Err(Error::CustomError("failed to get the drag threshold"));
+        }
+        Ok((width as u32, height as u32))
+    }
+
+    fn get_active_window_origin(&self) -> Result<(i32, i32), Error> {
+        unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return Err(Error::CustomError("there is no foreground window"));
+            }
+            // The client area of a window starts at (0, 0) in its client coordinates
+            let mut origin = Point { x: 0, y: 0 };
+            if ClientToScreen(window, &mut origin) == 0 {
+                return Err(Error::CustomError(
+                    "failed to get the foreground window origin",
+                ));
+            }
+            Ok((origin.x as i32, origin.y as i32))
+        }
+    }
+
+    fn get_cursor_shape(&self) -> Result<CursorShape, Error> {
+        let mut info = CursorInfo {
+            cb_size: size_of::<CursorInfo>() as DWord,
+            flags: 0,
+            h_cursor: null_mut(),
+            pt_screen_pos: Point { x: 0, y: 0 },
+        };
+        unsafe {
+            if GetCursorInfo(&mut info) == 0 {
+                return Err(Error::CustomError("failed to get the cursor info"));
+            }
+            // The shared standard cursors always have the same handle, so they
+            // can be compared with the handles that are loaded here
+            let shapes = [
+                (IDC_ARROW, CursorShape::Arrow),
+                (IDC_WAIT, CursorShape::Wait),
+                (IDC_APPSTARTING, CursorShape::Wait),
+                (IDC_IBEAM, CursorShape::IBeam),
+                (IDC_HAND, CursorShape::Hand),
+            ];
+            for (id, shape) in shapes {
+                if LoadCursorA(null_mut(), id as *const c_char) == info.h_cursor {
+                    return Ok(shape);
+                }
+            }
+        }
+        Ok(CursorShape::Unknown)
+    }
+
+    fn confine_cursor(
+        &self,
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+    ) -> Result<CursorClip, Error> {
+        let rect = Rect {
+            left: x as c_long,
+            top: y as c_long,
+            right: x as c_long + width as c_long,
+            bottom: y as c_long + height as c_long,
+        };
+        unsafe {
+            if ClipCursor(&rect) == 0 {
+                return Err(Error::CustomError("failed to confine the cursor"));
+            }
+        }
+        // A null rectangle lets the cursor move anywhere on the screen again
+        Ok(CursorClip::new(|| unsafe {
+            ClipCursor(null());
+        }))
+    }
+
+    fn long_press(
+        &self,
+        button: &MouseButton,
+        hold: Duration,
+        keep_still: bool,
+    ) -> Result<(), Error> {
+        let clip = if keep_still {
+            let (x, y) = self.get_position_raw()?;
+            Some(self.confine_cursor(x as i32, y as i32, 1, 1)?)
+        } else {
+            None
         };
+        self.press_button(button)?;
+        self.state.sleep_precisely(hold);
+        let released = self.release_button(button);
+        drop(clip);
+        released
+    }
+
+    fn is_pointer_grabbed(&self) -> Result<bool, Error> {
+        let mut clip = Rect {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            if GetClipCursor(&mut clip) == 0 {
+                return Err(Error::CustomError("failed to get the cursor clip"));
+            }
+        }
+        Ok(!clip_covers(&clip, &self.virtual_desktop()?))
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (event, _) = button_events(&self.state.map_button(button));
+        self.send_input(event, 0)?;
+        if let Ok((x, y)) = self.get_position() {
+            self.state.remember_click(x, y);
+        }
+        Ok(())
+    }
 
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (_, event) = button_events(&self.state.map_button(button));
         self.send_input(event, 0)
     }
 
     fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+        // Send the press and release events in a single batch, instead of
+        // making two separate `SendInput` calls
+        let (x, y) = self.get_position_raw()?;
+        let (press, release) = button_events(&self.state.map_button(button));
+        self.send_inputs(&mut [create_input(x, y, press, 0), create_input(x, y, release, 0)])?;
+        self.state
+            .remember_click(x as i32, common::convert_origin(self, y as i32)?);
+        Ok(())
     }
 
     fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
-        let (event, scroll_amount) = match direction {
-            ScrollDirection::Up => (WindowsMouseEvent::Wheel, 150),
-            ScrollDirection::Down => (WindowsMouseEvent::Wheel, -150),
-            ScrollDirection::Right => (WindowsMouseEvent::HWheel, 150),
-            ScrollDirection::Left => (WindowsMouseEvent::HWheel, -150),
-        };
+        let (event, scroll_amount) = scroll_input(direction);
         self.send_input(event, scroll_amount)
     }
 
-    fn hook(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            self.start_listener()?;
-            self.is_listening = true;
+    fn scroll_detents(&self, direction: &ScrollDirection, detents: i32) -> Result<(), Error> {
+        // A wheel event without any rotation would still be delivered
+        if detents == 0 {
+            return Ok(());
         }
+        let (event, scroll_amount) = detent_input(direction, detents);
+        self.send_input(event, scroll_amount)
+    }
 
-        let id = self.callback_counter;
+    fn hold_modifiers(&self, modifiers: &[Modifier]) -> Result<(), Error> {
+        let mut held = self.state.held_modifiers();
+        let changes: Vec<(Modifier, bool)> = held
+            .iter()
+            .filter(|modifier| !modifiers.contains(modifier))
+            .map(|modifier| (*modifier, false))
+            .chain(
+                modifiers
+                    .iter()
+                    .filter(|modifier| !held.contains(modifier))
+                    .map(|modifier| (*modifier, true)),
+            )
+            .collect();
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut inputs: Vec<KeyboardInput> = changes
+            .iter()
+            .map(|(modifier, is_press)| create_keyboard_input(modifier, *is_press))
+            .collect();
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as c_uint,
+                inputs.as_mut_ptr() as LPInput,
+                size_of::<KeyboardInput>() as i32,
+            )
+        } as usize;
+        // Only the inputs before a blocked one are inserted, remember which
+        // keys are down now so a later call releases them
+        for (modifier, is_press) in &changes[..sent.min(changes.len())] {
+            if *is_press {
+                held.push(*modifier);
+            } else {
+                held.retain(|held| held != modifier);
+            }
+        }
+        self.state.set_held_modifiers(&held);
+        if sent < changes.len() {
+            return Err(blocked_input_error());
+        }
+        Ok(())
+    }
+
+    fn get_work_area(&self) -> Result<Monitor, Error> {
+        let mut area = Rect {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
         unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => {
-                    callbacks.get_mut().unwrap().insert(id, callback);
-                }
-                None => {
-                    initialize_callbacks();
-                    return self.hook(callback);
-                }
+            if SystemParametersInfoA(SPI_GETWORKAREA, 0, &mut area as *mut Rect as *mut c_void, 0)
+                == 0
+            {
+                return Err(Error::CustomError("failed to get the work area"));
+            }
+        }
+        // The right and bottom edges are exclusive
+        Ok(Monitor {
+            x: area.left as i32,
+            y: area.top as i32,
+            width: (area.right - area.left).max(0) as usize,
+            height: (area.bottom - area.top).max(0) as usize,
+        })
+    }
+
+    fn scroll_granularity(&self) -> Result<ScrollGranularity, Error> {
+        let mut lines: c_uint = 0;
+        unsafe {
+            if SystemParametersInfoA(
+                SPI_GETWHEELSCROLLLINES,
+                0,
+                &mut lines as *mut c_uint as *mut c_void,
+                0,
+            ) == 0
+            {
+                return Err(Error::CustomError("failed to get the wheel scroll lines"));
             }
         }
+        if lines == WHEEL_PAGESCROLL {
+            return Ok(ScrollGranularity::Page);
+        }
+        // `scroll_wheel` sends 150 units, which is more than a single notch
+        Ok(ScrollGranularity::Lines(
+            lines as f32 * 150.0 / WHEEL_DELTA as f32,
+        ))
+    }
+
+    fn high_res_scroll_available(&self) -> bool {
+        // The wheel deltas are not limited to multiples of WHEEL_DELTA
+        true
+    }
+
+    fn set_mark_synthetic(&mut self, mark: bool) -> Result<(), Error> {
+        // SendInput always sets the LLMHF_INJECTED flag, it can not be cleared
+        if mark {
+            Ok(())
+        } else {
+            Err(Error::NotImplemented)
+        }
+    }
+
+    fn hook(&mut self, callback: MouseEventFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn listener_sources(&self) -> Vec<String> {
+        match self.listener {
+            Some(_) => vec!["WH_MOUSE_LL".to_string()],
+            None => Vec::new(),
+        }
+    }
+
+    fn hook_once(&mut self, callback: MouseEventOnceFn) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert_once(id, callback);
+        self.callback_counter += 1;
+        Ok(id)
+    }
+
+    fn hook_filtered_kinds(
+        &mut self,
+        kinds: EventKindSet,
+        callback: MouseEventFn,
+    ) -> Result<CallbackId, Error> {
+        self.ensure_listener()?;
+
+        let id = self.callback_counter;
+        HOOKS.lock().unwrap().insert_filtered(id, kinds, callback);
         self.callback_counter += 1;
         Ok(id)
     }
 
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => match callbacks.get_mut().unwrap().remove(&callback_id) {
-                    Some(_) => Ok(()),
-                    None => Err(Error::UnhookFailed),
-                },
-                None => {
-                    initialize_callbacks();
-                    self.unhook(callback_id)
-                }
+        self.state.forget_label(callback_id);
+        // The callback is dropped once the lock is released, see `RemovedCallbacks`
+        let removed = HOOKS.lock().unwrap().remove(callback_id);
+        removed.map(drop)
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.state.forget_labels();
+        let removed = lock_ignore_poison(&HOOKS).clear();
+        drop(removed);
+        // Nothing is listening anymore, let the listener thread exit
+        self.stop_listener()
+    }
+
+    fn seed_position(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        HOOKS.lock().unwrap().seed_position(x, y);
+        Ok(())
+    }
+
+    fn get_position_from_hook(&self) -> Result<(i32, i32), Error> {
+        if self.listener.is_none() {
+            return Err(Error::CustomError(
+                "the position is only tracked while listening",
+            ));
+        }
+        HOOKS.lock().unwrap().position()
+    }
+
+    fn hook_is_active(&self, callback_id: CallbackId) -> bool {
+        self.listener.as_ref().is_some_and(PumpThread::is_running)
+            && HOOKS.lock().unwrap().contains(callback_id)
+    }
+
+    fn last_event_at(&self) -> Option<Instant> {
+        HOOKS.lock().unwrap().last_event_at()
+    }
+
+    fn ensure_listener(&mut self) -> Result<(), Error> {
+        if self.listener.is_none() {
+            self.start_listener()?;
+        }
+        Ok(())
+    }
+
+    fn is_mouse_active(&mut self, within: Duration) -> Result<bool, Error> {
+        // The listener only sees the mouse, the system also counts the keyboard
+        if self.listener.as_ref().is_some_and(PumpThread::is_running) {
+            return Ok(self
+                .last_event_at()
+                .is_some_and(|at| at.elapsed() <= within));
+        }
+        Ok(self.system_idle_time()? <= within)
+    }
+
+    fn system_idle_time(&self) -> Result<Duration, Error> {
+        let mut info = LastInputInfo {
+            cb_size: size_of::<LastInputInfo>() as c_uint,
+            dw_time: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return Err(Error::CustomError(
+                "failed to get the time of the last input",
+            ));
+        }
+        // The tick count wraps around every 49.7 days
+        let idle = unsafe { GetTickCount() }.wrapping_sub(info.dw_time);
+        Ok(Duration::from_millis(idle as u64))
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        let _ = common::release_modifiers(self);
+        // Stop the display watcher even if the listener does not exit in time
+        let listener = if self.is_output {
+            self.stop_listener()
+        } else {
+            self.unhook_all()
+        };
+        let display_watcher = match self.display_watcher.take() {
+            // The display callbacks are global, so leave them to the manager that added them
+            Some(display_watcher) => {
+                lock_ignore_poison(&DISPLAY_CALLBACKS).clear();
+                display_watcher.stop()
             }
+            None => Ok(()),
+        };
+        listener.and(display_watcher)
+    }
+
+    fn enable_event_history(&mut self, capacity: usize) -> Result<(), Error> {
+        self.ensure_listener()?;
+
+        HOOKS.lock().unwrap().enable_history(capacity);
+        Ok(())
+    }
+
+    fn event_history(&self) -> Vec<MouseEvent> {
+        HOOKS.lock().unwrap().history()
+    }
+
+    fn on_callback_panic(&mut self, handler: CallbackPanicFn) -> Result<(), Error> {
+        HOOKS.lock().unwrap().set_panic_handler(handler);
+        Ok(())
+    }
+
+    fn on_display_change(&mut self, callback: Box<dyn Fn() + Send>) -> Result<(), Error> {
+        if self.display_watcher.is_none() {
+            self.start_display_watcher()?;
         }
+
+        DISPLAY_CALLBACKS.lock().unwrap().push(callback);
+        Ok(())
     }
+}
 
-    fn unhook_all(&mut self) -> Result<(), Error> {
-        unsafe {
-            match &mut CALLBACKS {
-                Some(callbacks) => {
-                    callbacks.get_mut().unwrap().clear();
+fn create_input(x: c_long, y: c_long, event: WindowsMouseEvent, mouse_data: i32) -> Input {
+    Input {
+        r#type: INPUT_MOUSE,
+        mi: MouseInput {
+            dx: x,
+            dy: y,
+            mouse_data,
+            dw_flags: event as DWord,
+            time: 0,
+            dw_extra_info: unsafe { GetMessageExtraInfo() as *mut c_ulong },
+        },
+    }
+}
+
+/// Create a press or a release of the key of the given modifier
+fn create_keyboard_input(modifier: &Modifier, is_press: bool) -> KeyboardInput {
+    let virtual_key = match modifier {
+        Modifier::Shift => VK_SHIFT,
+        Modifier::Control => VK_CONTROL,
+        Modifier::Alt => VK_MENU,
+        Modifier::Meta => VK_LWIN,
+    };
+    KeyboardInput {
+        r#type: INPUT_KEYBOARD,
+        ki: KeybdInput {
+            w_vk: virtual_key,
+            w_scan: 0,
+            dw_flags: if is_press { 0 } else { KEYEVENTF_KEYUP },
+            time: 0,
+            dw_extra_info: unsafe { GetMessageExtraInfo() as *mut c_ulong },
+        },
+        _padding: [0; size_of::<MouseInput>() - size_of::<KeybdInput>()],
+    }
+}
+
+/// Get the error of a `SendInput` call that did not insert all of the inputs
+///
+/// The input was blocked, there is no foreground window while the session is
+/// locked or the secure desktop (e.g. UAC) is shown, which no process can
+/// inject into
+fn blocked_input_error() -> Error {
+    if unsafe { GetForegroundWindow() }.is_null() {
+        Error::DesktopLocked
+    } else {
+        Error::InputIsBlocked
+    }
+}
+
+/// Create an absolute move to the given normalized coordinates of the virtual desktop
+fn raw_normalized_input(nx: u16, ny: u16) -> Input {
+    create_input(
+        nx as c_long,
+        ny as c_long,
+        WindowsMouseEvent::VirtualDeskMove,
+        0,
+    )
+}
+
+/// Get the wheel event and the amount of a single click towards the given direction
+fn scroll_input(direction: &ScrollDirection) -> (WindowsMouseEvent, i32) {
+    match direction {
+        ScrollDirection::Up => (WindowsMouseEvent::Wheel, SCROLL_CLICK),
+        ScrollDirection::Down => (WindowsMouseEvent::Wheel, -SCROLL_CLICK),
+        ScrollDirection::Right => (WindowsMouseEvent::HWheel, SCROLL_CLICK),
+        ScrollDirection::Left => (WindowsMouseEvent::HWheel, -SCROLL_CLICK),
+    }
+}
+
+/// Get the wheel event and the amount of the given number of detents, i.e.
+/// multiples of `WHEEL_DELTA`, towards the given direction
+fn detent_input(direction: &ScrollDirection, detents: i32) -> (WindowsMouseEvent, i32) {
+    let (event, click) = scroll_input(direction);
+    (
+        event,
+        click.signum() * detents.saturating_mul(WHEEL_DELTA as i32),
+    )
+}
+
+/// Build the inputs of a single `SendInput` batch for the given events, the
+/// absolute moves are normalized to the given virtual desktop
+fn batch_inputs(events: &[MouseEvent], virtual_desktop: &Monitor) -> Vec<Input> {
+    let normalize = |coordinate: i32, origin: i32, length: usize| {
+        let fraction = (coordinate - origin) as f64 / length.saturating_sub(1).max(1) as f64;
+        common::to_normalized_absolute(fraction) as c_long
+    };
+    let mut inputs = Vec::with_capacity(events.len());
+    for event in events {
+        match *event {
+            MouseEvent::RelativeMove(x_offset, y_offset) => inputs.push(create_input(
+                x_offset as c_long,
+                y_offset as c_long,
+                WindowsMouseEvent::Move,
+                0,
+            )),
+            MouseEvent::AbsoluteMove(x, y) => inputs.push(create_input(
+                normalize(x, virtual_desktop.x, virtual_desktop.width),
+                normalize(y, virtual_desktop.y, virtual_desktop.height),
+                WindowsMouseEvent::VirtualDeskMove,
+                0,
+            )),
+            MouseEvent::Press(button) => {
+                inputs.push(create_input(0, 0, button_events(&button).0, 0))
+            }
+            MouseEvent::Release(button) => {
+                inputs.push(create_input(0, 0, button_events(&button).1, 0))
+            }
+            MouseEvent::Scroll(direction) => {
+                let (event, scroll_amount) = scroll_input(&direction);
+                inputs.push(create_input(0, 0, event, scroll_amount));
+            }
+            MouseEvent::ScrollDelta(dx, dy) => {
+                if dy != 0 {
+                    inputs.push(create_input(
+                        0,
+                        0,
+                        WindowsMouseEvent::Wheel,
+                        dy * SCROLL_CLICK,
+                    ));
                 }
-                None => {
-                    initialize_callbacks();
-                    return self.unhook_all();
+                if dx != 0 {
+                    inputs.push(create_input(
+                        0,
+                        0,
+                        WindowsMouseEvent::HWheel,
+                        dx * SCROLL_CLICK,
+                    ));
                 }
             }
+            // `atomic` rejects the batches with a dial, it can not be sent
+            MouseEvent::Dial(_) => {}
         }
-        Ok(())
     }
+    inputs
 }
 
-fn initialize_callbacks() {
+/// Check whether the cursor clip rectangle lets the cursor reach the whole
+/// virtual desktop, the right and bottom edges of the clip are exclusive
+fn clip_covers(clip: &Rect, virtual_desktop: &Monitor) -> bool {
+    clip.left <= virtual_desktop.x as c_long
+        && clip.top <= virtual_desktop.y as c_long
+        && clip.right >= virtual_desktop.x as c_long + virtual_desktop.width as c_long
+        && clip.bottom >= virtual_desktop.y as c_long + virtual_desktop.height as c_long
+}
+
+/// Look the given function of a system library up, `None` if the library or
+/// the function does not exist on this version of Windows
+fn system_function(library: &CStr, function: &CStr) -> Option<*mut c_void> {
     unsafe {
-        match CALLBACKS {
-            Some(_) => {}
-            None => {
-                CALLBACKS = Some(Mutex::new(HashMap::new()));
-            }
+        let module = LoadLibraryA(library.as_ptr());
+        if module.is_null() {
+            return None;
         }
+        let address = GetProcAddress(module, function.as_ptr());
+        (!address.is_null()).then_some(address)
+    }
+}
+
+/// Get the press and release events of the given button
+fn button_events(button: &MouseButton) -> (WindowsMouseEvent, WindowsMouseEvent) {
+    match button {
+        MouseButton::Left => (WindowsMouseEvent::LeftDown, WindowsMouseEvent::LeftUp),
+        MouseButton::Middle => (WindowsMouseEvent::MiddleDown, WindowsMouseEvent::MiddleUp),
+        MouseButton::Right => (WindowsMouseEvent::RightDown, WindowsMouseEvent::RightUp),
     }
 }
 
@@ -273,9 +1104,35 @@ type HInstance = *mut HInstance__;
 type HookProc =
     Option<unsafe extern "system" fn(code: c_int, w_param: WParam, l_param: LParam) -> LResult>;
 type LPMsg = *mut Msg;
+// The functions that are only available on the newer versions of Windows,
+// they are looked up at runtime with `system_function`
+type SetProcessDpiAwarenessContextFn = unsafe extern "system" fn(value: *mut c_void) -> c_int;
+type GetDpiForMonitorFn = unsafe extern "system" fn(
+    hmonitor: *mut c_void,
+    dpi_type: c_int,
+    dpi_x: *mut c_uint,
+    dpi_y: *mut c_uint,
+) -> c_int;
+type WndProc = Option<
+    unsafe extern "system" fn(
+        hwnd: HWND,
+        message: c_uint,
+        w_param: WParam,
+        l_param: LParam,
+    ) -> LResult,
+>;
 #[allow(clippy::upper_case_acronyms)]
 type HWND = *mut HWND__;
 type Word = c_ushort;
+type HCursor = *mut c_void;
+type MonitorEnumProc = Option<
+    unsafe extern "system" fn(
+        monitor: *mut c_void,
+        hdc: *mut c_void,
+        rect: *mut Rect,
+        data: LParam,
+    ) -> c_int,
+>;
 const WM_MOUSEMOVE: c_uint = 0x0200;
 const WM_LBUTTONDOWN: c_uint = 0x0201;
 const WM_LBUTTONUP: c_uint = 0x0202;
@@ -285,12 +1142,46 @@ const WM_MBUTTONDOWN: c_uint = 0x0207;
 const WM_MBUTTONUP: c_uint = 0x0208;
 const WM_MOUSEWHEEL: c_uint = 0x020A;
 const WM_MOUSEHWHEEL: c_uint = 0x020E;
+const WM_DISPLAYCHANGE: c_uint = 0x007E;
+const WM_QUIT: c_uint = 0x0012;
+const THREAD_PRIORITY_NORMAL: c_int = 0;
+const THREAD_PRIORITY_HIGHEST: c_int = 2;
+const THREAD_PRIORITY_TIME_CRITICAL: c_int = 15;
+const MONITOR_DEFAULTTONEAREST: DWord = 2;
+const MDT_EFFECTIVE_DPI: c_int = 0;
+const DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2: *mut c_void = -4isize as *mut c_void;
+/// The wheel amount of a single `scroll_wheel` click
+const SCROLL_CLICK: i32 = 150;
+const VK_SHIFT: c_ushort = 0x10;
+const VK_CONTROL: c_ushort = 0x11;
+const VK_MENU: c_ushort = 0x12;
+const VK_LWIN: c_ushort = 0x5B;
+const KEYEVENTF_KEYUP: DWord = 0x0002;
+const ENUM_CURRENT_SETTINGS: DWord = DWord::MAX;
+const SM_CXSCREEN: c_int = 0;
+const SM_CYSCREEN: c_int = 1;
+const SM_CXDOUBLECLK: c_int = 36;
+const SM_CXDRAG: c_int = 68;
+const SM_CYDRAG: c_int = 69;
+const SM_XVIRTUALSCREEN: c_int = 76;
+const SM_YVIRTUALSCREEN: c_int = 77;
+const SM_CXVIRTUALSCREEN: c_int = 78;
+const SM_CYVIRTUALSCREEN: c_int = 79;
 const WHEEL_DELTA: c_short = 120;
+const WHEEL_PAGESCROLL: c_uint = c_uint::MAX;
+const SPI_GETWHEELSCROLLLINES: c_uint = 0x0068;
+const SPI_GETWORKAREA: c_uint = 0x0030;
 const WH_MOUSE_LL: c_int = 14;
 enum Hhook__ {}
 enum HInstance__ {}
 enum HWND__ {}
 const INPUT_MOUSE: DWord = 0;
+const INPUT_KEYBOARD: DWord = 1;
+const IDC_ARROW: usize = 32512;
+const IDC_IBEAM: usize = 32513;
+const IDC_WAIT: usize = 32514;
+const IDC_HAND: usize = 32649;
+const IDC_APPSTARTING: usize = 32650;
 #[repr(C)]
 struct MouseInput {
     dx: c_long,
@@ -306,13 +1197,70 @@ struct Input {
     mi: MouseInput,
 }
 #[repr(C)]
+struct KeybdInput {
+    w_vk: c_ushort,
+    w_scan: c_ushort,
+    dw_flags: DWord,
+    time: DWord,
+    dw_extra_info: *mut c_ulong,
+}
+/// An `INPUT` with the keyboard member of the union, padded to the size of
+/// the mouse member so `SendInput` takes it like `Input`
+#[repr(C)]
+struct KeyboardInput {
+    r#type: DWord,
+    ki: KeybdInput,
+    _padding: [u8; size_of::<MouseInput>() - size_of::<KeybdInput>()],
+}
+#[repr(C)]
 #[derive(Clone, Copy)]
 struct Point {
     x: c_long,
     y: c_long,
 }
 #[repr(C)]
+struct Rect {
+    left: c_long,
+    top: c_long,
+    right: c_long,
+    bottom: c_long,
+}
+/// The unions and the fields after the refresh rate are only kept for the layout
+#[allow(dead_code)]
+#[repr(C)]
+struct DevModeA {
+    dm_device_name: [c_char; 32],
+    dm_spec_version: Word,
+    dm_driver_version: Word,
+    dm_size: Word,
+    dm_driver_extra: Word,
+    dm_fields: DWord,
+    dm_position: [u8; 16],
+    dm_color: [c_short; 5],
+    dm_form_name: [c_char; 32],
+    dm_log_pixels: Word,
+    dm_bits_per_pel: DWord,
+    dm_pels_width: DWord,
+    dm_pels_height: DWord,
+    dm_display_flags: DWord,
+    dm_display_frequency: DWord,
+    dm_reserved: [DWord; 8],
+}
+#[repr(C)]
+struct LastInputInfo {
+    cb_size: c_uint,
+    dw_time: DWord,
+}
+#[repr(C)]
+struct CursorInfo {
+    cb_size: DWord,
+    flags: DWord,
+    h_cursor: HCursor,
+    pt_screen_pos: Point,
+}
+#[repr(C)]
 enum WindowsMouseEvent {
+    Move = 0x0001,
     LeftDown = 0x0002,
     LeftUp = 0x0004,
     RightDown = 0x0008,
@@ -321,6 +1269,8 @@ enum WindowsMouseEvent {
     MiddleUp = 0x0040,
     Wheel = 0x0800,
     HWheel = 0x01000,
+    /// MOUSEEVENTF_MOVE | MOUSEEVENTF_VIRTUALDESK | MOUSEEVENTF_ABSOLUTE
+    VirtualDeskMove = 0xC001,
 }
 
 #[repr(C)]
@@ -333,6 +1283,20 @@ struct Msg {
     pt: Point,
 }
 
+#[repr(C)]
+struct WndClassA {
+    style: c_uint,
+    lpfn_wnd_proc: WndProc,
+    cb_cls_extra: c_int,
+    cb_wnd_extra: c_int,
+    h_instance: HInstance,
+    h_icon: *mut c_void,
+    h_cursor: *mut c_void,
+    hbr_background: *mut c_void,
+    lpsz_menu_name: *const c_char,
+    lpsz_class_name: *const c_char,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 struct MSLLHookStruct {
@@ -348,6 +1312,33 @@ struct MSLLHookStruct {
 extern "system" {
     fn SetCursorPos(x: c_int, y: c_int) -> c_int;
     fn GetCursorPos(lp_point: *mut Point) -> c_int;
+    fn GetSystemMetrics(n_index: c_int) -> c_int;
+    fn ClipCursor(lp_rect: *const Rect) -> c_int;
+    fn GetClipCursor(lp_rect: *mut Rect) -> c_int;
+    fn SystemParametersInfoA(
+        ui_action: c_uint,
+        ui_param: c_uint,
+        pv_param: *mut c_void,
+        f_win_ini: c_uint,
+    ) -> c_int;
+    fn GetDoubleClickTime() -> c_uint;
+    fn GetForegroundWindow() -> HWND;
+    fn ClientToScreen(h_wnd: HWND, lp_point: *mut Point) -> c_int;
+    fn EnumDisplayMonitors(
+        hdc: *mut c_void,
+        lprc_clip: *const Rect,
+        lpfn_enum: MonitorEnumProc,
+        dw_data: LParam,
+    ) -> c_int;
+    fn GetCursorInfo(pci: *mut CursorInfo) -> c_int;
+    fn EnumDisplaySettingsA(
+        lpsz_device_name: *const c_char,
+        i_mode_num: DWord,
+        lp_dev_mode: *mut DevModeA,
+    ) -> c_int;
+    fn MonitorFromPoint(pt: Point, dw_flags: DWord) -> *mut c_void;
+    fn GetLastInputInfo(plii: *mut LastInputInfo) -> c_int;
+    fn LoadCursorA(h_instance: HInstance, lp_cursor_name: *const c_char) -> HCursor;
     fn SendInput(c_inputs: c_uint, p_inputs: LPInput, cb_size: c_int) -> c_uint;
     fn GetMessageExtraInfo() -> LParam;
     fn SetWindowsHookExA(
@@ -362,6 +1353,159 @@ extern "system" {
         h_wnd: HWND,
         w_msg_filter_min: c_uint,
         w_msg_filter_max: c_uint,
-    ) -> bool;
+    ) -> c_int;
+    fn PostThreadMessageA(id_thread: DWord, msg: c_uint, w_param: WParam, l_param: LParam)
+        -> c_int;
     fn UnhookWindowsHookEx(hhk: HHook) -> bool;
+    fn DispatchMessageA(lp_msg: *const Msg) -> LResult;
+    fn DefWindowProcA(h_wnd: HWND, msg: c_uint, w_param: WParam, l_param: LParam) -> LResult;
+    fn RegisterClassA(lp_wnd_class: *const WndClassA) -> c_ushort;
+    #[allow(clippy::too_many_arguments)]
+    fn CreateWindowExA(
+        dw_ex_style: DWord,
+        lp_class_name: *const c_char,
+        lp_window_name: *const c_char,
+        dw_style: DWord,
+        x: c_int,
+        y: c_int,
+        n_width: c_int,
+        n_height: c_int,
+        h_wnd_parent: HWND,
+        h_menu: *mut c_void,
+        h_instance: HInstance,
+        lp_param: *mut c_void,
+    ) -> HWND;
+}
+
+// Kernel32 function definitions
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetModuleHandleA(lp_module_name: *const c_char) -> HInstance;
+    fn LoadLibraryA(lp_lib_file_name: *const c_char) -> HInstance;
+    fn GetProcAddress(h_module: HInstance, lp_proc_name: *const c_char) -> *mut c_void;
+    fn GetCurrentThreadId() -> DWord;
+    fn GetTickCount() -> DWord;
+    fn GetCurrentThread() -> *mut c_void;
+    fn SetThreadPriority(h_thread: *mut c_void, n_priority: c_int) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_inputs() {
+        let virtual_desktop = Monitor {
+            x: -1920,
+            y: 0,
+            width: 3841,
+            height: 1081,
+        };
+        let events = [
+            MouseEvent::AbsoluteMove(0, 540),
+            MouseEvent::Press(MouseButton::Left),
+            MouseEvent::RelativeMove(-20, 5),
+            MouseEvent::Release(MouseButton::Left),
+            MouseEvent::Scroll(ScrollDirection::Down),
+            MouseEvent::ScrollDelta(2, 1),
+        ];
+        let inputs: Vec<_> = super::batch_inputs(&events, &virtual_desktop)
+            .iter()
+            .map(|input| {
+                assert_eq!(input.r#type, INPUT_MOUSE);
+                (
+                    input.mi.dw_flags,
+                    input.mi.dx,
+                    input.mi.dy,
+                    input.mi.mouse_data,
+                )
+            })
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                (WindowsMouseEvent::VirtualDeskMove as DWord, 32768, 32768, 0),
+                (WindowsMouseEvent::LeftDown as DWord, 0, 0, 0),
+                (WindowsMouseEvent::Move as DWord, -20, 5, 0),
+                (WindowsMouseEvent::LeftUp as DWord, 0, 0, 0),
+                (WindowsMouseEvent::Wheel as DWord, 0, 0, -SCROLL_CLICK),
+                (WindowsMouseEvent::Wheel as DWord, 0, 0, SCROLL_CLICK),
+                (WindowsMouseEvent::HWheel as DWord, 0, 0, 2 * SCROLL_CLICK),
+            ]
+        );
+    }
+
+    #[test]
+    fn detent_input() {
+        let input = |direction, detents| {
+            let (event, mouse_data) = super::detent_input(&direction, detents);
+            (event as DWord, mouse_data)
+        };
+        let wheel = WindowsMouseEvent::Wheel as DWord;
+        let hwheel = WindowsMouseEvent::HWheel as DWord;
+        assert_eq!(input(ScrollDirection::Up, 1), (wheel, 120));
+        assert_eq!(input(ScrollDirection::Down, 3), (wheel, -360));
+        assert_eq!(input(ScrollDirection::Right, 2), (hwheel, 240));
+        assert_eq!(input(ScrollDirection::Left, -2), (hwheel, 240));
+    }
+
+    #[test]
+    fn raw_normalized_input() {
+        let input = super::raw_normalized_input(65535, 12345);
+        assert_eq!(input.r#type, INPUT_MOUSE);
+        assert_eq!(
+            (
+                input.mi.dx,
+                input.mi.dy,
+                input.mi.mouse_data,
+                input.mi.dw_flags
+            ),
+            (65535, 12345, 0, 0xC001)
+        );
+    }
+
+    #[test]
+    fn clip_covers() {
+        let virtual_desktop = Monitor {
+            x: -1920,
+            y: 0,
+            width: 3840,
+            height: 1080,
+        };
+        let clip = |left, top, right, bottom| Rect {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        assert!(super::clip_covers(
+            &clip(-1920, 0, 1920, 1080),
+            &virtual_desktop
+        ));
+        // Windows reports a larger clip when the cursor is not confined
+        assert!(super::clip_covers(
+            &clip(-32768, -32768, 32767, 32767),
+            &virtual_desktop
+        ));
+        assert!(!super::clip_covers(
+            &clip(0, 0, 1920, 1080),
+            &virtual_desktop
+        ));
+        assert!(!super::clip_covers(
+            &clip(-1920, 0, 1920, 1079),
+            &virtual_desktop
+        ));
+    }
+
+    #[test]
+    fn keyboard_input() {
+        assert_eq!(size_of::<KeyboardInput>(), size_of::<Input>());
+        let input = create_keyboard_input(&Modifier::Meta, false);
+        assert_eq!(input.r#type, INPUT_KEYBOARD);
+        assert_eq!(
+            (input.ki.w_vk, input.ki.dw_flags),
+            (VK_LWIN, KEYEVENTF_KEYUP)
+        );
+        assert_eq!(create_keyboard_input(&Modifier::Shift, true).ki.dw_flags, 0);
+    }
 }